@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS discord_mappings (
+    post_id BIGINT NOT NULL,
+    discord_message_id BIGINT NOT NULL,
+    discord_channel_id BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (post_id, discord_message_id)
+);
+
+CREATE INDEX IF NOT EXISTS discord_mappings_message_idx ON discord_mappings (discord_message_id);
+
+CREATE TABLE IF NOT EXISTS topic_threads (
+    topic_id BIGINT PRIMARY KEY,
+    discord_thread_id BIGINT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
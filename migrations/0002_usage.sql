@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS usage_counters (
+    month TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    value BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (month, metric)
+);
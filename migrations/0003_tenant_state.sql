@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS tenant_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    reason TEXT,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
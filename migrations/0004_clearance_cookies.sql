@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS clearance_cookies (
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    cookie TEXT NOT NULL,
+    expires_at TIMESTAMPTZ,
+    -- cf_clearance is only honoured alongside the UA that solved it
+    user_agent TEXT,
+    solved_url TEXT,
+    saved_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (name, url)
+);
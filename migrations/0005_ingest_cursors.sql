@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS ingest_cursors (
+    -- the forum's base url
+    source TEXT PRIMARY KEY,
+    last_post_id BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS http_cache (
+    url TEXT PRIMARY KEY,
+    status SMALLINT NOT NULL,
+    etag TEXT,
+    last_modified TEXT,
+    content_type TEXT,
+    body BYTEA NOT NULL,
+    stored_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS tenant_config (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    -- config::TenantConfig as json
+    config TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,12 @@
+-- lets config_watch::ConfigWatcher pick up edits, including ones made by hand
+CREATE OR REPLACE FUNCTION notify_tenant_config() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('tenant_config', '');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS tenant_config_changed ON tenant_config;
+CREATE TRIGGER tenant_config_changed
+    AFTER INSERT OR UPDATE ON tenant_config
+    FOR EACH ROW EXECUTE FUNCTION notify_tenant_config();
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS delivered_posts (
+    -- sha256 of (post_id, updated_at, cooked), see dedupe::delivery_key
+    key TEXT PRIMARY KEY,
+    post_id BIGINT NOT NULL,
+    delivered_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS delivered_posts_delivered_at_idx ON delivered_posts (delivered_at);
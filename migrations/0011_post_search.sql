@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS post_search (
+    post_id BIGINT PRIMARY KEY,
+    topic_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    -- the markdown that was mirrored, not the cooked html
+    markdown TEXT NOT NULL,
+    search TSVECTOR NOT NULL,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS post_search_search_idx ON post_search USING GIN (search);
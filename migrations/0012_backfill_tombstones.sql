@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS backfill_tombstones (
+    topic_id BIGINT NOT NULL,
+    post_number BIGINT NOT NULL,
+    -- only written once the forum confirmed the post is gone (404/410 or deleted_at)
+    confirmed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (topic_id, post_number)
+);
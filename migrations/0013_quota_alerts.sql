@@ -0,0 +1,7 @@
+-- one row per metric and month once its soft quota alert went out
+CREATE TABLE IF NOT EXISTS quota_alerts (
+    month TEXT NOT NULL,
+    metric TEXT NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (month, metric)
+);
@@ -0,0 +1,3 @@
+-- finds the oldest pending row of a channel when claiming
+CREATE INDEX IF NOT EXISTS outbox_channel_pending_idx ON outbox (discord_channel_id, id)
+    WHERE delivered_at IS NULL AND failed_at IS NULL;
@@ -0,0 +1,130 @@
+use discourse::model::post::Post;
+use reqwest::header::CONTENT_TYPE;
+use reqwest_middleware::ClientWithMiddleware;
+use serenity::all::{CreateAttachment, CreateEmbed};
+use tracing::warn;
+use url::Url;
+
+use crate::discord::extract_imgs_excluding_class;
+
+// Discord's upload limit for unboosted servers
+pub const DEFAULT_MAX_BYTES: usize = 10 * 1024 * 1024;
+const MAX_ATTACHMENTS: usize = 9;
+
+#[derive(Debug, Clone)]
+pub struct DownloadedAttachment {
+    pub source_url: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+impl DownloadedAttachment {
+    pub fn to_create_attachment(&self) -> CreateAttachment {
+        CreateAttachment::bytes(self.bytes.clone(), self.filename.clone())
+    }
+
+    pub fn attachment_url(&self) -> String {
+        format!("attachment://{}", self.filename)
+    }
+}
+
+fn absolute_url(src: &str, base_url: &str) -> Option<Url> {
+    if src.starts_with("//") {
+        return Url::parse(&format!("https:{src}")).ok();
+    }
+    match Url::parse(src) {
+        Ok(url) => Some(url),
+        Err(_) => Url::parse(base_url).ok()?.join(src).ok(),
+    }
+}
+
+fn filename_for(url: &Url, index: usize, content_type: Option<&str>) -> String {
+    let last = url
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("image");
+    // Discord wants unique, attachment://-safe names
+    let clean: String = last
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if clean.contains('.') {
+        return format!("{index}_{clean}");
+    }
+    let ext = match content_type {
+        Some("image/png") => "png",
+        Some("image/gif") => "gif",
+        Some("image/webp") => "webp",
+        _ => "jpg",
+    };
+    format!("{index}_{clean}.{ext}")
+}
+
+pub async fn download_attachment(
+    client: &ClientWithMiddleware,
+    url: &Url,
+    index: usize,
+    max_bytes: usize,
+) -> anyhow::Result<DownloadedAttachment> {
+    let resp = client.get(url.clone()).send().await?.error_for_status()?;
+    if let Some(len) = resp.content_length() {
+        if len as usize > max_bytes {
+            anyhow::bail!("{url} is {len} bytes, over the {max_bytes} byte limit");
+        }
+    }
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or(v).trim().to_string());
+    let bytes = resp.bytes().await?;
+    if bytes.len() > max_bytes {
+        anyhow::bail!(
+            "{url} is {} bytes, over the {max_bytes} byte limit",
+            bytes.len()
+        );
+    }
+    Ok(DownloadedAttachment {
+        source_url: url.to_string(),
+        filename: filename_for(url, index, content_type.as_deref()),
+        content_type,
+        bytes: bytes.to_vec(),
+    })
+}
+
+// images that fail to download are skipped, callers can fall back to hotlinking
+pub async fn download_post_images(
+    client: &ClientWithMiddleware,
+    post: &Post,
+    base_url: &str,
+    max_bytes: usize,
+) -> Vec<DownloadedAttachment> {
+    let mut ret = Vec::new();
+    let srcs = extract_imgs_excluding_class(&post.cooked, "avatar");
+    for (i, src) in srcs.iter().take(MAX_ATTACHMENTS).enumerate() {
+        let Some(url) = absolute_url(src, base_url) else {
+            continue;
+        };
+        match download_attachment(client, &url, i, max_bytes).await {
+            Ok(a) => ret.push(a),
+            Err(e) => warn!(url = %url, error = %e, "attachment download failed"),
+        }
+    }
+    ret
+}
+
+// same shape as get_images but pointing at the re-uploaded files
+pub fn attachment_embeds(url: &str, attachments: &[DownloadedAttachment]) -> Vec<CreateEmbed> {
+    attachments
+        .iter()
+        .map(|a| CreateEmbed::new().url(url).image(a.attachment_url()))
+        .collect()
+}
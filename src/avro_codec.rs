@@ -0,0 +1,87 @@
+use apache_avro::{Schema, from_avro_datum, from_value, to_avro_datum, to_value};
+use pulsar::{
+    DeserializeMessage, Error as PulsarError, Payload, SerializeMessage,
+    message::proto::{self, schema::Type},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::discord::{DiscordMapping, TopicThreadMapping};
+
+pub trait AvroSchema {
+    const NAME: &'static str;
+    const SCHEMA: &'static str;
+
+    fn schema() -> Schema {
+        Schema::parse_str(Self::SCHEMA).expect("built-in Avro schema must parse")
+    }
+}
+
+impl AvroSchema for DiscordMapping {
+    const NAME: &'static str = "DiscordMapping";
+    const SCHEMA: &'static str = r#"{
+        "type": "record",
+        "name": "DiscordMapping",
+        "namespace": "forum_stream",
+        "fields": [
+            {"name": "discord_message_id", "type": "string"},
+            {"name": "post_id", "type": "long"}
+        ]
+    }"#;
+}
+
+impl AvroSchema for TopicThreadMapping {
+    const NAME: &'static str = "TopicThreadMapping";
+    const SCHEMA: &'static str = r#"{
+        "type": "record",
+        "name": "TopicThreadMapping",
+        "namespace": "forum_stream",
+        "fields": [
+            {"name": "topic_id", "type": "long"},
+            {"name": "discord_thread_id", "type": "string"}
+        ]
+    }"#;
+}
+
+// pass to ProducerOptions/ConsumerBuilder so the broker's registry knows the schema
+pub fn pulsar_schema<T: AvroSchema>() -> proto::Schema {
+    proto::Schema {
+        name: T::NAME.to_string(),
+        schema_data: T::SCHEMA.as_bytes().to_vec(),
+        r#type: Type::Avro as i32,
+        properties: Vec::new(),
+    }
+}
+
+pub fn encode<T: AvroSchema + Serialize>(input: &T) -> Result<Vec<u8>, apache_avro::Error> {
+    let value = to_value(input)?;
+    to_avro_datum(&T::schema(), value)
+}
+
+pub fn decode<T: AvroSchema + DeserializeOwned>(data: &[u8]) -> Result<T, apache_avro::Error> {
+    let mut reader = data;
+    let value = from_avro_datum(&T::schema(), &mut reader, None)?;
+    from_value(&value)
+}
+
+// opt-in wrapper: produce/consume Avro<DiscordMapping> instead of the JSON impls
+#[derive(Debug)]
+pub struct Avro<T>(pub T);
+
+impl<T: AvroSchema + Serialize> SerializeMessage for Avro<T> {
+    fn serialize_message(input: Self) -> Result<pulsar::producer::Message, PulsarError> {
+        let payload = encode(&input.0).map_err(|e| PulsarError::Custom(e.to_string()))?;
+
+        Ok(pulsar::producer::Message {
+            payload,
+            ..Default::default()
+        })
+    }
+}
+
+impl<T: AvroSchema + DeserializeOwned> DeserializeMessage for Avro<T> {
+    type Output = Result<T, apache_avro::Error>;
+
+    fn deserialize_message(payload: &Payload) -> Self::Output {
+        decode(&payload.data)
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use discourse::bundle::PostData;
+use reqwest_middleware::ClientWithMiddleware;
+use serde_json::Value;
+use serenity::all::{CreateEmbed, CreateEmbedFooter};
+use sqlx::{Pool, Postgres};
+use tracing::warn;
+
+use crate::{
+    discord::{create_embeds, extract_imgs_excluding_class, get_link, get_post_content},
+    error::Result,
+    theme::{EmbedTheme, category_color},
+};
+
+const SHORT_POST_CHARS: usize = 280;
+const COALESCED_MAX_CHARS: usize = 1900;
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+
+fn is_short(post_data: &PostData) -> bool {
+    post_data.post.post_type == 1
+        && post_data.replying_to_post.is_none()
+        && extract_imgs_excluding_class(&post_data.post.cooked, "avatar").is_empty()
+        && get_post_content(post_data).chars().count() <= SHORT_POST_CHARS
+}
+
+fn coalesce(run: &[&PostData]) -> Option<CreateEmbed> {
+    let first = run.first()?;
+    let last = run.last()?;
+    let mut description = String::new();
+    for post_data in run {
+        let url = get_link(post_data, &post_data.base_url)?;
+        description.push_str(&format!(
+            "**{}** [#{}]({url}): {}\n",
+            post_data.post.username,
+            post_data.post.post_number,
+            get_post_content(post_data).trim()
+        ));
+    }
+    let mut embed = CreateEmbed::new()
+        .description(description)
+        .url(get_link(first, &first.base_url)?)
+        .footer(CreateEmbedFooter::new(format!(
+            "posts #{}–#{}",
+            first.post.post_number, last.post.post_number
+        )))
+        .timestamp(last.post.created_at);
+    let category = &first.category;
+    embed = embed.color(category_color(category.id, &category.color));
+    Some(embed)
+}
+
+// a topic's history as Discord messages (each up to 10 embeds), in post
+// order; `tombstones` are post numbers confirmed deleted, which are skipped
+// even if a stale copy is still in `posts`
+pub fn create_topic_backfill(
+    posts: &[PostData],
+    theme: &EmbedTheme,
+    tombstones: &HashSet<u64>,
+) -> Vec<Vec<CreateEmbed>> {
+    let mut ordered: Vec<&PostData> = posts
+        .iter()
+        .filter(|p| !tombstones.contains(&(p.post.post_number as u64)))
+        .collect();
+    ordered.sort_by_key(|p| p.post.post_number);
+
+    let mut embeds: Vec<Vec<CreateEmbed>> = Vec::new();
+    let mut run: Vec<&PostData> = Vec::new();
+    let mut run_chars = 0;
+
+    let flush = |run: &mut Vec<&PostData>, embeds: &mut Vec<Vec<CreateEmbed>>| {
+        match run.len() {
+            0 => {}
+            1 => {
+                if let Ok(e) = create_embeds(run[0], theme) {
+                    embeds.push(e);
+                }
+            }
+            _ => {
+                if let Some(e) = coalesce(run) {
+                    embeds.push(vec![e]);
+                }
+            }
+        }
+        run.clear();
+    };
+
+    for post_data in ordered {
+        if is_short(post_data) {
+            let chars = get_post_content(post_data).chars().count() + 64;
+            if run_chars + chars > COALESCED_MAX_CHARS {
+                flush(&mut run, &mut embeds);
+                run_chars = 0;
+            }
+            run.push(post_data);
+            run_chars += chars;
+        } else {
+            flush(&mut run, &mut embeds);
+            run_chars = 0;
+            if let Ok(e) = create_embeds(post_data, theme) {
+                embeds.push(e);
+            }
+        }
+    }
+    flush(&mut run, &mut embeds);
+
+    for message in embeds.iter_mut() {
+        message.truncate(MAX_EMBEDS_PER_MESSAGE);
+    }
+    embeds
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapStatus {
+    // the post exists, the gap was transient
+    Present,
+    // the API confirms the post number is gone
+    Deleted,
+    // couldn't tell (network error, rate limit), try again later
+    Unknown,
+}
+
+// post numbers missing between 1 and the highest seen
+pub fn find_gaps(post_numbers: &[u64]) -> Vec<u64> {
+    let mut seen: Vec<u64> = post_numbers.to_vec();
+    seen.sort_unstable();
+    seen.dedup();
+    let Some(&max) = seen.last() else {
+        return Vec::new();
+    };
+    (1..max)
+        .filter(|n| seen.binary_search(n).is_err())
+        .collect()
+}
+
+pub async fn confirm_deletion(
+    client: &ClientWithMiddleware,
+    base_url: &str,
+    topic_id: u64,
+    post_number: u64,
+) -> GapStatus {
+    let url = format!("{base_url}/posts/by_number/{topic_id}/{post_number}.json");
+    let resp = match client.get(&url).send().await {
+        Ok(r) => r,
+        Err(_) => return GapStatus::Unknown,
+    };
+    match resp.status().as_u16() {
+        404 | 410 => GapStatus::Deleted,
+        200 => match resp.json::<Value>().await {
+            // staff can still see soft-deleted posts
+            Ok(post) if post.get("deleted_at").is_some_and(|d| !d.is_null()) => GapStatus::Deleted,
+            Ok(_) => GapStatus::Present,
+            Err(_) => GapStatus::Unknown,
+        },
+        _ => GapStatus::Unknown,
+    }
+}
+
+#[async_trait::async_trait]
+pub trait TombstoneStore: Send + Sync {
+    async fn record(&self, topic_id: u64, post_number: u64) -> Result<()>;
+    async fn load(&self, topic_id: u64) -> Result<HashSet<u64>>;
+}
+
+#[derive(Default)]
+pub struct MemoryTombstoneStore {
+    tombstones: Mutex<HashSet<(u64, u64)>>,
+}
+
+#[async_trait::async_trait]
+impl TombstoneStore for MemoryTombstoneStore {
+    async fn record(&self, topic_id: u64, post_number: u64) -> Result<()> {
+        self.tombstones
+            .lock()
+            .unwrap()
+            .insert((topic_id, post_number));
+        Ok(())
+    }
+
+    async fn load(&self, topic_id: u64) -> Result<HashSet<u64>> {
+        let tombstones = self.tombstones.lock().unwrap();
+        Ok(tombstones
+            .iter()
+            .filter(|(t, _)| *t == topic_id)
+            .map(|(_, n)| *n)
+            .collect())
+    }
+}
+
+pub struct PgTombstoneStore {
+    pool: Pool<Postgres>,
+}
+
+impl PgTombstoneStore {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PgTombstoneStore { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TombstoneStore for PgTombstoneStore {
+    async fn record(&self, topic_id: u64, post_number: u64) -> Result<()> {
+        sqlx::query(
+            r#"INSERT INTO backfill_tombstones (topic_id, post_number) VALUES ($1, $2)
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(topic_id as i64)
+        .bind(post_number as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn load(&self, topic_id: u64) -> Result<HashSet<u64>> {
+        let rows: Vec<(i64,)> =
+            sqlx::query_as("SELECT post_number FROM backfill_tombstones WHERE topic_id = $1")
+                .bind(topic_id as i64)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows.into_iter().map(|(n,)| n as u64).collect())
+    }
+}
+
+pub struct GapTracker {
+    store: Box<dyn TombstoneStore>,
+    tombstones: HashSet<(u64, u64)>,
+    loaded: HashSet<u64>,
+    attempts: HashMap<(u64, u64), u32>,
+    max_attempts: u32,
+}
+
+impl GapTracker {
+    pub fn new(max_attempts: u32) -> Self {
+        GapTracker {
+            store: Box::new(MemoryTombstoneStore::default()),
+            tombstones: HashSet::new(),
+            loaded: HashSet::new(),
+            attempts: HashMap::new(),
+            max_attempts,
+        }
+    }
+
+    pub fn with_store(mut self, store: impl TombstoneStore + 'static) -> Self {
+        self.store = Box::new(store);
+        self
+    }
+
+    pub fn is_tombstoned(&self, topic_id: u64, post_number: u64) -> bool {
+        self.tombstones.contains(&(topic_id, post_number))
+    }
+
+    pub fn tombstones(&self) -> impl Iterator<Item = &(u64, u64)> {
+        self.tombstones.iter()
+    }
+
+    // confirmed deletions for one topic, for create_topic_backfill
+    pub async fn tombstones_for(&mut self, topic_id: u64) -> Result<HashSet<u64>> {
+        self.load(topic_id).await?;
+        Ok(self
+            .tombstones
+            .iter()
+            .filter(|(t, _)| *t == topic_id)
+            .map(|(_, n)| *n)
+            .collect())
+    }
+
+    async fn load(&mut self, topic_id: u64) -> Result<()> {
+        if self.loaded.contains(&topic_id) {
+            return Ok(());
+        }
+        for post_number in self.store.load(topic_id).await? {
+            self.tombstones.insert((topic_id, post_number));
+        }
+        self.loaded.insert(topic_id);
+        Ok(())
+    }
+
+    // only for posts the forum confirmed are gone
+    pub async fn record_tombstone(&mut self, topic_id: u64, post_number: u64) -> Result<()> {
+        self.store.record(topic_id, post_number).await?;
+        self.attempts.remove(&(topic_id, post_number));
+        self.tombstones.insert((topic_id, post_number));
+        Ok(())
+    }
+
+    // gaps that still need fetching, with confirmed deletions filtered out.
+    // A gap that keeps coming back Unknown is dropped for this run after
+    // max_attempts but never tombstoned, so a later run probes it again.
+    pub async fn resolve_gaps(
+        &mut self,
+        client: &ClientWithMiddleware,
+        base_url: &str,
+        topic_id: u64,
+        post_numbers: &[u64],
+    ) -> Result<Vec<u64>> {
+        self.load(topic_id).await?;
+        let mut pending = Vec::new();
+        for post_number in find_gaps(post_numbers) {
+            if self.is_tombstoned(topic_id, post_number) {
+                continue;
+            }
+            let attempts = self.attempts.get(&(topic_id, post_number)).copied();
+            if attempts.is_some_and(|a| a >= self.max_attempts) {
+                continue;
+            }
+            match confirm_deletion(client, base_url, topic_id, post_number).await {
+                GapStatus::Deleted => self.record_tombstone(topic_id, post_number).await?,
+                GapStatus::Present => pending.push(post_number),
+                GapStatus::Unknown => {
+                    let attempts = self.attempts.entry((topic_id, post_number)).or_insert(0);
+                    *attempts += 1;
+                    if *attempts >= self.max_attempts {
+                        warn!(
+                            topic_id,
+                            post_number,
+                            attempts = *attempts,
+                            "giving up on gap for now, deletion not confirmed"
+                        );
+                    } else {
+                        pending.push(post_number);
+                    }
+                }
+            }
+        }
+        Ok(pending)
+    }
+}
@@ -0,0 +1,202 @@
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use library::config::TenantConfig;
+use library::database::{
+    TenancyMode, TenantPoolConfig, archive_tenant, bootstrap_tenant, drop_tenant, open_tenant,
+};
+use library::fixtures::capture_fixture;
+use library::pause::set_paused;
+use library::preflight::{PreflightTenant, preflight};
+use library::template::TemplateContext;
+use library::usage::monthly_report;
+
+fn usage() -> ExitCode {
+    eprintln!("usage:");
+    eprintln!("  forum-stream preflight <name> <forum base url> <flaresolverr url>");
+    eprintln!("  forum-stream preflight --config <tenant.toml>");
+    eprintln!("  forum-stream config validate <template>");
+    eprintln!("  forum-stream usage <tenant db> [YYYY-MM]");
+    eprintln!("  forum-stream pause <tenant db> [reason]");
+    eprintln!("  forum-stream capture <forum base url> <post id> [fixtures dir] [--keep-authors]");
+    eprintln!("  forum-stream resume <tenant db>");
+    eprintln!(
+        "  forum-stream drop-tenant <tenant> [--archive <dump path>] [--schema-in <database>]"
+    );
+    #[cfg(feature = "preview-server")]
+    eprintln!("  forum-stream preview-server <listen addr>");
+    ExitCode::FAILURE
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("preflight") => {
+            let tenant = match args.as_slice() {
+                [_, flag, path] if flag == "--config" => {
+                    match TenantConfig::load(Some(Path::new(path)), "FORUM_STREAM_") {
+                        Ok(config) => match config.preflight_tenant() {
+                            Some(tenant) => tenant,
+                            None => {
+                                eprintln!("{path} has no flaresolverr_url");
+                                return ExitCode::FAILURE;
+                            }
+                        },
+                        Err(e) => {
+                            eprintln!("{e}");
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                [_, name, base_url, flaresolverr_url] => PreflightTenant {
+                    name: name.clone(),
+                    base_url: base_url.clone(),
+                    flaresolverr_url: flaresolverr_url.clone(),
+                },
+                _ => return usage(),
+            };
+            let report = preflight(&tenant).await;
+            print!("{report}");
+            if report.passed() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        Some("config") => match args.get(1).map(String::as_str) {
+            Some("validate") => {
+                let Some(template) = args.get(2) else {
+                    return usage();
+                };
+                config_validate(template)
+            }
+            _ => usage(),
+        },
+        Some("usage") => {
+            let Some(tenant) = args.get(1) else {
+                return usage();
+            };
+            match usage_report(tenant, args.get(2).map(String::as_str)).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(cmd @ ("pause" | "resume")) => {
+            let Some(tenant) = args.get(1) else {
+                return usage();
+            };
+            let reason = args.get(2).map(String::as_str);
+            match set_tenant_paused(tenant, cmd == "pause", reason).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some("drop-tenant") => {
+            let Some(tenant) = args.get(1) else {
+                return usage();
+            };
+            let mut archive = None;
+            let mut mode = TenancyMode::DatabasePerTenant;
+            for pair in args[2..].chunks(2) {
+                match pair {
+                    [flag, path] if flag == "--archive" => archive = Some(Path::new(path)),
+                    [flag, database] if flag == "--schema-in" => {
+                        mode = TenancyMode::SchemaPerTenant {
+                            database: database.clone(),
+                        }
+                    }
+                    _ => return usage(),
+                }
+            }
+            let result = match archive {
+                Some(path) => archive_tenant(tenant, path, &mode).await,
+                None => drop_tenant(tenant, &mode).await,
+            };
+            match result {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some("capture") => {
+            let (Some(base_url), Some(post_id)) = (args.get(1), args.get(2)) else {
+                return usage();
+            };
+            let Ok(post_id) = post_id.parse::<u64>() else {
+                return usage();
+            };
+            let dir = args
+                .get(3)
+                .filter(|a| !a.starts_with("--"))
+                .map(String::as_str)
+                .unwrap_or("fixtures");
+            let anonymize = !args.iter().any(|a| a == "--keep-authors");
+            let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new()).build();
+            match capture_fixture(&client, base_url, post_id, Path::new(dir), anonymize).await {
+                Ok(path) => {
+                    println!("wrote {}", path.display());
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        #[cfg(feature = "preview-server")]
+        Some("preview-server") => {
+            let addr = args.get(1).map(String::as_str).unwrap_or("127.0.0.1:3000");
+            match library::preview::serve(addr).await {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        _ => usage(),
+    }
+}
+
+async fn set_tenant_paused(tenant: &str, paused: bool, reason: Option<&str>) -> anyhow::Result<()> {
+    let pool = bootstrap_tenant(tenant.to_string(), &TenantPoolConfig::default()).await?;
+    set_paused(&pool, paused, reason).await?;
+    println!("{tenant} {}", if paused { "paused" } else { "resumed" });
+    Ok(())
+}
+
+async fn usage_report(tenant: &str, month: Option<&str>) -> anyhow::Result<()> {
+    let pool = open_tenant(tenant, &TenantPoolConfig::default()).await?;
+    let report = monthly_report(&pool, month).await?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+fn config_validate(template: &str) -> ExitCode {
+    println!("available placeholders:");
+    for var in TemplateContext::variables() {
+        println!(
+            "  {{{}}} - {} (e.g. {})",
+            var.name, var.description, var.example
+        );
+    }
+    let unknown = TemplateContext::unknown_placeholders(template);
+    if !unknown.is_empty() {
+        for name in unknown {
+            eprintln!("unknown placeholder: {{{name}}}");
+        }
+        return ExitCode::FAILURE;
+    }
+    println!("preview: {}", TemplateContext::fixture().render(template));
+    ExitCode::SUCCESS
+}
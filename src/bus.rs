@@ -0,0 +1,22 @@
+use tokio::sync::mpsc::{self, Receiver, Sender};
+
+// in-process stand-in for a Pulsar topic
+pub struct MemoryBus<T> {
+    sender: Sender<T>,
+    receiver: Receiver<T>,
+}
+
+impl<T> MemoryBus<T> {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        MemoryBus { sender, receiver }
+    }
+
+    pub fn publisher(&self) -> Sender<T> {
+        self.sender.clone()
+    }
+
+    pub fn split(self) -> (Sender<T>, Receiver<T>) {
+        (self.sender, self.receiver)
+    }
+}
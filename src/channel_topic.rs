@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serenity::all::{ChannelId, EditChannel, Http};
+use tracing::warn;
+
+use crate::utils::trim_to_n_chars;
+
+// Discord allows 2 name/topic edits per channel every 10 minutes
+const EDITS_PER_WINDOW: usize = 2;
+const EDIT_WINDOW: Duration = Duration::from_secs(600);
+const MAX_TOPIC_CHARS: usize = 1024;
+
+pub fn format_relative(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = (now - then).num_seconds().max(0);
+    match secs {
+        0..=59 => String::from("just now"),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86399 => format!("{} h ago", secs / 3600),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+pub fn format_channel_topic(
+    forum_host: &str,
+    breadcrumb: Option<&str>,
+    last_post_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> String {
+    let mut topic = format!("Bridged from {forum_host}");
+    if let Some(breadcrumb) = breadcrumb {
+        topic.push_str(&format!(" › {breadcrumb}"));
+    }
+    if let Some(last) = last_post_at {
+        topic.push_str(&format!(" — last post {}", format_relative(last, now)));
+    }
+    trim_to_n_chars(&topic, MAX_TOPIC_CHARS)
+}
+
+pub struct ChannelTopicUpdater {
+    http: Arc<Http>,
+    channel_id: ChannelId,
+    recent_edits: VecDeque<Instant>,
+    last_topic: Option<String>,
+}
+
+impl ChannelTopicUpdater {
+    pub fn new(http: Arc<Http>, channel_id: ChannelId) -> Self {
+        ChannelTopicUpdater {
+            http,
+            channel_id,
+            recent_edits: VecDeque::new(),
+            last_topic: None,
+        }
+    }
+
+    fn can_edit(&mut self, now: Instant) -> bool {
+        while let Some(front) = self.recent_edits.front() {
+            if now.duration_since(*front) >= EDIT_WINDOW {
+                self.recent_edits.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.recent_edits.len() < EDITS_PER_WINDOW
+    }
+
+    // Ok(false) when skipped because of the rate limit or an unchanged topic
+    pub async fn update(&mut self, topic: String) -> Result<bool, serenity::Error> {
+        if self.last_topic.as_deref() == Some(topic.as_str()) {
+            return Ok(false);
+        }
+        let now = Instant::now();
+        if !self.can_edit(now) {
+            return Ok(false);
+        }
+        self.channel_id
+            .edit(&self.http, EditChannel::new().topic(&topic))
+            .await?;
+        self.recent_edits.push_back(now);
+        self.last_topic = Some(topic);
+        Ok(true)
+    }
+
+    pub async fn run(
+        mut self,
+        interval: Duration,
+        mut next_topic: impl FnMut() -> Option<String> + Send,
+    ) {
+        // never poll faster than the edit budget allows
+        let interval = interval.max(EDIT_WINDOW / EDITS_PER_WINDOW as u32);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Some(topic) = next_topic() {
+                if let Err(e) = self.update(topic).await {
+                    warn!(channel_id = %self.channel_id, error = %e, "channel topic update failed");
+                }
+            }
+        }
+    }
+}
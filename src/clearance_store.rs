@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    error::ForumStreamError,
+    flaresolverr_middleware::{Solution, SolvedCookie},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PersistedCookie {
+    pub name: String,
+    pub cookie: String,
+    pub url: String,
+    // unix seconds
+    pub expires: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PersistedClearance {
+    pub cookies: Vec<PersistedCookie>,
+    pub user_agent: Option<String>,
+    pub solved_url: String,
+}
+
+impl PersistedClearance {
+    pub fn new(solution: &Solution, solved_url: &Url) -> Self {
+        PersistedClearance {
+            cookies: solution
+                .cookies
+                .iter()
+                .map(|c| PersistedCookie {
+                    name: c.name.clone(),
+                    cookie: c.cookie.clone(),
+                    url: c.url.to_string(),
+                    expires: c.expires.map(|e| e.timestamp()),
+                })
+                .collect(),
+            user_agent: solution.user_agent.clone(),
+            solved_url: solved_url.to_string(),
+        }
+    }
+
+    // None once cf_clearance has expired; the rest of the cookies are useless without it
+    pub fn into_solution(self, now: DateTime<Utc>) -> Option<(Solution, Url)> {
+        let solved_url = Url::parse(&self.solved_url).ok()?;
+        let cookies: Vec<SolvedCookie> = self
+            .cookies
+            .into_iter()
+            .filter_map(|c| {
+                let expires = match c.expires {
+                    Some(ts) => Some(Utc.timestamp_opt(ts, 0).single()?),
+                    None => None,
+                };
+                if expires.is_some_and(|e| e <= now) {
+                    return None;
+                }
+                Some(SolvedCookie {
+                    name: c.name,
+                    cookie: c.cookie,
+                    url: Url::parse(&c.url).ok()?,
+                    expires,
+                })
+            })
+            .collect();
+        if !cookies.iter().any(|c| c.name == "cf_clearance") {
+            return None;
+        }
+        let solution = Solution {
+            cookies,
+            user_agent: self.user_agent,
+        };
+        Some((solution, solved_url))
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ClearanceStore: Send + Sync {
+    async fn load(&self) -> Result<Option<PersistedClearance>, ForumStreamError>;
+    async fn save(&self, clearance: &PersistedClearance) -> Result<(), ForumStreamError>;
+}
+
+pub struct FileClearanceStore {
+    path: PathBuf,
+}
+
+impl FileClearanceStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileClearanceStore { path: path.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClearanceStore for FileClearanceStore {
+    async fn load(&self) -> Result<Option<PersistedClearance>, ForumStreamError> {
+        match tokio::fs::read(&self.path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, clearance: &PersistedClearance) -> Result<(), ForumStreamError> {
+        // write then rename so a crash mid-write can't leave a truncated file
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(clearance)?).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+        Ok(())
+    }
+}
+
+pub struct PgClearanceStore {
+    pool: Pool<Postgres>,
+}
+
+impl PgClearanceStore {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PgClearanceStore { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ClearanceStore for PgClearanceStore {
+    async fn load(&self) -> Result<Option<PersistedClearance>, ForumStreamError> {
+        let rows: Vec<(String, String, String, Option<i64>, Option<String>, Option<String>)> =
+            sqlx::query_as(
+                r#"SELECT name, url, cookie, extract(epoch FROM expires_at)::BIGINT, user_agent, solved_url
+                   FROM clearance_cookies"#,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+        let Some(first) = rows.first() else {
+            return Ok(None);
+        };
+        let user_agent = first.4.clone();
+        let solved_url = first.5.clone().unwrap_or_default();
+        let cookies = rows
+            .into_iter()
+            .map(|(name, url, cookie, expires, _, _)| PersistedCookie {
+                name,
+                cookie,
+                url,
+                expires,
+            })
+            .collect();
+        Ok(Some(PersistedClearance {
+            cookies,
+            user_agent,
+            solved_url,
+        }))
+    }
+
+    async fn save(&self, clearance: &PersistedClearance) -> Result<(), ForumStreamError> {
+        // a solve replaces the whole set; stale cookies from an older UA must not linger
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM clearance_cookies")
+            .execute(&mut *tx)
+            .await?;
+        for c in &clearance.cookies {
+            sqlx::query(
+                r#"INSERT INTO clearance_cookies (name, url, cookie, expires_at, user_agent, solved_url)
+                   VALUES ($1, $2, $3, to_timestamp($4), $5, $6)
+                   ON CONFLICT (name, url) DO UPDATE
+                   SET cookie = EXCLUDED.cookie, expires_at = EXCLUDED.expires_at"#,
+            )
+            .bind(&c.name)
+            .bind(&c.url)
+            .bind(&c.cookie)
+            .bind(c.expires.map(|e| e as f64))
+            .bind(&clearance.user_agent)
+            .bind(&clearance.solved_url)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use discourse::bundle::PostData;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serenity::all::ChannelId;
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    content_filter::ContentFilterConfig,
+    database::TenantPoolConfig,
+    error::{ForumStreamError, Result},
+    filter::PostFilter,
+    formatter::WebhookFormatter,
+    language::LanguageRouting,
+    metadata::MetadataCache,
+    notifier::{DEFAULT_NTFY_URL, Notifier},
+    preflight::PreflightTenant,
+    router::Router,
+    theme::EmbedTheme,
+    webhook::{IdentityRules, webhook_username},
+};
+
+// everything one tenant needs, in one place instead of a handful of strings
+// handed around separately
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TenantConfig {
+    pub name: String,
+    pub base_url: String,
+    // checked first; see router::RouteRule
+    pub router: Router,
+    // by category id; anything not listed goes to default_channel
+    pub channels: HashMap<u64, ChannelId>,
+    pub default_channel: Option<ChannelId>,
+    // takes precedence over the category map when a route matches
+    pub language_routing: Option<LanguageRouting>,
+    pub filter: PostFilter,
+    // compile with ContentFilter::compile once per load
+    pub content_filter: ContentFilterConfig,
+    pub theme: EmbedTheme,
+    // webhook identities pinned per category, e.g. announcements posted as
+    // the forum itself instead of the author
+    pub identities: IdentityRules,
+    pub flaresolverr_url: Option<String>,
+    pub ntfy_url: Option<String>,
+    pub ntfy_topic: Option<String>,
+    pub pool: TenantPoolConfig,
+}
+
+fn env_var(prefix: &str, name: &str) -> Option<String> {
+    std::env::var(format!("{prefix}{name}"))
+        .ok()
+        .filter(|v| !v.is_empty())
+}
+
+impl TenantConfig {
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(|e| ForumStreamError::Config(e.to_string()))
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    // `{prefix}NAME`, `BASE_URL`, `FLARESOLVERR_URL`, `NTFY_URL`,
+    // `NTFY_TOPIC` and `DEFAULT_CHANNEL` override what the file set
+    pub fn apply_env(&mut self, prefix: &str) -> Result<()> {
+        if let Some(name) = env_var(prefix, "NAME") {
+            self.name = name;
+        }
+        if let Some(base_url) = env_var(prefix, "BASE_URL") {
+            self.base_url = base_url;
+        }
+        if let Some(url) = env_var(prefix, "FLARESOLVERR_URL") {
+            self.flaresolverr_url = Some(url);
+        }
+        if let Some(url) = env_var(prefix, "NTFY_URL") {
+            self.ntfy_url = Some(url);
+        }
+        if let Some(topic) = env_var(prefix, "NTFY_TOPIC") {
+            self.ntfy_topic = Some(topic);
+        }
+        if let Some(channel) = env_var(prefix, "DEFAULT_CHANNEL") {
+            let id = channel.parse::<u64>().map_err(|_| {
+                ForumStreamError::Config(format!("{prefix}DEFAULT_CHANNEL is not a channel id"))
+            })?;
+            self.default_channel = Some(ChannelId::new(id));
+        }
+        Ok(())
+    }
+
+    // the file if given, then the environment on top
+    pub fn load(path: Option<&Path>, env_prefix: &str) -> Result<Self> {
+        let mut config = match path {
+            Some(path) => Self::from_file(path)?,
+            None => TenantConfig::default(),
+        };
+        config.apply_env(env_prefix)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(ForumStreamError::Config(String::from(
+                "tenant name is empty",
+            )));
+        }
+        Url::parse(&self.base_url)
+            .map_err(|e| ForumStreamError::Config(format!("base_url `{}`: {e}", self.base_url)))?;
+        if let Some(url) = &self.flaresolverr_url {
+            Url::parse(url)
+                .map_err(|e| ForumStreamError::Config(format!("flaresolverr_url `{url}`: {e}")))?;
+        }
+        if self.router.rules.is_empty()
+            && self.channels.is_empty()
+            && self.default_channel.is_none()
+            && self.language_routing.is_none()
+        {
+            return Err(ForumStreamError::Config(format!(
+                "tenant `{}` has no channels to post to",
+                self.name
+            )));
+        }
+        for rule in &self.identities.rules {
+            if webhook_username(&rule.identity.username).is_none() {
+                return Err(ForumStreamError::Config(format!(
+                    "identity for `{}`: `{}` isn't a usable webhook username",
+                    rule.category, rule.identity.username
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn channel_for(&self, category_id: u64) -> Option<ChannelId> {
+        self.channels
+            .get(&category_id)
+            .copied()
+            .or(self.default_channel)
+    }
+
+    // routing rules, then language routing, then the category map; empty
+    // means the post has nowhere to go
+    pub fn channels_for(
+        &self,
+        post_data: &PostData,
+        cache: Option<&MetadataCache>,
+    ) -> Vec<ChannelId> {
+        let routed = self.router.route(post_data, cache);
+        if !routed.is_empty() {
+            return routed;
+        }
+        if let Some(channel) = self
+            .language_routing
+            .as_ref()
+            .and_then(|r| r.route_post(post_data))
+        {
+            return vec![channel];
+        }
+        self.channel_for(post_data.category.id)
+            .into_iter()
+            .collect()
+    }
+
+    pub fn webhook_formatter(&self, impersonate: bool) -> WebhookFormatter {
+        WebhookFormatter {
+            theme: self.theme.clone(),
+            impersonate,
+            identities: self.identities.clone(),
+        }
+    }
+
+    pub fn preflight_tenant(&self) -> Option<PreflightTenant> {
+        Some(PreflightTenant {
+            name: self.name.clone(),
+            base_url: self.base_url.clone(),
+            flaresolverr_url: self.flaresolverr_url.clone()?,
+        })
+    }
+
+    // None when no topic is configured; alerts then go wherever the global
+    // notifier (NTFY_* env) sends them
+    pub fn notifier(&self) -> Option<Notifier> {
+        let topic = self.ntfy_topic.as_deref()?;
+        let url = self.ntfy_url.as_deref().unwrap_or(DEFAULT_NTFY_URL);
+        Some(Notifier::new(url, topic))
+    }
+
+    // from the tenant's own database, see migrations/0007_tenant_config.sql
+    pub async fn load_from_db(pool: &Pool<Postgres>) -> Result<Option<Self>> {
+        let config: Option<String> =
+            sqlx::query_scalar("SELECT config FROM tenant_config WHERE id")
+                .fetch_optional(pool)
+                .await?;
+        match config {
+            Some(config) => Ok(Some(serde_json::from_str(&config)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn save(&self, pool: &Pool<Postgres>) -> Result<()> {
+        sqlx::query(
+            r#"INSERT INTO tenant_config (id, config, updated_at)
+               VALUES (TRUE, $1, now())
+               ON CONFLICT (id) DO UPDATE SET config = $1, updated_at = now()"#,
+        )
+        .bind(serde_json::to_string(self)?)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use sqlx::postgres::PgListener;
+use sqlx::{Pool, Postgres};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::{config::TenantConfig, error::Result};
+
+// see migrations/0008_tenant_config_notify.sql
+pub const NOTIFY_CHANNEL: &str = "tenant_config";
+
+// holds the live TenantConfig; tasks either call current() per event or
+// subscribe() to react to changes. A config that fails to load or validate
+// is logged and the previous one stays in effect.
+pub struct ConfigWatcher {
+    config: watch::Sender<Arc<TenantConfig>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(initial: TenantConfig) -> Self {
+        let (config, _) = watch::channel(Arc::new(initial));
+        ConfigWatcher { config }
+    }
+
+    pub fn current(&self) -> Arc<TenantConfig> {
+        self.config.borrow().clone()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<TenantConfig>> {
+        self.config.subscribe()
+    }
+
+    pub fn update(&self, config: TenantConfig) -> Result<()> {
+        config.validate()?;
+        info!(tenant = %config.name, "tenant config reloaded");
+        self.config.send_replace(Arc::new(config));
+        Ok(())
+    }
+
+    // re-reads `path` (plus `{env_prefix}*` overrides) whenever its mtime
+    // changes; polled, so editors that replace the file are handled too
+    pub fn spawn_file_watch(
+        self: &Arc<Self>,
+        path: impl Into<PathBuf>,
+        env_prefix: &str,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        let path = path.into();
+        let env_prefix = env_prefix.to_string();
+        tokio::spawn(async move {
+            let modified = |path: &PathBuf| -> Option<SystemTime> {
+                std::fs::metadata(path).and_then(|m| m.modified()).ok()
+            };
+            let mut last = modified(&path);
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let current = modified(&path);
+                if current.is_none() || current == last {
+                    continue;
+                }
+                last = current;
+                let result = TenantConfig::load(Some(&path), &env_prefix)
+                    .and_then(|config| this.update(config));
+                if let Err(e) = result {
+                    warn!(path = %path.display(), error = %e, "keeping previous tenant config");
+                }
+            }
+        })
+    }
+
+    // reloads from the tenant_config table on every NOTIFY; PgListener
+    // reconnects on its own, a notification missed while disconnected is
+    // covered by reloading after each reconnect
+    pub fn spawn_pg_listen(self: &Arc<Self>, pool: Pool<Postgres>) -> tokio::task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut listener = loop {
+                match PgListener::connect_with(&pool).await {
+                    Ok(mut listener) => match listener.listen(NOTIFY_CHANNEL).await {
+                        Ok(()) => break listener,
+                        Err(e) => warn!(error = %e, "could not LISTEN for config changes"),
+                    },
+                    Err(e) => warn!(error = %e, "could not connect config listener"),
+                }
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            };
+            loop {
+                match listener.try_recv().await {
+                    // Ok(None) is a dropped connection that has been re-established
+                    Ok(_) => this.reload_from_db(&pool).await,
+                    Err(e) => {
+                        warn!(error = %e, "config listener failed");
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        })
+    }
+
+    async fn reload_from_db(&self, pool: &Pool<Postgres>) {
+        match TenantConfig::load_from_db(pool).await {
+            Ok(Some(config)) => {
+                if let Err(e) = self.update(config) {
+                    warn!(error = %e, "keeping previous tenant config");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "could not load tenant config"),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ForumStreamError;
+
+pub const REDACTED: &str = "[redacted]";
+
+// ordered least to most restrictive; when several rules match the strictest wins
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentAction {
+    // keep the post but hide it behind a spoiler with the rule name
+    #[default]
+    Flag,
+    // replace just the matching text
+    Redact,
+    Drop,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ContentRule {
+    // shown on flagged posts and in logs
+    pub name: String,
+    // whole words, case-insensitive
+    pub keywords: Vec<String>,
+    pub regexes: Vec<String>,
+    pub action: ContentAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ContentFilterConfig {
+    pub rules: Vec<ContentRule>,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    name: String,
+    action: ContentAction,
+    pattern: Regex,
+}
+
+// ContentFilterConfig with every pattern compiled up front; build it once per
+// config and share it
+#[derive(Debug)]
+pub struct ContentFilter {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentVerdict {
+    // None when no rule matched
+    pub action: Option<ContentAction>,
+    // with Redact rules applied, and spoilered when flagged
+    pub markdown: String,
+    pub matched: Vec<String>,
+}
+
+impl ContentVerdict {
+    pub fn is_dropped(&self) -> bool {
+        self.action == Some(ContentAction::Drop)
+    }
+}
+
+impl ContentFilter {
+    pub fn compile(config: &ContentFilterConfig) -> Result<Self, ForumStreamError> {
+        let mut rules = Vec::new();
+        for rule in &config.rules {
+            let mut alternatives: Vec<String> = rule
+                .keywords
+                .iter()
+                .filter(|k| !k.trim().is_empty())
+                .map(|k| format!(r"\b{}\b", regex::escape(k.trim())))
+                .collect();
+            for pattern in &rule.regexes {
+                // checked on its own so the error names the bad pattern
+                Regex::new(pattern).map_err(|e| {
+                    ForumStreamError::Config(format!("content rule `{}`: {e}", rule.name))
+                })?;
+                alternatives.push(format!("(?:{pattern})"));
+            }
+            if alternatives.is_empty() {
+                continue;
+            }
+            let pattern = RegexBuilder::new(&alternatives.join("|"))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| {
+                    ForumStreamError::Config(format!("content rule `{}`: {e}", rule.name))
+                })?;
+            rules.push(CompiledRule {
+                name: rule.name.clone(),
+                action: rule.action,
+                pattern,
+            });
+        }
+        Ok(ContentFilter { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    // run on the rendered markdown, before it goes into an embed
+    pub fn apply(&self, markdown: &str) -> ContentVerdict {
+        let mut action = None;
+        let mut matched = Vec::new();
+        let mut text = markdown.to_string();
+        for rule in &self.rules {
+            if !rule.pattern.is_match(&text) {
+                continue;
+            }
+            matched.push(rule.name.clone());
+            action = action.max(Some(rule.action));
+            if rule.action == ContentAction::Redact {
+                text = rule.pattern.replace_all(&text, REDACTED).into_owned();
+            }
+        }
+        // only Flag rules matched
+        if action == Some(ContentAction::Flag) {
+            // "||" inside the text would close the spoiler early
+            text = format!(
+                "⚠ Flagged: {}\n||{}||",
+                matched.join(", "),
+                text.replace("||", "|\u{200b}|")
+            );
+        }
+        ContentVerdict {
+            action,
+            markdown: text,
+            matched,
+        }
+    }
+}
@@ -1,9 +1,88 @@
+use std::collections::HashMap;
 use std::env;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use sqlx::{Connection, Executor, PgConnection, Pool};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use sqlx::{migrate::Migrator, Connection, Executor, PgConnection, Pool};
+use tokio::sync::{Mutex, OnceCell};
+use tracing::info;
 
-pub async fn bootstrap_tenant(db_name: String) -> anyhow::Result<Pool<sqlx::Postgres>> {
-    let admin_url = env::var("PG_ADMIN_URL").expect("PG_ADMIN_URL must be set");
+use crate::error::{ForumStreamError, Result};
+use crate::metrics::record_pool_usage;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TenancyMode {
+    #[default]
+    DatabasePerTenant,
+    // lighter option for small deployments: one database, a schema per tenant
+    SchemaPerTenant { database: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TenantPoolConfig {
+    pub mode: TenancyMode,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+    pub idle_timeout_secs: Option<u64>,
+    pub statement_cache_capacity: usize,
+}
+
+impl Default for TenantPoolConfig {
+    fn default() -> Self {
+        TenantPoolConfig {
+            mode: TenancyMode::DatabasePerTenant,
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+            idle_timeout_secs: Some(600),
+            statement_cache_capacity: 100,
+        }
+    }
+}
+
+impl TenantPoolConfig {
+    pub async fn connect(&self, url: &str) -> Result<Pool<sqlx::Postgres>> {
+        self.connect_with_search_path(url, None).await
+    }
+
+    async fn connect_with_search_path(
+        &self,
+        url: &str,
+        search_path: Option<&str>,
+    ) -> Result<Pool<sqlx::Postgres>> {
+        let mut options = PgConnectOptions::from_str(url)?
+            .statement_cache_capacity(self.statement_cache_capacity);
+        if let Some(schema) = search_path {
+            options = options.options([("search_path", quote_ident(schema))]);
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+            .idle_timeout(self.idle_timeout_secs.map(Duration::from_secs))
+            .connect_with(options)
+            .await?;
+        Ok(pool)
+    }
+}
+
+#[tracing::instrument(skip(config), fields(mode = ?config.mode))]
+pub async fn bootstrap_tenant(
+    db_name: String,
+    config: &TenantPoolConfig,
+) -> Result<Pool<sqlx::Postgres>> {
+    if let TenancyMode::SchemaPerTenant { database } = &config.mode {
+        return bootstrap_tenant_schema(database, &db_name, config).await;
+    }
+
+    let admin_url = admin_url()?;
 
     let mut admin_conn: PgConnection = Connection::connect(&admin_url).await?;
 
@@ -14,7 +93,7 @@ pub async fn bootstrap_tenant(db_name: String) -> anyhow::Result<Pool<sqlx::Post
             .await?;
 
     if !exists {
-        println!("Creating tenant database: {}", db_name);
+        info!(tenant = %db_name, "creating tenant database");
 
         let create_sql = format!(r#"CREATE DATABASE "{}" WITH TEMPLATE template"#, db_name);
 
@@ -22,14 +101,349 @@ pub async fn bootstrap_tenant(db_name: String) -> anyhow::Result<Pool<sqlx::Post
     }
 
     let tenant_url = format!("{}/{}", base_url_without_db(&admin_url)?, db_name);
-    let pool = Pool::connect(&tenant_url).await?;
+    let pool = config.connect(&tenant_url).await?;
+    migrate_tenant(&pool).await?;
+
+    Ok(pool)
+}
+
+// a pool for a tenant that must already exist; unlike bootstrap_tenant it
+// never creates or migrates anything, for read-only tools
+#[tracing::instrument(skip(config), fields(mode = ?config.mode))]
+pub async fn open_tenant(
+    db_name: &str,
+    config: &TenantPoolConfig,
+) -> Result<Pool<sqlx::Postgres>> {
+    let admin_url = admin_url()?;
+    match &config.mode {
+        TenancyMode::DatabasePerTenant => {
+            let mut admin_conn: PgConnection = Connection::connect(&admin_url).await?;
+            let exists: bool =
+                sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)")
+                    .bind(db_name)
+                    .fetch_one(&mut admin_conn)
+                    .await?;
+            if !exists {
+                return Err(ForumStreamError::Data(format!("no tenant database {db_name}")));
+            }
+            let tenant_url = format!("{}/{}", base_url_without_db(&admin_url)?, db_name);
+            config.connect(&tenant_url).await
+        }
+        TenancyMode::SchemaPerTenant { database } => {
+            let url = format!("{}/{}", base_url_without_db(&admin_url)?, database);
+            let mut conn: PgConnection = Connection::connect(&url).await?;
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM information_schema.schemata WHERE schema_name = $1)",
+            )
+            .bind(db_name)
+            .fetch_one(&mut conn)
+            .await?;
+            if !exists {
+                return Err(ForumStreamError::Data(format!(
+                    "no tenant schema {db_name} in {database}"
+                )));
+            }
+            config.connect_with_search_path(&url, Some(db_name)).await
+        }
+    }
+}
+
+async fn bootstrap_tenant_schema(
+    database: &str,
+    schema: &str,
+    config: &TenantPoolConfig,
+) -> Result<Pool<sqlx::Postgres>> {
+    let admin_url = admin_url()?;
+    let url = format!("{}/{}", base_url_without_db(&admin_url)?, database);
+
+    let mut conn: PgConnection = Connection::connect(&url).await?;
+    let create_sql = format!("CREATE SCHEMA IF NOT EXISTS {}", quote_ident(schema));
+    conn.execute(&*create_sql).await?;
+
+    // migrations (and their bookkeeping table) land in the tenant's schema
+    let pool = config.connect_with_search_path(&url, Some(schema)).await?;
+    migrate_tenant(&pool).await?;
 
     Ok(pool)
 }
 
-fn base_url_without_db(url: &str) -> anyhow::Result<String> {
+pub async fn drop_tenant_schema(database: &str, schema: &str) -> Result<()> {
+    let admin_url = admin_url()?;
+    let url = format!("{}/{}", base_url_without_db(&admin_url)?, database);
+    let mut conn: PgConnection = Connection::connect(&url).await?;
+    let drop_sql = format!("DROP SCHEMA IF EXISTS {} CASCADE", quote_ident(schema));
+    conn.execute(&*drop_sql).await?;
+    Ok(())
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+async fn terminate_connections(admin_conn: &mut PgConnection, db_name: &str) -> Result<()> {
+    // block new connections first so nothing sneaks in between terminate and drop
+    let disallow = format!(
+        "ALTER DATABASE {} WITH ALLOW_CONNECTIONS false",
+        quote_ident(db_name)
+    );
+    admin_conn.execute(&*disallow).await?;
+    sqlx::query(
+        "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE datname = $1 AND pid <> pg_backend_pid()",
+    )
+    .bind(db_name)
+    .execute(&mut *admin_conn)
+    .await?;
+    Ok(())
+}
+
+// in SchemaPerTenant mode only the tenant's schema is dropped, never the
+// shared database
+#[tracing::instrument]
+pub async fn drop_tenant(db_name: &str, mode: &TenancyMode) -> Result<()> {
+    if let TenancyMode::SchemaPerTenant { database } = mode {
+        info!(tenant = %db_name, %database, "dropping tenant schema");
+        return drop_tenant_schema(database, db_name).await;
+    }
+    let admin_url = admin_url()?;
+    let mut admin_conn: PgConnection = Connection::connect(&admin_url).await?;
+
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(db_name)
+            .fetch_one(&mut admin_conn)
+            .await?;
+    if !exists {
+        return Ok(());
+    }
+
+    info!(tenant = %db_name, "dropping tenant database");
+    terminate_connections(&mut admin_conn, db_name).await?;
+    let drop_sql = format!("DROP DATABASE {}", quote_ident(db_name));
+    admin_conn.execute(&*drop_sql).await?;
+    Ok(())
+}
+
+// pg_dump (custom format) to dump_path, then drop; the database is kept if the dump fails
+#[tracing::instrument]
+pub async fn archive_tenant(db_name: &str, dump_path: &Path, mode: &TenancyMode) -> Result<()> {
+    let admin_url = admin_url()?;
+
+    let mut command = match mode {
+        TenancyMode::DatabasePerTenant => pg_dump_command(&admin_url, db_name)?,
+        TenancyMode::SchemaPerTenant { database } => {
+            // quoted so the name is matched exactly rather than as a pattern
+            let mut command = pg_dump_command(&admin_url, database)?;
+            command.arg("--schema").arg(quote_ident(db_name));
+            command
+        }
+    };
+    let status = command
+        .arg("--format=custom")
+        .arg("--file")
+        .arg(dump_path)
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(ForumStreamError::Data(format!(
+            "pg_dump of {} failed with {}",
+            db_name, status
+        )));
+    }
+
+    drop_tenant(db_name, mode).await
+}
+
+// pg_dump against `database` on the admin server. The password goes in
+// PGPASSWORD rather than the connection string, which would put it in argv
+// where any local user can read it from the process list
+fn pg_dump_command(admin_url: &str, database: &str) -> Result<tokio::process::Command> {
+    let mut url = url::Url::parse(admin_url)
+        .map_err(|_| ForumStreamError::Config(String::from("Invalid PG_ADMIN_URL")))?;
+    let password = url.password().map(percent_decode);
+    let _ = url.set_password(None);
+    url.set_path(database);
+
+    let mut command = tokio::process::Command::new("pg_dump");
+    if let Some(password) = password {
+        command.env("PGPASSWORD", password);
+    }
+    command.arg("--dbname").arg(url.as_str());
+    Ok(command)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+#[tracing::instrument(skip_all)]
+pub async fn migrate_tenant(pool: &Pool<sqlx::Postgres>) -> Result<()> {
+    MIGRATOR.run(pool).await?;
+    Ok(())
+}
+
+fn admin_url() -> Result<String> {
+    env::var("PG_ADMIN_URL")
+        .map_err(|_| ForumStreamError::Config(String::from("PG_ADMIN_URL must be set")))
+}
+
+fn base_url_without_db(url: &str) -> Result<String> {
     let idx = url
         .rfind('/')
-        .ok_or_else(|| anyhow::anyhow!("Invalid PG_ADMIN_URL"))?;
+        .ok_or_else(|| ForumStreamError::Config(String::from("Invalid PG_ADMIN_URL")))?;
     Ok(url[..idx].to_string())
-}
\ No newline at end of file
+}
+
+struct CachedPool {
+    // initialized by whichever caller gets there first, outside the map lock
+    pool: Arc<OnceCell<Pool<sqlx::Postgres>>>,
+    last_used: Instant,
+}
+
+pub struct TenantManager {
+    pools: Mutex<HashMap<String, CachedPool>>,
+    idle_timeout: Duration,
+    pool_config: TenantPoolConfig,
+    overrides: HashMap<String, TenantPoolConfig>,
+}
+
+impl TenantManager {
+    pub fn new(idle_timeout: Duration, pool_config: TenantPoolConfig) -> Self {
+        TenantManager {
+            pools: Mutex::new(HashMap::new()),
+            idle_timeout,
+            pool_config,
+            overrides: HashMap::new(),
+        }
+    }
+
+    // high-traffic tenants can get a bigger pool than the default
+    pub fn with_override(mut self, tenant: &str, config: TenantPoolConfig) -> Self {
+        self.overrides.insert(tenant.to_string(), config);
+        self
+    }
+
+    pub async fn get_or_create(&self, tenant: &str) -> Result<Pool<sqlx::Postgres>> {
+        // the map lock only hands out the tenant's cell; bootstrapping happens
+        // on the cell so a slow tenant doesn't hold up the others
+        let cell = {
+            let mut pools = self.pools.lock().await;
+            let cached = pools.entry(tenant.to_string()).or_insert_with(|| CachedPool {
+                pool: Arc::new(OnceCell::new()),
+                last_used: Instant::now(),
+            });
+            if cached.pool.get().is_some_and(|pool| pool.is_closed()) {
+                cached.pool = Arc::new(OnceCell::new());
+            }
+            cached.last_used = Instant::now();
+            Arc::clone(&cached.pool)
+        };
+        let config = self.overrides.get(tenant).unwrap_or(&self.pool_config);
+        let result = cell
+            .get_or_try_init(|| bootstrap_tenant(tenant.to_string(), config))
+            .await;
+
+        let mut pools = self.pools.lock().await;
+        let current = pools
+            .get(tenant)
+            .is_some_and(|cached| Arc::ptr_eq(&cached.pool, &cell));
+        match result {
+            Ok(pool) if current => Ok(pool.clone()),
+            Ok(pool) => {
+                // remove() or eviction dropped the entry mid-bootstrap, nobody
+                // else will close this pool
+                let pool = pool.clone();
+                drop(pools);
+                pool.close().await;
+                Err(ForumStreamError::Data(format!(
+                    "tenant {tenant} was removed while its pool was being created"
+                )))
+            }
+            Err(e) => {
+                // drop the empty cell, but only if it's still ours; a newer
+                // entry may already be bootstrapping
+                if current && cell.get().is_none() {
+                    pools.remove(tenant);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn evict_idle(&self) -> usize {
+        let mut idle = Vec::new();
+        {
+            let mut pools = self.pools.lock().await;
+            pools.retain(|_, cached| {
+                // still bootstrapping, not idle
+                let Some(pool) = cached.pool.get() else {
+                    return true;
+                };
+                if cached.last_used.elapsed() >= self.idle_timeout {
+                    idle.push(pool.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        let count = idle.len();
+        if count > 0 {
+            info!(count, "evicting idle tenant pools");
+        }
+        for pool in idle {
+            pool.close().await;
+        }
+        count
+    }
+
+    pub async fn remove(&self, tenant: &str) {
+        let removed = self.pools.lock().await.remove(tenant);
+        if let Some(pool) = removed.as_ref().and_then(|cached| cached.pool.get()) {
+            pool.close().await;
+        }
+    }
+
+    pub async fn report_metrics(&self) {
+        let pools = self.pools.lock().await;
+        for (tenant, cached) in pools.iter() {
+            if let Some(pool) = cached.pool.get() {
+                record_pool_usage(tenant, pool.size(), pool.num_idle());
+            }
+        }
+    }
+
+    pub async fn tenants(&self) -> Vec<String> {
+        self.pools.lock().await.keys().cloned().collect()
+    }
+
+    pub fn spawn_evictor(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                this.evict_idle().await;
+                this.report_metrics().await;
+            }
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use discourse::bundle::PostData;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+
+use crate::error::Result;
+
+// the same post at the same revision hashes the same no matter which path
+// (Pulsar redelivery, webhook retry, poller) brought it in; an edit changes
+// updated_at and so is delivered again
+pub fn delivery_key(post_data: &PostData) -> String {
+    let post = &post_data.post;
+    let mut hasher = Sha256::new();
+    hasher.update(post.id.to_string().as_bytes());
+    hasher.update([0]);
+    hasher.update(post.updated_at.to_rfc3339().as_bytes());
+    hasher.update([0]);
+    hasher.update(post.cooked.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+#[async_trait::async_trait]
+pub trait DedupeStore: Send + Sync {
+    // true if `key` wasn't there before; must be atomic so two workers
+    // racing on one message can't both win
+    async fn insert(&self, key: &str, post_id: i64) -> Result<bool>;
+    async fn remove(&self, key: &str) -> Result<()>;
+}
+
+// evicts the oldest key once `capacity` are held
+pub struct MemoryDedupeStore {
+    capacity: usize,
+    keys: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl MemoryDedupeStore {
+    pub fn new(capacity: usize) -> Self {
+        MemoryDedupeStore {
+            capacity: capacity.max(1),
+            keys: Mutex::new((HashSet::new(), VecDeque::new())),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl DedupeStore for MemoryDedupeStore {
+    async fn insert(&self, key: &str, _post_id: i64) -> Result<bool> {
+        let mut guard = self.keys.lock().unwrap();
+        let (keys, order) = &mut *guard;
+        if !keys.insert(key.to_string()) {
+            return Ok(false);
+        }
+        order.push_back(key.to_string());
+        while order.len() > self.capacity {
+            if let Some(old) = order.pop_front() {
+                keys.remove(&old);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        let mut guard = self.keys.lock().unwrap();
+        let (keys, order) = &mut *guard;
+        if keys.remove(key) {
+            order.retain(|k| k != key);
+        }
+        Ok(())
+    }
+}
+
+pub struct PgDedupeStore {
+    pool: Pool<Postgres>,
+}
+
+impl PgDedupeStore {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PgDedupeStore { pool }
+    }
+
+    // keys only need to outlive the redelivery window
+    pub async fn prune(&self, older_than: Duration) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM delivered_posts WHERE delivered_at < now() - make_interval(secs => $1)",
+        )
+        .bind(older_than.as_secs_f64())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl DedupeStore for PgDedupeStore {
+    async fn insert(&self, key: &str, post_id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            r#"INSERT INTO delivered_posts (key, post_id) VALUES ($1, $2)
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(key)
+        .bind(post_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM delivered_posts WHERE key = $1")
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+// idempotency check in front of delivery. An in-memory LRU answers repeats
+// without a round trip; the durable store, if any, covers restarts and other
+// workers.
+pub struct Deduplicator {
+    recent: MemoryDedupeStore,
+    store: Option<Box<dyn DedupeStore>>,
+}
+
+impl Deduplicator {
+    pub fn new(capacity: usize) -> Self {
+        Deduplicator {
+            recent: MemoryDedupeStore::new(capacity),
+            store: None,
+        }
+    }
+
+    pub fn with_store(mut self, store: impl DedupeStore + 'static) -> Self {
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    // true the first time a post revision is seen; call forget() if the
+    // delivery then fails so a retry isn't suppressed
+    pub async fn should_emit(&self, post_data: &PostData) -> Result<bool> {
+        let key = delivery_key(post_data);
+        let post_id: i64 = serde_json::from_value(serde_json::to_value(&post_data.post.id)?)?;
+        if !self.recent.insert(&key, post_id).await? {
+            return Ok(false);
+        }
+        let Some(store) = &self.store else {
+            return Ok(true);
+        };
+        match store.insert(&key, post_id).await {
+            Ok(new) => Ok(new),
+            Err(e) => {
+                // not remembered anywhere durable, let the retry decide
+                self.recent.remove(&key).await?;
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn forget(&self, post_data: &PostData) -> Result<()> {
+        let key = delivery_key(post_data);
+        self.recent.remove(&key).await?;
+        if let Some(store) = &self.store {
+            store.remove(&key).await?;
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+use std::sync::Arc;
+
 use discourse::{
     bundle::PostData,
-    model::{PostId, post::Post},
+    model::{PostId, TopicId, post::Post, topic::Topic, user::User},
 };
 use once_cell::sync::Lazy;
 use pulsar::{DeserializeMessage, Error as PulsarError, Payload, SerializeMessage};
@@ -8,9 +10,27 @@ use regex::Regex;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use serenity::all::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, MessageId};
+use serenity::all::{
+    ChannelId, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, MessageId, Timestamp,
+};
 
-use crate::{md::html_to_md, utils::trim_to_n_chars};
+use crate::{
+    content_filter::{ContentAction, ContentFilter},
+    envelope::{deserialize_enveloped, serialize_enveloped},
+    error::ForumStreamError,
+    i18n::Locale,
+    md::{
+        COLLAPSED_EXCERPT_CHARS, MdOptions, QuoteStyle, RenderProfile, first_sentence,
+        html_to_md_with, html_to_md_with_base,
+    },
+    metadata::MetadataCache,
+    moderation::admin_action_description_in,
+    reactions::{Reaction, reactions_line},
+    sanitize::escape_markdown,
+    theme::{EmbedTheme, category_color},
+    utils::{TruncateOptions, trim_to_n_chars, truncate},
+    wiki::{WIKI_MARKER, is_wiki, last_edited_line},
+};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DiscordMapping {
@@ -20,110 +40,140 @@ pub struct DiscordMapping {
 
 impl SerializeMessage for DiscordMapping {
     fn serialize_message(input: Self) -> Result<pulsar::producer::Message, PulsarError> {
-        let payload = serde_json::to_vec(&input).map_err(|e| PulsarError::Custom(e.to_string()))?;
-
-        Ok(pulsar::producer::Message {
-            payload,
-            ..Default::default()
-        })
+        serialize_enveloped(input)
     }
 }
 
 impl DeserializeMessage for DiscordMapping {
-    type Output = Result<DiscordMapping, serde_json::Error>;
+    type Output = Result<DiscordMapping, ForumStreamError>;
+
+    fn deserialize_message(payload: &Payload) -> Self::Output {
+        deserialize_enveloped(&payload.data)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TopicThreadMapping {
+    pub topic_id: TopicId,
+    // a thread or a dedicated channel, both are ChannelIds
+    pub discord_thread_id: ChannelId,
+}
+
+impl SerializeMessage for TopicThreadMapping {
+    fn serialize_message(input: Self) -> Result<pulsar::producer::Message, PulsarError> {
+        serialize_enveloped(input)
+    }
+}
+
+impl DeserializeMessage for TopicThreadMapping {
+    type Output = Result<TopicThreadMapping, ForumStreamError>;
 
     fn deserialize_message(payload: &Payload) -> Self::Output {
-        serde_json::from_slice(&payload.data)
+        deserialize_enveloped(&payload.data)
     }
 }
 
-fn get_normal_description(post_data: &PostData) -> String {
+pub fn get_thread_name(post_data: &PostData) -> String {
+    // Discord caps thread names at 100 characters
+    trim_to_n_chars(&post_data.topic.title, 100)
+}
+
+fn get_normal_description(post_data: &PostData, options: &MdOptions, locale: &Locale) -> String {
     let mut ret = String::new();
     let mut reply = false;
-    if let Some(replying_to) = &post_data.replying_to_post {
+    if options.quote_style == QuoteStyle::Collapsed {
+        ret.push_str(&collapsed_reply(post_data, locale));
+    } else if let Some(replying_to) = &post_data.replying_to_post {
         reply = true;
-        let username = &replying_to.username;
+        let username = escape_markdown(&replying_to.username);
         let html = &replying_to.cooked;
-        let md = html_to_md(html);
+        let md = html_to_md_with(html, options);
         let mut quote = String::default();
         for line in md.lines() {
             let quoted = format!("> {line}\n");
             quote.push_str(&quoted);
         }
-        let quote = trim_to_n_chars(&quote, 1000);
+        let quote = truncate(&quote, &TruncateOptions::new(1000));
         ret.push_str(&quote);
         if !quote.ends_with("\n") {
             ret.push('\n');
         }
-        ret.push_str(&format!("⤷ replying to: {username}\n\n"));
+        match options.profile {
+            RenderProfile::Accessible => ret.push_str(&format!(
+                "{}\n\n",
+                locale.format("reply.accessible", &username)
+            )),
+            RenderProfile::Standard => ret.push_str(&format!(
+                "{}\n\n",
+                locale.format("reply.replying_to", &username)
+            )),
+        }
     }
 
     let html = &post_data.post.cooked;
-    let md = html_to_md(html);
-    let md = trim_to_n_chars(&md, if reply { 900 } else { 1900 });
+    let md = html_to_md_with(html, options);
+    let url = get_link(post_data, &post_data.base_url).unwrap_or_default();
+    let md = truncate(
+        &md,
+        &TruncateOptions::new(if reply { 900 } else { 1900 }).read_more(&url),
+    );
     ret.push_str(&md);
     ret
 }
 
-fn get_admin_action_description(post_data: &PostData) -> String {
-    match post_data.post.action_code.as_deref() {
-        Some(code) => match code {
-            "public_open" => String::from("Made this topic public"),
-            "open_topic" => String::from("Converted this to a topic"),
-            "private_topic" => String::from("Made this topic a personal message"),
-            "split_topic" => String::from("Split this topic"),
-            "invited_user" => match post_data.post.action_code_who.as_deref() {
-                Some(who) => format!("Invited {}", who),
-                None => String::from("Invited a user"),
-            },
-            "invited_group" => match post_data.post.action_code_who.as_deref() {
-                Some(who) => format!("Invited group {}", who),
-                None => String::from("Invited a group"),
-            },
-            "user_left" => match post_data.post.action_code_who.as_deref() {
-                Some(who) => format!("{} removed themselves from this message", who),
-                None => String::from("A user removed themselves from this message"),
-            },
-            "removed_user" => match post_data.post.action_code_who.as_deref() {
-                Some(who) => format!("Removed {}", who),
-                None => String::from("Removed a user"),
-            },
-            "removed_group" => match post_data.post.action_code_who.as_deref() {
-                Some(who) => format!("Removed {} group", who),
-                None => String::from("Removed a group"),
-            },
-            "autobumped" => String::from("Automatically bumped"),
-            "tags_changed" => String::from("Tags updated"),
-            "category_changed" => String::from("Category updated"),
-            "autoclosed.enabled" | "closed.enabled" => String::from("Closed"),
-            "autoclosed.disabled" | "closed.disabled" => String::from("Opened"),
-            "archived.enabled" => String::from("Archived"),
-            "archived.disabled" => String::from("Unarchived"),
-            "pinned.enabled" => String::from("Pinned"),
-            "pinned.disabled" | "pinned_globally.disabled" => String::from("Unpinned"),
-            "pinned_globally.enabled" => String::from("Pinned globally"),
-            "visible.enabled" => String::from("Listed"),
-            "visible.disabled" => String::from("Unlisted"),
-            "banner.enabled" => String::from(
-                "Made this a banner. It will appear at the top of every page until it is dismissed by the user.",
-            ),
-            "banner.disabled" => String::from(
-                "Removed this banner. It will no longer appear at the top of every page.",
-            ),
-            "forwarded" => String::from("Forwarded the above email"),
-            _ => String::from(""),
-        },
-        None => String::from(""),
-    }
+// "↳ replying to @user: first sentence…" linked to the replied-to post
+fn collapsed_reply(post_data: &PostData, locale: &Locale) -> String {
+    let Some(replying_to) = &post_data.replying_to_post else {
+        return String::new();
+    };
+    let username = escape_markdown(&replying_to.username);
+    let excerpt = first_sentence(&reply_text(&replying_to.cooked), COLLAPSED_EXCERPT_CHARS);
+    let url = format!(
+        "{}/t/{}/{}",
+        post_data.base_url, post_data.topic.id, replying_to.post_number
+    );
+    format!(
+        "[{} {}]({url})\n\n",
+        locale.format("reply.collapsed", &username),
+        escape_markdown(&excerpt)
+    )
+}
+
+// the replied-to post's own text, without the quotes it contains
+fn reply_text(cooked: &str) -> String {
+    let document = Html::parse_fragment(cooked);
+    let selector = Selector::parse("p").unwrap();
+    document
+        .select(&selector)
+        .filter(|p| {
+            !p.ancestors()
+                .filter_map(scraper::ElementRef::wrap)
+                .any(|a| matches!(a.value().name(), "aside" | "blockquote"))
+        })
+        .map(|p| p.text().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 pub fn get_post_content(post_data: &PostData) -> String {
+    get_post_content_with(post_data, RenderProfile::Standard)
+}
+
+pub fn get_post_content_with(post_data: &PostData, profile: RenderProfile) -> String {
+    let options = MdOptions::for_forum(&post_data.base_url).with_profile(profile);
+    get_post_content_in(post_data, &options)
+}
+
+// the locale for admin actions and reply labels comes from options.locale
+pub fn get_post_content_in(post_data: &PostData, options: &MdOptions) -> String {
+    let english = Locale::english();
+    let locale = options.locale.as_deref().unwrap_or(&english);
     match post_data.post.post_type {
         3 => {
-            let raw = get_admin_action_description(post_data);
+            let raw = admin_action_description_in(post_data, locale);
             format!("*{}*", raw)
         }
-        _ => get_normal_description(post_data),
+        _ => get_normal_description(post_data, options, locale),
     }
 }
 
@@ -137,19 +187,40 @@ fn get_compliant_username(username: &str) -> String {
 
 pub fn create_embed_author(post: &Post, base_url: &str) -> (String, String) {
     let name = get_compliant_username(&post.username);
+    (name, avatar_url(post, base_url, 144))
+}
 
-    let avatar = format!(
-        "{}/{}",
-        base_url,
-        post.avatar_template.replace("{size}", "144")
-    );
-    (name.clone(), avatar)
+// the template can be relative to the forum, protocol relative or already
+// absolute (external avatar stores)
+pub fn avatar_url(post: &Post, base_url: &str, size: u32) -> String {
+    let template = post.avatar_template.replace("{size}", &size.to_string());
+    if template.starts_with("http") {
+        template
+    } else if let Some(rest) = template.strip_prefix("//") {
+        format!("https://{rest}")
+    } else {
+        format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            template.trim_start_matches('/')
+        )
+    }
+}
+
+// images shown as embeds: no avatars, emoji or spoilered ones
+fn embed_image_srcs(post: &Post) -> Vec<String> {
+    let raw = &post.cooked;
+    let spoilered = extract_spoilered_imgs(&raw);
+    let emoji = extract_emoji_srcs(&raw);
+    extract_imgs_excluding_class(&raw, "avatar")
+        .into_iter()
+        .filter(|src| !spoilered.contains(src) && !emoji.contains(src))
+        .collect()
 }
 
 pub fn get_images(post: &Post, url: &str) -> Vec<CreateEmbed> {
     let mut ret = Vec::new();
-    let raw = &post.cooked;
-    let images = extract_imgs_excluding_class(&raw, "avatar");
+    let images = embed_image_srcs(post);
     for (i, image) in images.iter().enumerate() {
         if i >= 9 {
             break;
@@ -160,6 +231,13 @@ pub fn get_images(post: &Post, url: &str) -> Vec<CreateEmbed> {
     ret
 }
 
+pub fn get_spoiler_image_links(post: &Post) -> Vec<String> {
+    extract_spoilered_imgs(&post.cooked)
+        .iter()
+        .map(|src| format!("||[spoiler]({src})||"))
+        .collect()
+}
+
 pub fn get_link(post_data: &PostData, base_url: &str) -> Option<String> {
     let url = format!(
         "{base_url}/t/{}/{}",
@@ -171,53 +249,159 @@ pub fn get_link(post_data: &PostData, base_url: &str) -> Option<String> {
 pub fn get_title(post_data: &PostData) -> Option<String> {
     let thread_name = &post_data.topic.title;
     let ordinal = post_data.post.post_number;
+    if is_wiki(post_data) {
+        return Some(format!("{WIKI_MARKER} {thread_name} #{ordinal}"));
+    }
     Some(format!("{thread_name} #{ordinal}"))
 }
 
-pub fn create_embeds(post_data: &PostData) -> Option<Vec<CreateEmbed>> {
+pub fn get_category_breadcrumb(post_data: &PostData, cache: &MetadataCache) -> String {
+    match cache.breadcrumb(post_data.category.id) {
+        Some(breadcrumb) => breadcrumb,
+        None => post_data.category.name.clone(),
+    }
+}
+
+pub fn create_category_footer(post_data: &PostData, cache: &MetadataCache) -> CreateEmbedFooter {
+    CreateEmbedFooter::new(get_category_breadcrumb(post_data, cache))
+}
+
+pub fn create_embeds(
+    post_data: &PostData,
+    theme: &EmbedTheme,
+) -> Result<Vec<CreateEmbed>, ForumStreamError> {
+    create_embeds_with_reactions(post_data, theme, &[])
+}
+
+// used both for the initial message and for reaction-mirroring edits
+pub fn create_embeds_with_reactions(
+    post_data: &PostData,
+    theme: &EmbedTheme,
+    reactions: &[Reaction],
+) -> Result<Vec<CreateEmbed>, ForumStreamError> {
+    let extras = EmbedExtras {
+        reactions: reactions.to_vec(),
+        ..Default::default()
+    };
+    create_embeds_with_extras(post_data, theme, &extras)
+}
+
+// data that isn't part of the bundle and has to be fetched separately
+#[derive(Debug, Clone, Default)]
+pub struct EmbedExtras {
+    pub reactions: Vec<Reaction>,
+    pub wiki_editor: Option<String>,
+    pub content_filter: Option<Arc<ContentFilter>>,
+}
+
+#[tracing::instrument(skip_all, fields(post_id = %post_data.post.id))]
+pub fn create_embeds_with_extras(
+    post_data: &PostData,
+    theme: &EmbedTheme,
+    extras: &EmbedExtras,
+) -> Result<Vec<CreateEmbed>, ForumStreamError> {
     let base_url = &post_data.base_url;
     let mut ret: Vec<CreateEmbed> = Vec::new();
-    let url = get_link(&post_data, base_url)?;
-    let media = get_images(&post_data.post, &url);
-
-    let color = _hex_color_to_int(&post_data.category.color)?;
-    let description = get_post_content(&post_data);
-    let title = get_title(&post_data)?;
-    let author_name = &post_data.post.display_username;
-    let username = &post_data.post.username;
-    let author_url = format!("{base_url}/u/{username}");
-    let icon_url = {
-        let ret = post_data.post.avatar_template.replace("{size}", "144");
-        Some(format!("{base_url}/{ret}"))
-    }?;
-    let author = CreateEmbedAuthor::new(author_name)
-        .icon_url(icon_url)
-        .url(author_url);
+    let url = get_link(&post_data, base_url)
+        .ok_or_else(|| ForumStreamError::Data(format!("no link for post {}", post_data.post.id)))?;
+    let mut media = get_images(&post_data.post, &url);
+
+    let color = theme.color_for(post_data);
+    let mut description = get_post_content_in(&post_data, &theme.md_options(&post_data.base_url));
+    let mut flagged = false;
+    if let Some(filter) = &extras.content_filter {
+        let verdict = filter.apply(&description);
+        if verdict.is_dropped() {
+            return Err(ForumStreamError::Filtered(verdict.matched.join(", ")));
+        }
+        flagged = verdict.action == Some(ContentAction::Flag);
+        description = verdict.markdown;
+    }
+    // a flagged post's images would show unspoilered as embeds, so they
+    // become spoiler links like the text
+    if flagged {
+        media.clear();
+    }
+    if theme.profile == RenderProfile::Accessible && !media.is_empty() {
+        let count = media.len();
+        description.push_str(&format!(
+            "\n\n{count} image{} attached below.",
+            if count == 1 { "" } else { "s" }
+        ));
+    }
+    let media_links = get_media_links(&post_data.post, base_url);
+    if !media_links.is_empty() {
+        description.push_str("\n\n");
+        description.push_str(&media_links.join(" "));
+    }
+    // embed images can't be spoilered, so spoilered ones go in as links
+    let mut spoiler_links = get_spoiler_image_links(&post_data.post);
+    if flagged {
+        spoiler_links.extend(
+            embed_image_srcs(&post_data.post)
+                .iter()
+                .map(|src| format!("||[image]({src})||")),
+        );
+    }
+    if !spoiler_links.is_empty() {
+        description.push_str("\n\n");
+        description.push_str(&spoiler_links.join(" "));
+    }
+    let title = get_title(&post_data).ok_or_else(|| {
+        ForumStreamError::Data(format!("no title for post {}", post_data.post.id))
+    })?;
+    let description = theme.sanitize.sanitize_content(&description);
+    let title = theme.sanitize.sanitize_title(&title);
+    let author = theme.create_author(post_data);
     let timestamp = post_data.post.created_at;
-    let embed = CreateEmbed::new()
+    let mut embed = CreateEmbed::new()
         .description(description)
         .url(url)
         .title(title)
         .author(author)
         .color(color)
         .timestamp(timestamp);
+    let footer_text = match (
+        &theme.footer_text,
+        last_edited_line(post_data, extras.wiki_editor.as_deref()),
+    ) {
+        (Some(text), Some(wiki)) => Some(format!("{wiki} · {text}")),
+        (text, wiki) => wiki.or_else(|| text.clone()),
+    };
+    let footer_text = match (&footer_text, reactions_line(&extras.reactions)) {
+        (Some(text), Some(line)) => Some(format!("{line} · {text}")),
+        (Some(text), None) => Some(text.clone()),
+        (None, line) => line,
+    };
+    if let Some(footer_text) = footer_text {
+        embed = embed.footer(CreateEmbedFooter::new(footer_text));
+    }
     ret.push(embed);
 
     for image in media {
-        // println!("found image");
         ret.push(image);
     }
 
-    Some(ret)
+    Ok(ret)
 }
 
-pub fn create_embeds_impersonate(post_data: &PostData, base_url: &str) -> Vec<CreateEmbed> {
+pub fn create_embeds_impersonate(
+    post_data: &PostData,
+    base_url: &str,
+    theme: &EmbedTheme,
+) -> Vec<CreateEmbed> {
     let mut ret: Vec<CreateEmbed> = Vec::new();
     if let Some(url) = get_link(&post_data, base_url) {
         let media = get_images(&post_data.post, &url);
-        let description = get_post_content(&post_data);
+        let mut content = get_post_content(&post_data);
+        let spoiler_links = get_spoiler_image_links(&post_data.post);
+        if !spoiler_links.is_empty() {
+            content.push_str("\n\n");
+            content.push_str(&spoiler_links.join(" "));
+        }
+        let description = theme.sanitize.sanitize_content(&content);
         let ordinal = post_data.post.post_number;
-        let footer = CreateEmbedFooter::new(&post_data.post.username);
+        let footer = CreateEmbedFooter::new(theme.attribution(post_data));
         let mut embed = CreateEmbed::new()
             .description(description)
             .url(url)
@@ -225,18 +409,128 @@ pub fn create_embeds_impersonate(post_data: &PostData, base_url: &str) -> Vec<Cr
             .footer(footer)
             .timestamp(post_data.post.updated_at);
         if post_data.post.post_type == 2 {
-            embed = embed.color(_hex_color_to_int("#0277BD").unwrap());
+            if let Some(color) = _hex_color_to_int(&theme.whisper_color) {
+                embed = embed.color(color);
+            }
         }
         ret.push(embed);
 
         for image in media {
-            // println!("found image");
             ret.push(image);
         }
     }
     ret
 }
 
+pub const USER_CARD_COLOR: u32 = 0x5865F2;
+pub const USER_BIO_MAX_CHARS: usize = 300;
+
+pub fn trust_level_name(level: u8) -> &'static str {
+    match level {
+        0 => "New user",
+        1 => "Basic user",
+        2 => "Member",
+        3 => "Regular",
+        _ => "Leader",
+    }
+}
+
+// profile card for "who is this" style commands
+pub fn create_user_embed(user: &User, base_url: &str) -> CreateEmbed {
+    let profile_url = format!("{base_url}/u/{}", user.username);
+    let avatar = format!(
+        "{}{}",
+        base_url,
+        user.avatar_template.replace("{size}", "144")
+    );
+    let name = get_compliant_username(&user.username);
+    let title = match user.name.as_deref() {
+        Some(full) if !full.is_empty() && full != user.username => format!("{full} (@{name})"),
+        _ => name,
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .url(&profile_url)
+        .thumbnail(&avatar)
+        .color(USER_CARD_COLOR)
+        .field("Trust level", trust_level_name(user.trust_level), true)
+        .field("Posts", user.post_count.to_string(), true)
+        .footer(CreateEmbedFooter::new("Joined"))
+        .timestamp(user.created_at);
+    if let Some(user_title) = user.title.as_deref().filter(|t| !t.is_empty()) {
+        embed = embed.author(CreateEmbedAuthor::new(user_title));
+    }
+    if let Some(bio) = user.bio_cooked.as_deref().filter(|b| !b.is_empty()) {
+        let opts = TruncateOptions::new(USER_BIO_MAX_CHARS).read_more(&profile_url);
+        embed = embed.description(truncate(&html_to_md_with_base(bio, base_url), &opts));
+    }
+    embed
+}
+
+pub const TOPIC_EXCERPT_MAX_CHARS: usize = 400;
+
+// new-topic announcement, kept visually distinct from per-post embeds
+pub fn create_topic_embed(topic: &Topic, base_url: &str) -> CreateEmbed {
+    create_topic_embed_with(topic, base_url, None)
+}
+
+// resolves the category breadcrumb and color when the metadata is cached
+pub fn create_topic_embed_with(
+    topic: &Topic,
+    base_url: &str,
+    cache: Option<&MetadataCache>,
+) -> CreateEmbed {
+    let url = format!("{base_url}/t/{}/{}", topic.slug, topic.id);
+    let category = cache.and_then(|c| c.category(topic.category_id));
+    let category_name = match cache.and_then(|c| c.breadcrumb(topic.category_id)) {
+        Some(breadcrumb) => breadcrumb,
+        None => format!("Category {}", topic.category_id),
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("New topic: {}", topic.title))
+        .url(&url)
+        .field("Category", category_name, true)
+        .field("Replies", topic.reply_count.to_string(), true)
+        .footer(CreateEmbedFooter::new("Last activity"))
+        .timestamp(topic.last_posted_at);
+    if !topic.tags.is_empty() {
+        let tags: Vec<String> = topic.tags.iter().map(|t| format!("`{t}`")).collect();
+        embed = embed.field("Tags", tags.join(" "), false);
+    }
+    if let Some(category) = category {
+        embed = embed.color(category_color(category.id, &category.color));
+    }
+    if let Some(excerpt) = topic.excerpt.as_deref().filter(|e| !e.is_empty()) {
+        let opts = TruncateOptions::new(TOPIC_EXCERPT_MAX_CHARS).read_more(&url);
+        embed = embed.description(truncate(&html_to_md_with_base(excerpt, base_url), &opts));
+    }
+    embed
+}
+
+pub const DELETED_COLOR: u32 = 0x747F8D;
+
+// replaces the mirrored message once the forum post is gone
+pub fn create_deletion_embed(post_data: &PostData, theme: &EmbedTheme) -> Option<CreateEmbed> {
+    let base_url = &post_data.base_url;
+    let url = get_link(post_data, base_url)?;
+    let title = get_title(post_data)?;
+    let footer = CreateEmbedFooter::new(format!(
+        "originally posted by {}",
+        theme.attribution(post_data)
+    ));
+    Some(
+        CreateEmbed::new()
+            .title(format!("~~{title}~~"))
+            .url(url)
+            .description("*This post was deleted on the forum.*")
+            .footer(footer)
+            .color(DELETED_COLOR)
+            .timestamp(Timestamp::now()),
+    )
+}
+
 pub fn _hex_color_to_int(hex: &str) -> Option<u32> {
     // Remove leading '#' if present
     let hex = hex.strip_prefix('#').unwrap_or(hex);
@@ -267,6 +561,112 @@ pub fn extract_imgs_excluding_class(html: &str, excluded_class: &str) -> Vec<Str
         .collect()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaItem {
+    pub kind: MediaKind,
+    pub src: String,
+}
+
+fn is_spoilered(element: &scraper::ElementRef) -> bool {
+    element
+        .ancestors()
+        .any(|node| match node.value().as_element() {
+            Some(el) => {
+                el.name() == "details"
+                    || el.attr("class").is_some_and(|c| {
+                        c.split_whitespace()
+                            .any(|c| c == "spoiler" || c == "spoiled")
+                    })
+            }
+            None => false,
+        })
+}
+
+fn extract_emoji_srcs(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("img.emoji").unwrap();
+    document
+        .select(&selector)
+        .filter_map(|img| img.value().attr("src").map(String::from))
+        .collect()
+}
+
+pub fn extract_spoilered_imgs(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let img_selector = Selector::parse("img").unwrap();
+
+    document
+        .select(&img_selector)
+        .filter(|img| is_spoilered(img))
+        .filter_map(|img| img.value().attr("src").map(String::from))
+        .collect()
+}
+
+pub fn extract_media(html: &str, excluded_class: &str) -> Vec<MediaItem> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("img, video, audio, source").unwrap();
+    let mut ret: Vec<MediaItem> = Vec::new();
+
+    for element in document.select(&selector) {
+        let value = element.value();
+        if let Some(class_list) = value.attr("class") {
+            if class_list.split_whitespace().any(|c| c == excluded_class) {
+                continue;
+            }
+        }
+        let kind = match value.name() {
+            "img" => MediaKind::Image,
+            "video" => MediaKind::Video,
+            "audio" => MediaKind::Audio,
+            // <source> takes the kind of the enclosing <video>/<audio>
+            _ => match element
+                .parent()
+                .and_then(|p| p.value().as_element())
+                .map(|p| p.name())
+            {
+                Some("audio") => MediaKind::Audio,
+                Some("video") => MediaKind::Video,
+                _ => continue,
+            },
+        };
+        if let Some(src) = value.attr("src") {
+            if !ret.iter().any(|m| m.src == src) {
+                ret.push(MediaItem {
+                    kind,
+                    src: src.to_string(),
+                });
+            }
+        }
+    }
+    ret
+}
+
+pub fn get_media_links(post: &Post, base_url: &str) -> Vec<String> {
+    extract_media(&post.cooked, "avatar")
+        .into_iter()
+        .filter(|m| m.kind != MediaKind::Image)
+        .map(|m| {
+            let label = match m.kind {
+                MediaKind::Video => "Video",
+                _ => "Audio",
+            };
+            let src = if m.src.starts_with('/') && !m.src.starts_with("//") {
+                format!("{base_url}{}", m.src)
+            } else {
+                m.src
+            };
+            format!("[{label}]({src})")
+        })
+        .collect()
+}
+
 pub fn _tidy_description(input: &mut String) {
     static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[.*?\]\(.*?\)").unwrap());
 
@@ -292,10 +692,7 @@ pub async fn _create_embeds(
     let mut raw = String::from(post.get("raw")?.as_str()?);
     _tidy_description(&mut raw);
     if let Some(replying_post) = replying_to_post {
-        println!(
-            "Post {} is replying to post {}",
-            post["id"], replying_post["id"]
-        );
+        tracing::debug!(post = %post["id"], replying_to = %replying_post["id"], "reply");
         // TODO
         let mut replying_raw = replying_post["raw"].as_str().unwrap().to_string();
         _tidy_description(&mut replying_raw);
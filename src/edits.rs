@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use discourse::{
+    bundle::PostData,
+    model::{PostId, TopicId},
+};
+use reqwest_middleware::ClientWithMiddleware;
+use serenity::all::{ChannelId, CreateEmbed, CreateEmbedFooter, Embed, MessageId};
+
+use crate::{
+    discord::{DiscordMapping, EmbedExtras, create_embeds_with_extras, get_link, get_title},
+    events::PostEvent,
+    mapping_store::MappingStore,
+    md::html_to_md,
+    post_stream::TopicPager,
+    theme::{EmbedTheme, category_color},
+    utils::trim_to_n_chars,
+    wiki::{SyncMode, fetch_last_editor, sync_mode_for},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+// plain LCS over lines, posts are small enough for the quadratic table
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = if a[i] == b[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let mut ret = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            ret.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            ret.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            ret.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    ret.extend(a[i..].iter().map(|l| DiffLine::Removed(l.to_string())));
+    ret.extend(b[j..].iter().map(|l| DiffLine::Added(l.to_string())));
+    ret
+}
+
+fn render_diff(diff: &[DiffLine]) -> String {
+    let mut body = String::new();
+    for line in diff {
+        match line {
+            DiffLine::Same(_) => {}
+            DiffLine::Removed(l) => body.push_str(&format!("- {}\n", l.replace("```", "'''"))),
+            DiffLine::Added(l) => body.push_str(&format!("+ {}\n", l.replace("```", "'''"))),
+        }
+    }
+    let body = trim_to_n_chars(&body, 3900);
+    format!("```diff\n{body}\n```")
+}
+
+pub fn create_edit_embed(
+    old: &PostData,
+    new: &PostData,
+    theme: &EmbedTheme,
+) -> Option<CreateEmbed> {
+    let base_url = &new.base_url;
+    let old_md = html_to_md(&old.post.cooked);
+    let new_md = html_to_md(&new.post.cooked);
+    let diff = diff_lines(&old_md, &new_md);
+
+    let mut description = String::new();
+    if old.topic.title != new.topic.title {
+        description.push_str(&format!(
+            "Title: ~~{}~~ → {}\n",
+            old.topic.title, new.topic.title
+        ));
+    }
+    if diff.iter().any(|l| !matches!(l, DiffLine::Same(_))) {
+        description.push_str(&render_diff(&diff));
+    }
+    if description.is_empty() {
+        return None;
+    }
+
+    let embed = CreateEmbed::new()
+        .title(format!("Edited: {}", get_title(new)?))
+        .url(get_link(new, base_url)?)
+        .description(description)
+        .footer(CreateEmbedFooter::new(format!(
+            "edited by {}",
+            theme.attribution(new)
+        )))
+        .timestamp(new.post.updated_at);
+    let category = &new.category;
+    Some(embed.color(category_color(category.id, &category.color)))
+}
+
+pub trait MappingLookup {
+    fn message_for_post(&self, post_id: &PostId) -> Option<MessageId>;
+}
+
+#[derive(Default)]
+pub struct MappingIndex {
+    by_post: HashMap<PostId, MessageId>,
+}
+
+impl MappingIndex {
+    pub fn insert(&mut self, mapping: DiscordMapping) {
+        self.by_post
+            .insert(mapping.post_id, mapping.discord_message_id);
+    }
+}
+
+impl MappingLookup for MappingIndex {
+    fn message_for_post(&self, post_id: &PostId) -> Option<MessageId> {
+        self.by_post.get(post_id).copied()
+    }
+}
+
+pub struct EditTarget {
+    pub discord_message_id: MessageId,
+    pub mode: SyncMode,
+    // NewMessage: the diff embed describing the change, sent alongside the
+    // mirrored message. EditInPlace: the re-rendered wiki, which replaces it.
+    pub embeds: Vec<CreateEmbed>,
+}
+
+// `wiki_editor` is only used for wikis, see prepare_edit_fetching
+pub fn prepare_edit(
+    old: &PostData,
+    new: &PostData,
+    lookup: &dyn MappingLookup,
+    theme: &EmbedTheme,
+    wiki_editor: Option<String>,
+) -> Option<EditTarget> {
+    let discord_message_id = lookup.message_for_post(&new.post.id)?;
+    let mode = sync_mode_for(new);
+    let embeds = match mode {
+        SyncMode::NewMessage => vec![create_edit_embed(old, new, theme)?],
+        SyncMode::EditInPlace => {
+            let extras = EmbedExtras {
+                wiki_editor,
+                ..Default::default()
+            };
+            create_embeds_with_extras(new, theme, &extras).ok()?
+        }
+    };
+    Some(EditTarget {
+        discord_message_id,
+        mode,
+        embeds,
+    })
+}
+
+// prepare_edit, looking up who last edited a wiki first; ordinary posts
+// don't cost a round trip
+pub async fn prepare_edit_fetching(
+    client: &ClientWithMiddleware,
+    old: &PostData,
+    new: &PostData,
+    lookup: &dyn MappingLookup,
+    theme: &EmbedTheme,
+) -> Option<EditTarget> {
+    let wiki_editor = match sync_mode_for(new) {
+        SyncMode::EditInPlace => {
+            let post_id = serde_json::to_value(&new.post.id).ok()?.as_u64()?;
+            fetch_last_editor(client, &new.base_url, post_id).await
+        }
+        SyncMode::NewMessage => None,
+    };
+    prepare_edit(old, new, lookup, theme, wiki_editor)
+}
+
+pub const TITLE_CHANGED_COLOR: u32 = 0xF1C40F;
+
+// announcement for PostEvent::TitleChanged; None for any other event
+pub fn create_title_changed_embed(event: &PostEvent, base_url: &str) -> Option<CreateEmbed> {
+    let PostEvent::TitleChanged {
+        topic_id,
+        old_title,
+        new_title,
+    } = event
+    else {
+        return None;
+    };
+    Some(
+        CreateEmbed::new()
+            .title(format!("Renamed: {}", trim_to_n_chars(new_title, 200)))
+            .url(format!("{base_url}/t/{topic_id}"))
+            .description(format!(
+                "~~{}~~ → {}",
+                trim_to_n_chars(old_title, 1000),
+                trim_to_n_chars(new_title, 1000)
+            ))
+            .color(TITLE_CHANGED_COLOR),
+    )
+}
+
+// an already sent post embed with the topic title swapped; get_title puts
+// the title first, so only a title that starts with `old_title` is touched
+pub fn retitle_embed(embed: &Embed, old_title: &str, new_title: &str) -> Option<CreateEmbed> {
+    let title = embed.title.as_deref()?;
+    let (prefix, rest) = match title.split_once(old_title) {
+        Some((prefix, rest)) if prefix.trim().chars().count() <= 2 => (prefix, rest),
+        _ => return None,
+    };
+    Some(CreateEmbed::from(embed.clone()).title(format!("{prefix}{new_title}{rest}")))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetitleTarget {
+    pub post_id: i64,
+    pub discord_message_id: MessageId,
+    pub channel_id: Option<ChannelId>,
+}
+
+// every mirrored message of the topic whose embed title still shows the
+// old name; fetch each one and edit it with retitle_embed
+pub async fn retitle_targets<P: TopicPager + ?Sized>(
+    store: &MappingStore,
+    pager: &P,
+    topic_id: TopicId,
+) -> anyhow::Result<Vec<RetitleTarget>> {
+    let mut post_ids = Vec::new();
+    for post_id in pager.post_ids(topic_id).await? {
+        post_ids.push(serde_json::from_value::<i64>(serde_json::to_value(
+            &post_id,
+        )?)?);
+    }
+    Ok(store
+        .messages_for_posts(&post_ids)
+        .await?
+        .into_iter()
+        .map(|(post_id, discord_message_id, channel_id)| RetitleTarget {
+            post_id,
+            discord_message_id,
+            channel_id,
+        })
+        .collect())
+}
@@ -0,0 +1,65 @@
+// common Discourse shortcodes; anything else stays as :shortcode:
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("angry", "😠"),
+    ("blush", "😊"),
+    ("check", "✔️"),
+    ("clap", "👏"),
+    ("confused", "😕"),
+    ("cry", "😢"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("frowning", "😦"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("hugs", "🤗"),
+    ("joy", "😂"),
+    ("laughing", "😆"),
+    ("man_shrugging", "🤷‍♂️"),
+    ("neutral_face", "😐"),
+    ("ok_hand", "👌"),
+    ("open_mouth", "😮"),
+    ("pray", "🙏"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("scream", "😱"),
+    ("shrug", "🤷"),
+    ("slight_smile", "🙂"),
+    ("slightly_frowning_face", "🙁"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("sob", "😭"),
+    ("stuck_out_tongue", "😛"),
+    ("sunglasses", "😎"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("upside_down_face", "🙃"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("x", "❌"),
+];
+
+pub fn shortcode_to_unicode(code: &str) -> Option<&'static str> {
+    // skin tones are dropped rather than rendered wrong
+    let code = code.split(":t").next().unwrap_or(code);
+    EMOJI.iter().find(|(c, _)| *c == code).map(|(_, e)| *e)
+}
+
+// Unicode when known, :shortcode: otherwise
+pub fn display_emoji(code: &str) -> String {
+    let code = code.trim().trim_matches(':');
+    match shortcode_to_unicode(code) {
+        Some(e) => e.to_string(),
+        None => format!(":{code}:"),
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use pulsar::Error as PulsarError;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{error::ForumStreamError, metrics::record_serialize_failure};
+
+// bump when a payload changes in a way older consumers must know about
+pub const SCHEMA_VERSION: u32 = 1;
+pub const SCHEMA_VERSION_PROPERTY: &str = "schema_version";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Envelope<T> {
+    pub schema_version: u32,
+    pub payload: T,
+}
+
+pub fn serialize_enveloped<T: Serialize>(
+    input: T,
+) -> Result<pulsar::producer::Message, PulsarError> {
+    let envelope = Envelope {
+        schema_version: SCHEMA_VERSION,
+        payload: input,
+    };
+    let payload = serde_json::to_vec(&envelope).map_err(|e| {
+        record_serialize_failure();
+        PulsarError::Custom(e.to_string())
+    })?;
+    let mut properties = HashMap::new();
+    properties.insert(
+        SCHEMA_VERSION_PROPERTY.to_string(),
+        SCHEMA_VERSION.to_string(),
+    );
+
+    Ok(pulsar::producer::Message {
+        payload,
+        properties,
+        ..Default::default()
+    })
+}
+
+// accepts enveloped payloads up to SCHEMA_VERSION (unknown fields are
+// ignored) and bare payloads from producers that predate the envelope
+pub fn deserialize_versioned<T: DeserializeOwned>(
+    data: &[u8],
+) -> Result<Envelope<T>, ForumStreamError> {
+    let value: Value = serde_json::from_slice(data)?;
+    let version = value.get("schema_version").and_then(Value::as_u64);
+    if let (Some(version), Some(_)) = (version, value.get("payload")) {
+        if version > u64::from(SCHEMA_VERSION) {
+            return Err(ForumStreamError::UnsupportedSchema {
+                version: u32::try_from(version).unwrap_or(u32::MAX),
+                supported: SCHEMA_VERSION,
+            });
+        }
+        return Ok(serde_json::from_value(value)?);
+    }
+    Ok(Envelope {
+        schema_version: 0,
+        payload: serde_json::from_value(value)?,
+    })
+}
+
+pub fn deserialize_enveloped<T: DeserializeOwned>(data: &[u8]) -> Result<T, ForumStreamError> {
+    deserialize_versioned(data)
+        .map(|e| e.payload)
+        .inspect_err(|_| record_serialize_failure())
+}
@@ -0,0 +1,97 @@
+use thiserror::Error;
+
+pub type Result<T, E = ForumStreamError> = std::result::Result<T, E>;
+
+#[derive(Error, Debug)]
+pub enum ForumStreamError {
+    // talking to something over the wire failed
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("flaresolverr unreachable at {0}")]
+    SolverUnreachable(String),
+    #[error("middleware error: {0}")]
+    Middleware(anyhow::Error),
+    #[error("{0} is disallowed by robots.txt")]
+    RobotsDisallowed(String),
+
+    // something came back in a shape we didn't expect
+    #[error("failed to parse {what}: {reason}")]
+    Parse { what: &'static str, reason: String },
+    #[error("missing field `{0}` in response")]
+    MissingField(&'static str),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    // written by a newer producer; better to stop than misread it
+    #[error("schema version {version} is newer than the supported {supported}")]
+    UnsupportedSchema { version: u32, supported: u32 },
+
+    // the input itself can't be used
+    #[error("invalid data: {0}")]
+    Data(String),
+    #[error("invalid color `{0}`")]
+    InvalidColor(String),
+    #[error("webhook signature mismatch")]
+    InvalidSignature,
+    #[error("missing header `{0}`")]
+    MissingHeader(&'static str),
+    #[error("dropped by content rules: {0}")]
+    Filtered(String),
+
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("migration failed: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
+    #[error("configuration error: {0}")]
+    Config(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl From<reqwest_middleware::Error> for ForumStreamError {
+    fn from(e: reqwest_middleware::Error) -> Self {
+        match e {
+            reqwest_middleware::Error::Reqwest(e) => ForumStreamError::Network(e),
+            reqwest_middleware::Error::Middleware(e) => ForumStreamError::Middleware(e),
+        }
+    }
+}
+
+impl ForumStreamError {
+    pub fn parse(what: &'static str, reason: impl ToString) -> Self {
+        ForumStreamError::Parse {
+            what,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn is_network(&self) -> bool {
+        matches!(
+            self,
+            ForumStreamError::Network(_)
+                | ForumStreamError::SolverUnreachable(_)
+                | ForumStreamError::Middleware(_)
+                | ForumStreamError::RobotsDisallowed(_)
+        )
+    }
+
+    pub fn is_parse(&self) -> bool {
+        matches!(
+            self,
+            ForumStreamError::Parse { .. }
+                | ForumStreamError::MissingField(_)
+                | ForumStreamError::Json(_)
+                | ForumStreamError::UnsupportedSchema { .. }
+        )
+    }
+
+    pub fn is_data(&self) -> bool {
+        matches!(
+            self,
+            ForumStreamError::Data(_)
+                | ForumStreamError::InvalidColor(_)
+                | ForumStreamError::InvalidSignature
+                | ForumStreamError::MissingHeader(_)
+                | ForumStreamError::Filtered(_)
+        )
+    }
+}
@@ -0,0 +1,86 @@
+use discourse::model::{PostId, TopicId};
+use pulsar::{DeserializeMessage, Error as PulsarError, Payload, SerializeMessage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    envelope::{deserialize_enveloped, serialize_enveloped},
+    error::ForumStreamError,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostEvent {
+    // topic_id is missing from events written before it was added
+    Created {
+        post_id: PostId,
+        #[serde(default)]
+        topic_id: Option<TopicId>,
+    },
+    Edited {
+        post_id: PostId,
+        #[serde(default)]
+        topic_id: Option<TopicId>,
+    },
+    Deleted {
+        post_id: PostId,
+        deleted_by: Option<String>,
+    },
+    Recovered {
+        post_id: PostId,
+        #[serde(default)]
+        topic_id: Option<TopicId>,
+    },
+    TitleChanged {
+        topic_id: TopicId,
+        old_title: String,
+        new_title: String,
+    },
+    // a post split or merged into another topic
+    Moved {
+        post_id: PostId,
+        from_topic_id: TopicId,
+        to_topic_id: TopicId,
+    },
+    // event types added by newer producers
+    #[serde(other)]
+    Unknown,
+}
+
+impl PostEvent {
+    pub fn post_id(&self) -> Option<&PostId> {
+        match self {
+            PostEvent::Created { post_id, .. }
+            | PostEvent::Edited { post_id, .. }
+            | PostEvent::Deleted { post_id, .. }
+            | PostEvent::Recovered { post_id, .. }
+            | PostEvent::Moved { post_id, .. } => Some(post_id),
+            PostEvent::TitleChanged { .. } | PostEvent::Unknown => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PostEvent::Created { .. } => "created",
+            PostEvent::Edited { .. } => "edited",
+            PostEvent::Deleted { .. } => "deleted",
+            PostEvent::Recovered { .. } => "recovered",
+            PostEvent::TitleChanged { .. } => "title_changed",
+            PostEvent::Moved { .. } => "moved",
+            PostEvent::Unknown => "unknown",
+        }
+    }
+}
+
+impl SerializeMessage for PostEvent {
+    fn serialize_message(input: Self) -> Result<pulsar::producer::Message, PulsarError> {
+        serialize_enveloped(input)
+    }
+}
+
+impl DeserializeMessage for PostEvent {
+    type Output = Result<PostEvent, ForumStreamError>;
+
+    fn deserialize_message(payload: &Payload) -> Self::Output {
+        deserialize_enveloped(&payload.data)
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use discourse::bundle::PostData;
+use rss::{ChannelBuilder, GuidBuilder, ItemBuilder};
+
+use crate::discord::{get_link, get_title};
+
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub author: String,
+    pub content_html: String,
+    pub published: DateTime<Utc>,
+}
+
+impl FeedItem {
+    pub fn from_post_data(post_data: &PostData) -> Option<Self> {
+        let link = get_link(post_data, &post_data.base_url)?;
+        Some(FeedItem {
+            id: link.clone(),
+            title: get_title(post_data)?,
+            link,
+            author: post_data.post.username.clone(),
+            content_html: post_data.post.cooked.clone(),
+            published: post_data.post.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FeedKey {
+    Topic(String),
+    Category(String),
+}
+
+pub struct Feed {
+    pub title: String,
+    pub link: String,
+    items: VecDeque<FeedItem>,
+    max_items: usize,
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+impl Feed {
+    pub fn new(title: String, link: String, max_items: usize) -> Self {
+        Feed {
+            title,
+            link,
+            items: VecDeque::new(),
+            max_items,
+        }
+    }
+
+    // newest first, re-pushing an item replaces the old version
+    pub fn push(&mut self, item: FeedItem) {
+        self.items.retain(|i| i.id != item.id);
+        self.items.push_front(item);
+        self.items.truncate(self.max_items);
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &FeedItem> {
+        self.items.iter()
+    }
+
+    pub fn to_rss(&self) -> String {
+        let items: Vec<rss::Item> = self
+            .items
+            .iter()
+            .map(|i| {
+                ItemBuilder::default()
+                    .title(Some(i.title.clone()))
+                    .link(Some(i.link.clone()))
+                    .author(Some(i.author.clone()))
+                    .description(Some(i.content_html.clone()))
+                    .pub_date(Some(i.published.to_rfc2822()))
+                    .guid(Some(
+                        GuidBuilder::default()
+                            .value(i.id.clone())
+                            .permalink(true)
+                            .build(),
+                    ))
+                    .build()
+            })
+            .collect();
+        ChannelBuilder::default()
+            .title(self.title.clone())
+            .link(self.link.clone())
+            .description(self.title.clone())
+            .items(items)
+            .build()
+            .to_string()
+    }
+
+    pub fn to_atom(&self) -> String {
+        let updated = self
+            .items
+            .front()
+            .map(|i| i.published)
+            .unwrap_or_else(Utc::now);
+        let mut out = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        out.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        out.push_str(&format!("<title>{}</title>", escape_xml(&self.title)));
+        out.push_str(&format!(r#"<link href="{}"/>"#, escape_xml(&self.link)));
+        out.push_str(&format!("<id>{}</id>", escape_xml(&self.link)));
+        out.push_str(&format!("<updated>{}</updated>", updated.to_rfc3339()));
+        for i in &self.items {
+            out.push_str("<entry>");
+            out.push_str(&format!("<title>{}</title>", escape_xml(&i.title)));
+            out.push_str(&format!(r#"<link href="{}"/>"#, escape_xml(&i.link)));
+            out.push_str(&format!("<id>{}</id>", escape_xml(&i.id)));
+            out.push_str(&format!("<updated>{}</updated>", i.published.to_rfc3339()));
+            out.push_str(&format!(
+                "<author><name>{}</name></author>",
+                escape_xml(&i.author)
+            ));
+            out.push_str(&format!(
+                r#"<content type="html">{}</content>"#,
+                escape_xml(&i.content_html)
+            ));
+            out.push_str("</entry>");
+        }
+        out.push_str("</feed>");
+        out
+    }
+}
+
+// one feed per topic and one per category, filled as posts stream through
+pub struct FeedRegistry {
+    feeds: HashMap<FeedKey, Feed>,
+    max_items: usize,
+}
+
+impl FeedRegistry {
+    pub fn new(max_items: usize) -> Self {
+        FeedRegistry {
+            feeds: HashMap::new(),
+            max_items,
+        }
+    }
+
+    pub fn push(&mut self, post_data: &PostData) {
+        let Some(item) = FeedItem::from_post_data(post_data) else {
+            return;
+        };
+        let base_url = &post_data.base_url;
+        let topic_key = FeedKey::Topic(post_data.topic.id.to_string());
+        let category_key = FeedKey::Category(post_data.category.name.clone());
+        let max_items = self.max_items;
+
+        self.feeds
+            .entry(topic_key)
+            .or_insert_with(|| {
+                Feed::new(
+                    post_data.topic.title.clone(),
+                    format!("{base_url}/t/{}", post_data.topic.id),
+                    max_items,
+                )
+            })
+            .push(item.clone());
+        self.feeds
+            .entry(category_key)
+            .or_insert_with(|| {
+                Feed::new(post_data.category.name.clone(), base_url.clone(), max_items)
+            })
+            .push(item);
+    }
+
+    pub fn get(&self, key: &FeedKey) -> Option<&Feed> {
+        self.feeds.get(key)
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use discourse::bundle::PostData;
+use serde::{Deserialize, Serialize};
+
+use crate::metadata::MetadataCache;
+
+pub const REDACTED_COOKED: &str = "<p><em>This post is only visible on the forum.</em></p>";
+
+// ordered least to most restrictive; when several rules match the strictest wins
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    #[default]
+    Mirror,
+    // keep the post (author, link, title) but drop its content
+    Redact,
+    Skip,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PostFilter {
+    // post_type 2
+    pub whispers: FilterAction,
+    // post_type 3, e.g. "closed this topic"
+    pub small_actions: FilterAction,
+    pub min_trust_level: Option<u8>,
+    pub below_trust_level: FilterAction,
+    // by category id; also applies to subcategories when a MetadataCache is given
+    pub categories: HashMap<u64, FilterAction>,
+}
+
+impl Default for PostFilter {
+    fn default() -> Self {
+        PostFilter {
+            whispers: FilterAction::Mirror,
+            small_actions: FilterAction::Mirror,
+            min_trust_level: None,
+            below_trust_level: FilterAction::Skip,
+            categories: HashMap::new(),
+        }
+    }
+}
+
+impl PostFilter {
+    pub fn decide(&self, post_data: &PostData, cache: Option<&MetadataCache>) -> FilterAction {
+        let post = &post_data.post;
+        let mut action = match post.post_type {
+            2 => self.whispers,
+            3 => self.small_actions,
+            _ => FilterAction::Mirror,
+        };
+        if let Some(min) = self.min_trust_level {
+            if post.trust_level < min {
+                action = action.max(self.below_trust_level);
+            }
+        }
+
+        let category_id = post_data.category.id;
+        let mut path: Vec<u64> = match cache {
+            Some(cache) => cache
+                .category_path(category_id)
+                .iter()
+                .map(|c| c.id)
+                .collect(),
+            None => Vec::new(),
+        };
+        if path.is_empty() {
+            // not cached (yet); the post's own category still counts
+            path.push(category_id);
+        }
+        for id in path {
+            if let Some(rule) = self.categories.get(&id) {
+                action = action.max(*rule);
+            }
+        }
+        action
+    }
+
+    // None when the post shouldn't be mirrored at all
+    pub fn apply(&self, post_data: &PostData, cache: Option<&MetadataCache>) -> Option<PostData> {
+        match self.decide(post_data, cache) {
+            FilterAction::Mirror => Some(post_data.clone()),
+            FilterAction::Redact => {
+                let mut redacted = post_data.clone();
+                redacted.post.cooked = String::from(REDACTED_COOKED);
+                redacted.replying_to_post = None;
+                Some(redacted)
+            }
+            FilterAction::Skip => None,
+        }
+    }
+}
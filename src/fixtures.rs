@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use reqwest_middleware::ClientWithMiddleware;
+use serde_json::{Map, Value, json};
+
+use crate::md::html_to_md;
+
+// only what rendering needs; everything else (emails, IPs, user IDs) is dropped
+const KEPT_FIELDS: &[&str] = &[
+    "id",
+    "topic_id",
+    "post_number",
+    "post_type",
+    "cooked",
+    "username",
+    "display_username",
+    "name",
+    "avatar_template",
+    "created_at",
+    "updated_at",
+    "reply_to_post_number",
+    "action_code",
+    "action_code_who",
+    "wiki",
+    "polls",
+    "reactions",
+    "retorts",
+];
+
+pub fn sanitize_post(post: &Value) -> Value {
+    let mut kept = Map::new();
+    if let Value::Object(obj) = post {
+        for field in KEPT_FIELDS {
+            if let Some(v) = obj.get(*field) {
+                kept.insert(field.to_string(), v.clone());
+            }
+        }
+    }
+    Value::Object(kept)
+}
+
+// replaces the author with a stable placeholder so fixtures can be shared
+pub fn anonymize_post(post: &mut Value) {
+    if let Value::Object(obj) = post {
+        for field in ["username", "display_username", "name"] {
+            if obj.contains_key(field) {
+                obj.insert(
+                    field.to_string(),
+                    Value::String(String::from("fixture_user")),
+                );
+            }
+        }
+        obj.insert(
+            String::from("avatar_template"),
+            Value::String(String::from(
+                "/letter_avatar_proxy/v4/letter/f/8c91f0/{size}.png",
+            )),
+        );
+    }
+}
+
+pub fn build_fixture(base_url: &str, post: &Value, anonymize: bool) -> Value {
+    let mut sanitized = sanitize_post(post);
+    if anonymize {
+        anonymize_post(&mut sanitized);
+    }
+    let cooked = sanitized
+        .get("cooked")
+        .and_then(|c| c.as_str())
+        .unwrap_or("");
+    let markdown = html_to_md(cooked);
+    json!({
+        "source": base_url,
+        "post": sanitized,
+        "expected": {
+            "markdown": markdown,
+        }
+    })
+}
+
+pub async fn capture_fixture(
+    client: &ClientWithMiddleware,
+    base_url: &str,
+    post_id: u64,
+    dir: &Path,
+    anonymize: bool,
+) -> anyhow::Result<PathBuf> {
+    let base_url = base_url.trim_end_matches('/');
+    let post: Value = client
+        .get(format!("{base_url}/posts/{post_id}.json"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let fixture = build_fixture(base_url, &post, anonymize);
+
+    let host = url::Url::parse(base_url)
+        .ok()
+        .and_then(|u| u.host_str().map(String::from))
+        .unwrap_or_else(|| String::from("forum"));
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("{host}-{post_id}.json"));
+    tokio::fs::write(&path, serde_json::to_vec_pretty(&fixture)?).await?;
+    Ok(path)
+}
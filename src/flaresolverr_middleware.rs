@@ -1,31 +1,209 @@
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use http::{Extensions, HeaderMap, HeaderValue};
-use reqwest::header::{ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, CONNECTION, REFERER, USER_AGENT};
+use reqwest::header::{
+    ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, CONNECTION, CONTENT_TYPE, REFERER, SERVER, USER_AGENT,
+};
 use reqwest::{cookie::Jar, Client, Error, Request, Response, Url};
 use reqwest_middleware::{Middleware, Next};
 use serde::Deserialize;
 use serde_json::{json, Value};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, error, info, warn};
+use chrono::{DateTime, TimeZone, Utc};
 
-use crate::utils::ntfy;
+use crate::clearance_store::{ClearanceStore, PersistedClearance};
+use crate::error::ForumStreamError;
+use crate::metrics;
 
+// strings that only show up on cloudflare interstitials
+const CHALLENGE_MARKERS: [&str; 5] = [
+    "__cf_chl",
+    "cf-browser-verification",
+    "/cdn-cgi/challenge-platform/",
+    "<title>Just a moment...</title>",
+    "<title>Attention Required! | Cloudflare</title>",
+];
 
-pub struct FlaresolverrMiddleware {
-    instance: String,
-    refresh_client: Client,
-    cookie_jar: Arc<Jar>,
-    headers: RwLock<HeaderMap>,
+#[derive(Debug, Clone)]
+pub struct SolvedCookie {
+    pub name: String,
+    // Set-Cookie style string for the jar
+    pub cookie: String,
+    pub url: Url,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Solution {
+    pub cookies: Vec<SolvedCookie>,
+    pub user_agent: Option<String>,
+}
+
+impl Solution {
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty() && self.user_agent.is_none()
+    }
+}
+
+// body of the original request, replayed through the solver so POST-only
+// endpoints get clearance too
+#[derive(Debug, Clone)]
+pub struct PostBody {
+    pub data: String,
+    pub content_type: Option<String>,
+}
+
+impl PostBody {
+    fn from_request(req: &Request) -> Option<PostBody> {
+        if req.method() != http::Method::POST {
+            return None;
+        }
+        let data = req
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_default();
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        Some(PostBody { data, content_type })
+    }
+
+    // request.post only takes a form-encoded postData and flaresolverr
+    // ignores request headers, so a JSON body can't be replayed faithfully
+    fn check_replayable(&self) -> Result<(), ForumStreamError> {
+        let mime = self
+            .content_type
+            .as_deref()
+            .and_then(|ct| ct.split(';').next())
+            .map(|ct| ct.trim().to_ascii_lowercase());
+        match mime.as_deref() {
+            Some("application/x-www-form-urlencoded") => Ok(()),
+            None if self.data.is_empty() => Ok(()),
+            other => Err(ForumStreamError::Data(format!(
+                "flaresolverr can only replay form-encoded POST bodies, not {}",
+                other.unwrap_or("an untyped body")
+            ))),
+        }
+    }
+}
+
+// anything that can turn a challenged url into clearance cookies + the UA they're bound to
+#[async_trait::async_trait]
+pub trait ChallengeSolver: Send + Sync {
+    async fn solve(
+        &self,
+        url: &Url,
+        post: Option<&PostBody>,
+    ) -> Result<Solution, ForumStreamError>;
+}
+
+// for forums that aren't behind cloudflare; challenged responses are passed through
+pub struct NoopSolver;
+
+#[async_trait::async_trait]
+impl ChallengeSolver for NoopSolver {
+    async fn solve(
+        &self,
+        _url: &Url,
+        _post: Option<&PostBody>,
+    ) -> Result<Solution, ForumStreamError> {
+        Ok(Solution::default())
+    }
 }
 
-#[derive(Debug)]
-enum FlaresolverrError {
-    ConnectionProblem,
-    _MissingResponse,
-    MIssingUA,
-    MissingSolution,
-    MissingCookie,
-    CookiesNotAnArray,
+const PROXY_MAX_FAILURES: u32 = 3;
+const PROXY_COOLDOWN: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone)]
+struct ProxyState {
+    url: String,
+    consecutive_failures: u32,
+    benched_until: Option<Instant>,
+}
+
+impl ProxyState {
+    fn healthy(&self, now: Instant) -> bool {
+        self.benched_until.is_none_or(|until| until <= now)
+    }
+}
+
+// round-robin over upstream proxies; one that keeps failing solves is
+// benched for a while so a banned exit IP doesn't stall every solve
+#[derive(Debug, Default)]
+pub struct ProxyPool {
+    proxies: Vec<ProxyState>,
+    current: usize,
+}
+
+impl ProxyPool {
+    pub fn new(urls: Vec<String>) -> Self {
+        ProxyPool {
+            proxies: urls
+                .into_iter()
+                .map(|url| ProxyState {
+                    url,
+                    consecutive_failures: 0,
+                    benched_until: None,
+                })
+                .collect(),
+            current: 0,
+        }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.proxies.get(self.current).map(|p| p.url.as_str())
+    }
+
+    pub fn rotate(&mut self) {
+        if self.proxies.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        for step in 1..=self.proxies.len() {
+            let idx = (self.current + step) % self.proxies.len();
+            if self.proxies[idx].healthy(now) {
+                self.current = idx;
+                return;
+            }
+        }
+        // everything is benched; take whichever comes back first
+        if let Some((idx, _)) = self
+            .proxies
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, p)| p.benched_until)
+        {
+            self.current = idx;
+        }
+    }
+
+    pub fn mark_success(&mut self) {
+        if let Some(proxy) = self.proxies.get_mut(self.current) {
+            proxy.consecutive_failures = 0;
+            proxy.benched_until = None;
+        }
+    }
+
+    pub fn mark_failure(&mut self) {
+        if let Some(proxy) = self.proxies.get_mut(self.current) {
+            proxy.consecutive_failures += 1;
+            if proxy.consecutive_failures >= PROXY_MAX_FAILURES {
+                proxy.benched_until = Some(Instant::now() + PROXY_COOLDOWN);
+            }
+        }
+        self.rotate();
+    }
+
+    pub fn healthy_count(&self) -> usize {
+        let now = Instant::now();
+        self.proxies.iter().filter(|p| p.healthy(now)).count()
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -45,29 +223,98 @@ struct CookieData {
     same_site: Option<String>,
 }
 
-impl FlaresolverrMiddleware {
+pub struct FlaresolverrSolver {
+    instance: String,
+    client: Client,
+    session_name: String,
+    // recreated once older than this; None keeps it until flaresolverr drops it
+    session_ttl: Option<Duration>,
+    session_created: RwLock<Option<Instant>>,
+    proxies: std::sync::Mutex<ProxyPool>,
+}
+
+impl FlaresolverrSolver {
+    pub async fn new(
+        client: Client,
+        instance: String,
+        session_name: &str,
+        session_ttl: Option<Duration>,
+    ) -> Result<FlaresolverrSolver, reqwest::Error> {
+        let solver = FlaresolverrSolver {
+            instance,
+            client,
+            session_name: session_name.to_string(),
+            session_ttl,
+            session_created: RwLock::new(None),
+            proxies: std::sync::Mutex::new(ProxyPool::default()),
+        };
+        solver.create_session().await?;
+        let _ = solver.list_sessions().await;
+        Ok(solver)
+    }
+
+    // takes effect on the next solve, which recreates the session behind the first proxy
+    pub fn with_proxies(mut self, proxies: Vec<String>) -> Self {
+        *self.proxies.get_mut().unwrap() = ProxyPool::new(proxies);
+        *self.session_created.get_mut() = None;
+        self
+    }
+
+    pub fn healthy_proxies(&self) -> usize {
+        self.proxies.lock().unwrap().healthy_count()
+    }
+
     async fn list_sessions(&self) {
         let data: Value = json!({
             "cmd": "sessions.list",
         });
         if let Ok(resp) = self
-            .refresh_client
+            .client
             .post(&self.instance)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .json(&data)
             .send()
             .await
         {
-            println!("{:?}", resp.json::<Value>().await.unwrap())
+            match resp.json::<Value>().await {
+                Ok(sessions) => debug!(?sessions, "flaresolverr sessions"),
+                Err(e) => warn!(error = %e, "could not read flaresolverr session list"),
+            }
         }
     }
 
+    fn current_proxy(&self) -> Option<String> {
+        self.proxies.lock().unwrap().current().map(String::from)
+    }
+
+    // flaresolverr binds the proxy to the session, so a new proxy means a new session
+    #[tracing::instrument(skip_all, fields(session = %self.session_name))]
     async fn create_session(&self) -> Result<Response, Error> {
-        let data: Value = json!({
+        let mut data: Value = json!({
             "cmd": "sessions.create",
-            "session": "forum-stream"
+            "session": self.session_name
         });
-        self.refresh_client
+        if let Some(proxy) = self.current_proxy() {
+            data["proxy"] = json!({ "url": proxy });
+        }
+        let resp = self
+            .client
+            .post(&self.instance)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .json(&data)
+            .send()
+            .await?;
+        *self.session_created.write().await = Some(Instant::now());
+        Ok(resp)
+    }
+
+    async fn destroy_session(&self) -> Result<Response, Error> {
+        let data: Value = json!({
+            "cmd": "sessions.destroy",
+            "session": self.session_name
+        });
+        *self.session_created.write().await = None;
+        self.client
             .post(&self.instance)
             .header(reqwest::header::CONTENT_TYPE, "application/json")
             .json(&data)
@@ -75,8 +322,63 @@ impl FlaresolverrMiddleware {
             .await
     }
 
-    fn parse_cookie(cookie: &Value) -> (String, Url) {
-        let data = serde_json::from_value::<CookieData>(cookie.clone()).unwrap();
+    async fn recreate_session(&self) -> Result<(), ForumStreamError> {
+        // destroying a session flaresolverr already forgot is an error we don't care about
+        let _ = self.destroy_session().await;
+        self.create_session().await?;
+        Ok(())
+    }
+
+    async fn session_expired(&self) -> bool {
+        match (self.session_ttl, *self.session_created.read().await) {
+            (Some(ttl), Some(created)) => created.elapsed() >= ttl,
+            (_, None) => true,
+            (None, Some(_)) => false,
+        }
+    }
+
+    fn is_session_missing(json: &Value) -> bool {
+        let failed = json.get("status").and_then(Value::as_str) == Some("error");
+        let message = json
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_lowercase();
+        failed
+            && message.contains("session")
+            && (message.contains("not found") || message.contains("does not exist"))
+    }
+
+    async fn send_solve(
+        &self,
+        url: &Url,
+        post: Option<&PostBody>,
+    ) -> Result<Value, ForumStreamError> {
+        let mut data: Value = json!({
+          "cmd": "request.get",
+          "url": url.to_string(),
+          "session": self.session_name,
+          "maxTimeout": 60000
+        });
+        if let Some(post) = post {
+            data["cmd"] = json!("request.post");
+            data["postData"] = json!(post.data);
+        }
+
+        let resp = self
+            .client
+            .post(&self.instance)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .json(&data)
+            .send()
+            .await
+            .map_err(|_| ForumStreamError::SolverUnreachable(self.instance.clone()))?;
+        Ok(resp.json().await?)
+    }
+
+    fn parse_cookie(cookie: &Value) -> Result<SolvedCookie, ForumStreamError> {
+        let data = serde_json::from_value::<CookieData>(cookie.clone())
+            .map_err(|e| ForumStreamError::parse("cookie", e))?;
         let mut cookie_str = format!("{}={}", data.name, data.value);
         if !data.path.is_empty() {
             cookie_str.push_str(&format!("; Path={}", data.path));
@@ -94,110 +396,387 @@ impl FlaresolverrMiddleware {
             cookie_str.push_str(&format!("; SameSite={}", same_site));
         }
 
-        if let Some(exp) = data.expires {
-            use chrono::{TimeZone, Utc};
-            let date = Utc.timestamp_opt(exp as i64, 0).unwrap();
+        // session cookies come back as -1
+        let expires = data
+            .expires
+            .filter(|e| *e > 0.0)
+            .and_then(|exp| Utc.timestamp_opt(exp as i64, 0).single());
+        if let Some(date) = expires {
             let cookie_date = date.to_rfc2822(); // e.g. "Wed, 21 Oct 2015 07:28:00 GMT"
             cookie_str.push_str(&format!("; Expires={}", cookie_date));
         }
 
-        let domain_url =
-            Url::parse(&format!("https://{}", data.domain.trim_start_matches('.'))).unwrap();
-        (cookie_str, domain_url)
+        let domain_url = Url::parse(&format!("https://{}", data.domain.trim_start_matches('.')))
+            .map_err(|e| ForumStreamError::parse("cookie domain", e))?;
+        Ok(SolvedCookie {
+            name: data.name,
+            cookie: cookie_str,
+            url: domain_url,
+            expires,
+        })
     }
 
-    async fn resolve_cloudflare(&self, req: &mut Request) -> Result<(), FlaresolverrError> {
+    #[tracing::instrument(skip(self, post), fields(%url, session = %self.session_name))]
+    async fn solve_once(
+        &self,
+        url: &Url,
+        post: Option<&PostBody>,
+    ) -> Result<Solution, ForumStreamError> {
+        if self.session_expired().await {
+            self.recreate_session().await?;
+        }
+        let mut json = self.send_solve(url, post).await?;
+        if Self::is_session_missing(&json) {
+            // flaresolverr restarted or reaped the session behind our back
+            self.recreate_session().await?;
+            json = self.send_solve(url, post).await?;
+        }
+
+        let solution = json
+            .get("solution")
+            .ok_or(ForumStreamError::MissingField("solution"))?;
+        let cookies = solution
+            .get("cookies")
+            .ok_or(ForumStreamError::MissingField("solution.cookies"))?
+            .as_array()
+            .ok_or(ForumStreamError::parse("solution.cookies", "not an array"))?;
+        let user_agent = solution
+            .get("userAgent")
+            .and_then(Value::as_str)
+            .ok_or(ForumStreamError::MissingField("solution.userAgent"))?;
+
+        let mut parsed = Vec::new();
+        for c in cookies {
+            match Self::parse_cookie(c) {
+                Ok(cookie) => parsed.push(cookie),
+                Err(e) => warn!(error = %e, "skipping unparseable cookie"),
+            }
+        }
+        Ok(Solution {
+            cookies: parsed,
+            user_agent: Some(user_agent.to_string()),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ChallengeSolver for FlaresolverrSolver {
+    async fn solve(
+        &self,
+        url: &Url,
+        post: Option<&PostBody>,
+    ) -> Result<Solution, ForumStreamError> {
+        // not the proxy's fault, so checked before anything is rotated
+        if let Some(post) = post {
+            post.check_replayable()?;
+        }
+        let result = self.solve_once(url, post).await;
+        if self.proxies.lock().unwrap().proxies.is_empty() {
+            return result;
+        }
+        match &result {
+            Ok(_) => self.proxies.lock().unwrap().mark_success(),
+            // our own connection to flaresolverr says nothing about the proxy
+            Err(ForumStreamError::SolverUnreachable(_)) => {}
+            Err(e) => {
+                let next = {
+                    let mut proxies = self.proxies.lock().unwrap();
+                    proxies.mark_failure();
+                    proxies.current().map(String::from)
+                };
+                warn!(error = %e, next_proxy = ?next, "solve failed, rotating proxy");
+                // picked up with the new proxy on the next solve
+                *self.session_created.write().await = None;
+            }
+        }
+        result
+    }
+}
+
+impl Drop for FlaresolverrSolver {
+    fn drop(&mut self) {
+        // best effort; there's no runtime to run on during shutdown
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let client = self.client.clone();
+        let instance = self.instance.clone();
         let data: Value = json!({
-          "cmd": "request.get",
-          "url": req.url().to_string(),
-          "maxTimeout": 60000
+            "cmd": "sessions.destroy",
+            "session": self.session_name
+        });
+        handle.spawn(async move {
+            let _ = client
+                .post(&instance)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .json(&data)
+                .send()
+                .await;
         });
+    }
+}
 
-        if let Ok(resp) = self
-            .refresh_client
-            .post(&self.instance)
-            .header(reqwest::header::CONTENT_TYPE, "application/json")
-            .json(&data)
-            .send()
-            .await
-        {
-            let json: Value = resp.json().await.unwrap();
-            let cookies = json
-                .get("solution")
-                .ok_or(FlaresolverrError::MissingSolution)?
-                .get("cookies")
-                .ok_or(FlaresolverrError::MissingCookie)?
-                .as_array()
-                .ok_or(FlaresolverrError::CookiesNotAnArray)?;
-
-            for c in cookies {
-                let parsed = Self::parse_cookie(c);
-                self.cookie_jar.add_cookie_str(parsed.0.as_str(), &parsed.1);
-            }
+pub struct FlaresolverrMiddleware {
+    solver: Box<dyn ChallengeSolver>,
+    cookie_jar: Arc<Jar>,
+    headers: RwLock<HeaderMap>,
+    // expiry of the cf_clearance cookie from the last solve
+    clearance_expires: RwLock<Option<DateTime<Utc>>>,
+    last_solved_url: RwLock<Option<Url>>,
+    // one solve at a time; the generation tells waiters whether the solve
+    // they queued behind already refreshed the cookies
+    solve_lock: Mutex<()>,
+    solve_generation: AtomicU64,
+    store: Option<Box<dyn ClearanceStore>>,
+}
+
+impl FlaresolverrMiddleware {
+    // `seen` is the generation from before the challenged request was sent;
+    // false if the solver had nothing to offer
+    async fn resolve_cloudflare(
+        &self,
+        req: &mut Request,
+        seen: u64,
+    ) -> Result<bool, ForumStreamError> {
+        let _guard = self.solve_lock.lock().await;
+        if self.solve_generation.load(Ordering::Acquire) != seen {
+            // someone else solved while we were waiting
+            return Ok(true);
+        }
+        let post = PostBody::from_request(req);
+        self.solve(req.url(), post.as_ref()).await
+    }
 
-            let ua = json
-                .get("solution")
-                .ok_or(FlaresolverrError::MissingSolution)?
-                .get("userAgent")
-                .ok_or(FlaresolverrError::MIssingUA)?
-                .as_str()
-                .unwrap();
+    async fn solve_url(&self, url: &Url) -> Result<bool, ForumStreamError> {
+        let _guard = self.solve_lock.lock().await;
+        self.solve(url, None).await
+    }
 
-            {
-                let mut headers = self.headers.write().await;
-                headers.insert(USER_AGENT, HeaderValue::from_str(&ua).unwrap());
-                // Add common browser headers too (best-effort)
-                headers.entry(ACCEPT).or_insert(HeaderValue::from_static(
-                    "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
-                ));
-                headers
-                    .entry(ACCEPT_LANGUAGE)
-                    .or_insert(HeaderValue::from_static("en-US,en;q=0.9"));
-                headers
-                    .entry(ACCEPT_ENCODING)
-                    .or_insert(HeaderValue::from_static("gzip, deflate, br"));
-                headers
-                    .entry(CONNECTION)
-                    .or_insert(HeaderValue::from_static("keep-alive"));
-                headers
-                    .entry(REFERER)
-                    .or_insert(HeaderValue::from_static("https://example.com/"));
-                headers.insert("Sec-Fetch-Mode", HeaderValue::from_static("navigate"));
-                headers.insert("Sec-Fetch-Site", HeaderValue::from_static("none"));
+    #[tracing::instrument(skip(self, post), fields(%url, post = post.is_some()))]
+    async fn solve(&self, url: &Url, post: Option<&PostBody>) -> Result<bool, ForumStreamError> {
+        let started = Instant::now();
+        let solution = self.solver.solve(url, post).await;
+        metrics::record_solve(solution.is_ok(), started.elapsed());
+        let solution = solution?;
+        if solution.is_empty() {
+            return Ok(false);
+        }
+        if let Some(store) = &self.store {
+            let persisted = PersistedClearance::new(&solution, url);
+            if let Err(e) = store.save(&persisted).await {
+                warn!(error = %e, "could not persist clearance cookies");
             }
-        } else {
-            return Err(FlaresolverrError::ConnectionProblem);
+        }
+        self.apply_solution(solution, url).await?;
+        Ok(true)
+    }
+
+    async fn apply_solution(&self, solution: Solution, url: &Url) -> Result<(), ForumStreamError> {
+        let mut clearance_expires = None;
+        for c in &solution.cookies {
+            self.cookie_jar.add_cookie_str(&c.cookie, &c.url);
+            if c.name == "cf_clearance" {
+                clearance_expires = c.expires;
+            }
+        }
+        // no expiring cf_clearance means nothing for the refresh task to do
+        *self.clearance_expires.write().await = clearance_expires;
+        *self.last_solved_url.write().await = Some(url.clone());
+        self.solve_generation.fetch_add(1, Ordering::AcqRel);
+
+        let Some(ua) = solution.user_agent else {
+            return Ok(());
+        };
+        let ua = HeaderValue::from_str(&ua)
+            .map_err(|e| ForumStreamError::parse("user agent", e))?;
+
+        {
+            let mut headers = self.headers.write().await;
+            headers.insert(USER_AGENT, ua);
+            // Add common browser headers too (best-effort)
+            headers.entry(ACCEPT).or_insert(HeaderValue::from_static(
+                "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+            ));
+            headers
+                .entry(ACCEPT_LANGUAGE)
+                .or_insert(HeaderValue::from_static("en-US,en;q=0.9"));
+            headers
+                .entry(ACCEPT_ENCODING)
+                .or_insert(HeaderValue::from_static("gzip, deflate, br"));
+            headers
+                .entry(CONNECTION)
+                .or_insert(HeaderValue::from_static("keep-alive"));
+            headers
+                .entry(REFERER)
+                .or_insert(HeaderValue::from_static("https://example.com/"));
+            headers.insert("Sec-Fetch-Mode", HeaderValue::from_static("navigate"));
+            headers.insert("Sec-Fetch-Site", HeaderValue::from_static("none"));
         }
         Ok(())
     }
 
+    // saves every solve and restores the newest unexpired one, so restarts
+    // don't each cost a fresh solve
+    pub fn with_store(mut self, store: impl ClearanceStore + 'static) -> Self {
+        self.store = Some(Box::new(store));
+        self
+    }
+
+    // true if still-valid clearance was loaded into the jar
+    pub async fn restore(&self) -> Result<bool, ForumStreamError> {
+        let Some(store) = &self.store else {
+            return Ok(false);
+        };
+        let Some(persisted) = store.load().await? else {
+            return Ok(false);
+        };
+        let Some((solution, url)) = persisted.into_solution(Utc::now()) else {
+            return Ok(false);
+        };
+        let _guard = self.solve_lock.lock().await;
+        self.apply_solution(solution, &url).await?;
+        Ok(true)
+    }
+
     pub async fn new(
         client: Client,
         cookie_jar: Arc<Jar>,
         proxy_url: String
     ) -> Result<FlaresolverrMiddleware, reqwest::Error> {
-        let middleware = FlaresolverrMiddleware {
-            instance: proxy_url,
-            refresh_client: client,
+        Self::with_session(client, cookie_jar, proxy_url, "forum-stream", None).await
+    }
+
+    // separate names let several middlewares share one flaresolverr instance
+    pub async fn with_session(
+        client: Client,
+        cookie_jar: Arc<Jar>,
+        proxy_url: String,
+        session_name: &str,
+        session_ttl: Option<Duration>,
+    ) -> Result<FlaresolverrMiddleware, reqwest::Error> {
+        let solver = FlaresolverrSolver::new(client, proxy_url, session_name, session_ttl).await?;
+        info!(session = session_name, "flaresolverr middleware constructed");
+        Ok(Self::with_solver(cookie_jar, solver))
+    }
+
+    pub fn with_solver(cookie_jar: Arc<Jar>, solver: impl ChallengeSolver + 'static) -> Self {
+        FlaresolverrMiddleware {
+            solver: Box::new(solver),
             cookie_jar,
             headers: RwLock::new(HeaderMap::default()),
-        };
-        ntfy("Constructed", "forum-stream-errors").await;
-        middleware.create_session().await?;
-        let _ = middleware.list_sessions().await;
-        Ok(middleware)
+            clearance_expires: RwLock::new(None),
+            last_solved_url: RwLock::new(None),
+            solve_lock: Mutex::new(()),
+            solve_generation: AtomicU64::new(0),
+            store: None,
+        }
+    }
+}
+
+impl FlaresolverrMiddleware {
+    pub async fn clearance_expires(&self) -> Option<DateTime<Utc>> {
+        *self.clearance_expires.read().await
+    }
+
+    // re-solves `lead` before cf_clearance expires so foreground requests
+    // never hit the challenge; register the middleware with `with_arc`
+    pub fn spawn_clearance_refresh(
+        self: &Arc<Self>,
+        lead: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let expires = this.clearance_expires().await;
+                let url = this.last_solved_url.read().await.clone();
+                let (Some(expires), Some(url)) = (expires, url) else {
+                    // nothing solved yet
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    continue;
+                };
+                let refresh_at = expires - chrono::Duration::from_std(lead).unwrap_or_default();
+                let wait = (refresh_at - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                if let Err(e) = this.solve_url(&url).await {
+                    error!(error = %e, "proactive clearance refresh failed");
+                }
+                if this.clearance_expires().await != Some(expires) {
+                    continue;
+                }
+                // no fresh cookie; retry while the old one is still good,
+                // after that leave it to the next foreground challenge
+                if expires > Utc::now() {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                } else {
+                    let mut current = this.clearance_expires.write().await;
+                    if *current == Some(expires) {
+                        *current = None;
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn served_by_cloudflare(res: &Response) -> bool {
+    res.headers()
+        .get(SERVER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.to_ascii_lowercase().contains("cloudflare"))
+}
+
+fn is_html(res: &Response) -> bool {
+    res.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/html"))
+}
+
+// reading the body consumes the response, so hand back an equivalent one
+async fn detect_challenge(res: Response) -> Result<(bool, Response), Error> {
+    let mitigated = res
+        .headers()
+        .get("cf-mitigated")
+        .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"challenge"));
+    if mitigated || res.status() == 403 {
+        return Ok((true, res));
     }
+    let sniff = match res.status().as_u16() {
+        429 | 503 => served_by_cloudflare(&res),
+        // JSON API responses are never challenges, only sniff pages
+        200 => served_by_cloudflare(&res) && is_html(&res),
+        _ => false,
+    };
+    if !sniff {
+        return Ok((false, res));
+    }
+
+    let status = res.status();
+    let version = res.version();
+    let headers = res.headers().clone();
+    let extensions = res.extensions().clone();
+    let body = res.bytes().await?;
+    let text = String::from_utf8_lossy(&body);
+    let challenged = CHALLENGE_MARKERS.iter().any(|m| text.contains(m));
+
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+    *rebuilt.extensions_mut() = extensions;
+    Ok((challenged, Response::from(rebuilt)))
 }
 
 #[async_trait::async_trait]
 impl Middleware for FlaresolverrMiddleware {
+    #[tracing::instrument(skip_all, fields(method = %req.method(), url = %req.url()))]
     async fn handle(
         &self,
         mut req: Request,
         extensions: &mut Extensions,
         next: Next<'_>,
     ) -> reqwest_middleware::Result<Response> {
-        // println!("Request started {:?}", req);
+        let seen = self.solve_generation.load(Ordering::Acquire);
         let h = req.headers_mut();
         {
             let real_h = self.headers.read().await;
@@ -205,14 +784,22 @@ impl Middleware for FlaresolverrMiddleware {
                 h.insert(k, v.clone());
             }
         }
-        let mut res = next
-            .clone()
-            .run(req.try_clone().unwrap(), extensions)
-            .await?;
-        if res.status() == 403 {
-            ntfy("Status code 403 :(", "forum-stream-errors").await;
-            // println!("{:?}", self.cookie_jar);
-            self.resolve_cloudflare(&mut req).await.unwrap();
+        // streaming bodies can't be replayed after a solve
+        let Some(retry) = req.try_clone() else {
+            return next.run(req, extensions).await;
+        };
+        let res = next.clone().run(retry, extensions).await?;
+        let (challenged, mut res) = detect_challenge(res).await?;
+        metrics::record_response(res.status().as_u16(), challenged);
+        if challenged {
+            warn!(url = %req.url(), status = %res.status(), "cloudflare challenge");
+            let solved = self
+                .resolve_cloudflare(&mut req, seen)
+                .await
+                .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+            if !solved {
+                return Ok(res);
+            }
             let h = req.headers_mut();
             {
                 let real_h = self.headers.read().await;
@@ -222,7 +809,6 @@ impl Middleware for FlaresolverrMiddleware {
             }
             res = next.run(req, extensions).await?;
         }
-        // println!("Result: {:?}", res);
         Ok(res)
     }
 }
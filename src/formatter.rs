@@ -0,0 +1,164 @@
+use discourse::bundle::PostData;
+use serde_json::Value;
+use serenity::all::CreateEmbed;
+
+use crate::{
+    discord::{
+        create_embed_author, create_embeds, create_embeds_impersonate,
+        extract_imgs_excluding_class, get_link, get_post_content, get_title,
+    },
+    error::ForumStreamError,
+    matrix::{MatrixMessage, create_matrix_message},
+    metrics::record_embeds,
+    slack::create_slack_blocks,
+    telegram::{TelegramRequest, create_telegram_requests},
+    theme::EmbedTheme,
+    webhook::{
+        IdentityRules, WebhookPayload, create_webhook_payload, create_webhook_payload_routed,
+    },
+};
+
+pub trait PostFormatter {
+    type Output;
+
+    fn format(&self, post: &PostData) -> Self::Output;
+}
+
+// the target-independent pieces every formatter starts from
+#[derive(Debug, Clone)]
+pub struct PostParts {
+    pub title: String,
+    pub url: String,
+    pub author_name: String,
+    pub author_url: String,
+    pub avatar_url: String,
+    // quote + body, already converted to markdown
+    pub content: String,
+    pub images: Vec<String>,
+}
+
+impl PostParts {
+    // honors the theme's anonymous identity; avatar_url is empty when it has
+    // no icon
+    pub fn from_post_data(post_data: &PostData, theme: &EmbedTheme) -> Option<Self> {
+        let base_url = &post_data.base_url;
+        let (author_name, author_url, avatar_url) = match &theme.author.anonymous {
+            Some(anon) => (
+                anon.name.clone(),
+                base_url.clone(),
+                anon.icon_url.clone().unwrap_or_default(),
+            ),
+            None => {
+                let (name, avatar) = create_embed_author(&post_data.post, base_url);
+                (
+                    name,
+                    format!("{base_url}/u/{}", post_data.post.username),
+                    avatar,
+                )
+            }
+        };
+        Some(PostParts {
+            title: get_title(post_data)?,
+            url: get_link(post_data, base_url)?,
+            author_name,
+            author_url,
+            avatar_url,
+            content: get_post_content(post_data),
+            images: extract_imgs_excluding_class(&post_data.post.cooked, "avatar"),
+        })
+    }
+}
+
+pub struct DiscordFormatter {
+    pub theme: EmbedTheme,
+}
+
+impl PostFormatter for DiscordFormatter {
+    type Output = Result<Vec<CreateEmbed>, ForumStreamError>;
+
+    fn format(&self, post: &PostData) -> Self::Output {
+        let embeds = create_embeds(post, &self.theme)?;
+        record_embeds("discord", embeds.len());
+        Ok(embeds)
+    }
+}
+
+pub struct ImpersonateFormatter {
+    pub theme: EmbedTheme,
+}
+
+impl PostFormatter for ImpersonateFormatter {
+    type Output = Vec<CreateEmbed>;
+
+    fn format(&self, post: &PostData) -> Self::Output {
+        let embeds = create_embeds_impersonate(post, &post.base_url, &self.theme);
+        record_embeds("impersonate", embeds.len());
+        embeds
+    }
+}
+
+pub struct WebhookFormatter {
+    pub theme: EmbedTheme,
+    pub impersonate: bool,
+    // only consulted when impersonating
+    pub identities: IdentityRules,
+}
+
+impl PostFormatter for WebhookFormatter {
+    type Output = Option<WebhookPayload>;
+
+    fn format(&self, post: &PostData) -> Self::Output {
+        let payload = if self.impersonate {
+            create_webhook_payload_routed(post, &self.theme, &self.identities, None)
+        } else {
+            create_webhook_payload(post, &self.theme)
+        };
+        if let Some(payload) = &payload {
+            record_embeds("webhook", payload.embeds.len());
+        }
+        payload
+    }
+}
+
+pub struct MatrixFormatter {
+    pub theme: EmbedTheme,
+}
+
+impl PostFormatter for MatrixFormatter {
+    type Output = Option<MatrixMessage>;
+
+    fn format(&self, post: &PostData) -> Self::Output {
+        let message = create_matrix_message(post, &self.theme);
+        record_embeds("matrix", message.is_some() as usize);
+        message
+    }
+}
+
+pub struct SlackFormatter {
+    pub theme: EmbedTheme,
+}
+
+impl PostFormatter for SlackFormatter {
+    type Output = Option<Value>;
+
+    fn format(&self, post: &PostData) -> Self::Output {
+        let blocks = create_slack_blocks(post, &self.theme);
+        record_embeds("slack", blocks.is_some() as usize);
+        blocks
+    }
+}
+
+pub struct TelegramFormatter {
+    pub theme: EmbedTheme,
+    pub chat_id: String,
+}
+
+impl PostFormatter for TelegramFormatter {
+    type Output = Option<Vec<TelegramRequest>>;
+
+    fn format(&self, post: &PostData) -> Self::Output {
+        let requests = create_telegram_requests(post, &self.theme, &self.chat_id);
+        record_embeds("telegram", requests.as_ref().map_or(0, Vec::len));
+        requests
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use http::{Extensions, HeaderValue};
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Method, Request, Response, ResponseBuilderExt, StatusCode, Url};
+use reqwest_middleware::{Middleware, Next};
+use sqlx::{Pool, Postgres};
+use tracing::{debug, warn};
+
+use crate::error::ForumStreamError;
+
+// avatars and small json documents; anything bigger is passed through
+pub const DEFAULT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub url: String,
+    pub status: u16,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    fn into_response(self) -> Result<Response, ForumStreamError> {
+        let url = Url::parse(&self.url).map_err(|e| ForumStreamError::parse("cached url", e))?;
+        let mut builder = http::Response::builder().status(self.status).url(url);
+        if let Some(etag) = &self.etag {
+            builder = builder.header(ETAG, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            builder = builder.header(LAST_MODIFIED, last_modified);
+        }
+        if let Some(content_type) = &self.content_type {
+            builder = builder.header(CONTENT_TYPE, content_type);
+        }
+        let res = builder
+            .body(self.body)
+            .map_err(|e| ForumStreamError::parse("cached response", e))?;
+        Ok(Response::from(res))
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ResponseCache: Send + Sync {
+    async fn get(&self, url: &str) -> Result<Option<CachedResponse>, ForumStreamError>;
+    async fn put(&self, response: &CachedResponse) -> Result<(), ForumStreamError>;
+}
+
+// evicts the oldest entry once `capacity` urls are stored
+pub struct MemoryResponseCache {
+    capacity: usize,
+    entries: Mutex<(HashMap<String, CachedResponse>, VecDeque<String>)>,
+}
+
+impl MemoryResponseCache {
+    pub fn new(capacity: usize) -> Self {
+        MemoryResponseCache {
+            capacity: capacity.max(1),
+            entries: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ResponseCache for MemoryResponseCache {
+    async fn get(&self, url: &str) -> Result<Option<CachedResponse>, ForumStreamError> {
+        Ok(self.entries.lock().unwrap().0.get(url).cloned())
+    }
+
+    async fn put(&self, response: &CachedResponse) -> Result<(), ForumStreamError> {
+        let mut guard = self.entries.lock().unwrap();
+        let (entries, order) = &mut *guard;
+        if entries
+            .insert(response.url.clone(), response.clone())
+            .is_none()
+        {
+            order.push_back(response.url.clone());
+        }
+        while order.len() > self.capacity {
+            if let Some(old) = order.pop_front() {
+                entries.remove(&old);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct PgResponseCache {
+    pool: Pool<Postgres>,
+}
+
+impl PgResponseCache {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PgResponseCache { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ResponseCache for PgResponseCache {
+    async fn get(&self, url: &str) -> Result<Option<CachedResponse>, ForumStreamError> {
+        let row: Option<(i16, Option<String>, Option<String>, Option<String>, Vec<u8>)> =
+            sqlx::query_as(
+                r#"SELECT status, etag, last_modified, content_type, body
+                   FROM http_cache WHERE url = $1"#,
+            )
+            .bind(url)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(
+            |(status, etag, last_modified, content_type, body)| CachedResponse {
+                url: url.to_string(),
+                status: status as u16,
+                etag,
+                last_modified,
+                content_type,
+                body,
+            },
+        ))
+    }
+
+    async fn put(&self, response: &CachedResponse) -> Result<(), ForumStreamError> {
+        sqlx::query(
+            r#"INSERT INTO http_cache (url, status, etag, last_modified, content_type, body)
+               VALUES ($1, $2, $3, $4, $5, $6)
+               ON CONFLICT (url) DO UPDATE
+               SET status = EXCLUDED.status, etag = EXCLUDED.etag,
+                   last_modified = EXCLUDED.last_modified,
+                   content_type = EXCLUDED.content_type, body = EXCLUDED.body,
+                   stored_at = now()"#,
+        )
+        .bind(&response.url)
+        .bind(response.status as i16)
+        .bind(&response.etag)
+        .bind(&response.last_modified)
+        .bind(&response.content_type)
+        .bind(&response.body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+fn header(res: &Response, name: http::HeaderName) -> Option<String> {
+    res.headers().get(name)?.to_str().ok().map(String::from)
+}
+
+// caches GET responses that carry an ETag or Last-Modified and revalidates
+// them with If-None-Match / If-Modified-Since; a 304 is answered from the
+// cache. Add it before FlaresolverrMiddleware so challenge pages never reach
+// the cache and a revalidation still gets the clearance cookies.
+pub struct CacheMiddleware {
+    cache: Box<dyn ResponseCache>,
+    max_body_bytes: usize,
+}
+
+impl CacheMiddleware {
+    pub fn new(cache: impl ResponseCache + 'static) -> Self {
+        CacheMiddleware {
+            cache: Box::new(cache),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+
+    pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    // keyed by the requested url, not the one redirected to. The caller gets
+    // the live response back with all its headers, only the cache keeps the
+    // stripped down copy; cache failures only get logged
+    async fn store(&self, url: String, res: Response) -> reqwest_middleware::Result<Response> {
+        let etag = header(&res, ETAG);
+        let last_modified = header(&res, LAST_MODIFIED);
+        let too_big = res
+            .content_length()
+            .is_some_and(|len| len as usize > self.max_body_bytes);
+        if (etag.is_none() && last_modified.is_none()) || too_big {
+            return Ok(res);
+        }
+        let content_type = header(&res, CONTENT_TYPE);
+        let status = res.status();
+        let version = res.version();
+        let headers = res.headers().clone();
+        let final_url = res.url().clone();
+        let body = res.bytes().await?;
+
+        if body.len() <= self.max_body_bytes {
+            let entry = CachedResponse {
+                url,
+                status: status.as_u16(),
+                etag,
+                last_modified,
+                content_type,
+                body: body.to_vec(),
+            };
+            if let Err(e) = self.cache.put(&entry).await {
+                warn!(url = %entry.url, error = %e, "could not cache response");
+            }
+        }
+
+        let mut builder = http::Response::builder()
+            .status(status)
+            .version(version)
+            .url(final_url);
+        if let Some(h) = builder.headers_mut() {
+            *h = headers;
+        }
+        let live = builder
+            .body(body)
+            .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+        Ok(Response::from(live))
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for CacheMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if req.method() != Method::GET {
+            return next.run(req, extensions).await;
+        }
+        let url = req.url().to_string();
+        let cached = match self.cache.get(&url).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!(%url, error = %e, "response cache lookup failed");
+                None
+            }
+        };
+
+        if let Some(cached) = &cached {
+            let h = req.headers_mut();
+            if let Some(etag) = cached
+                .etag
+                .as_deref()
+                .and_then(|v| HeaderValue::from_str(v).ok())
+            {
+                h.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(since) = cached
+                .last_modified
+                .as_deref()
+                .and_then(|v| HeaderValue::from_str(v).ok())
+            {
+                h.insert(IF_MODIFIED_SINCE, since);
+            }
+        }
+
+        let res = next.run(req, extensions).await?;
+        match (res.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => {
+                debug!(%url, "served from cache");
+                cached
+                    .into_response()
+                    .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))
+            }
+            (StatusCode::OK, _) => self.store(url, res).await,
+            _ => Ok(res),
+        }
+    }
+}
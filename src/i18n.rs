@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// built-in strings; a locale only needs to override what it translates
+const ENGLISH: &[(&str, &str)] = &[
+    ("action.public_open", "Made this topic public"),
+    ("action.open_topic", "Converted this to a topic"),
+    ("action.private_topic", "Made this topic a personal message"),
+    ("action.split_topic", "Split this topic"),
+    ("action.invited_user", "Invited a user"),
+    ("action.invited_user.who", "Invited {who}"),
+    ("action.invited_group", "Invited a group"),
+    ("action.invited_group.who", "Invited group {who}"),
+    (
+        "action.user_left",
+        "A user removed themselves from this message",
+    ),
+    (
+        "action.user_left.who",
+        "{who} removed themselves from this message",
+    ),
+    ("action.removed_user", "Removed a user"),
+    ("action.removed_user.who", "Removed {who}"),
+    ("action.removed_group", "Removed a group"),
+    ("action.removed_group.who", "Removed {who} group"),
+    ("action.autobumped", "Automatically bumped"),
+    ("action.tags_changed", "Tags updated"),
+    ("action.category_changed", "Category updated"),
+    ("action.autoclosed.enabled", "Closed"),
+    ("action.closed.enabled", "Closed"),
+    ("action.autoclosed.disabled", "Opened"),
+    ("action.closed.disabled", "Opened"),
+    ("action.archived.enabled", "Archived"),
+    ("action.archived.disabled", "Unarchived"),
+    ("action.pinned.enabled", "Pinned"),
+    ("action.pinned.disabled", "Unpinned"),
+    ("action.pinned_globally.disabled", "Unpinned"),
+    ("action.pinned_globally.enabled", "Pinned globally"),
+    ("action.visible.enabled", "Listed"),
+    ("action.visible.disabled", "Unlisted"),
+    (
+        "action.banner.enabled",
+        "Made this a banner. It will appear at the top of every page until it is dismissed by the user.",
+    ),
+    (
+        "action.banner.disabled",
+        "Removed this banner. It will no longer appear at the top of every page.",
+    ),
+    ("action.forwarded", "Forwarded the above email"),
+    ("reply.replying_to", "⤷ replying to: {who}"),
+    ("reply.accessible", "Reply to {who}."),
+    ("reply.collapsed", "↳ replying to @{who}:"),
+    ("quote.quoting", "⤷ quoting: {who}"),
+    ("quote.collapsed", "↳ quoting @{who}:"),
+    ("quote.collapsed.anonymous", "↳ quoting:"),
+];
+
+fn english(key: &str) -> Option<&'static str> {
+    ENGLISH.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+// a forum locale as a flat key -> template map, e.g. loaded from
+// [theme.locale.strings] in the config; "{who}" is substituted
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Locale {
+    pub code: String,
+    pub strings: HashMap<String, String>,
+}
+
+impl Locale {
+    pub fn english() -> Self {
+        Locale {
+            code: String::from("en"),
+            strings: HashMap::new(),
+        }
+    }
+
+    pub fn from_toml_str(s: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn has(&self, key: &str) -> bool {
+        self.strings.contains_key(key) || english(key).is_some()
+    }
+
+    // falls back to English, then to empty
+    pub fn get(&self, key: &str) -> String {
+        match self.strings.get(key) {
+            Some(s) => s.clone(),
+            None => english(key).map(String::from).unwrap_or_default(),
+        }
+    }
+
+    pub fn format(&self, key: &str, who: &str) -> String {
+        self.get(key).replace("{who}", who)
+    }
+}
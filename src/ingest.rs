@@ -0,0 +1,3 @@
+// getting posts out of Discourse without the Pulsar bus
+pub mod poller;
+pub mod webhook;
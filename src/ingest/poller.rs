@@ -0,0 +1,352 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use discourse::model::{PostId, TopicId};
+use reqwest_middleware::ClientWithMiddleware;
+use serde_json::Value;
+use sqlx::{Pool, Postgres};
+use tokio::sync::mpsc::Sender;
+use tracing::{info, warn};
+
+use crate::{
+    error::{ForumStreamError, Result},
+    events::PostEvent,
+    metrics::record_catch_up_gap,
+};
+
+// /posts.json pages hold 20 posts; this bounds a catch-up after downtime
+const MAX_CATCH_UP_PAGES: usize = 25;
+const SEEN_CAPACITY: usize = 2048;
+// a bus poll that comes back empty quicker than this wasn't long-polling
+// (disabled on the forum, or a proxy in the way)
+const MIN_BUS_WAIT: Duration = Duration::from_secs(5);
+
+#[async_trait::async_trait]
+pub trait CursorStore: Send + Sync {
+    async fn load(&self, source: &str) -> Result<Option<u64>>;
+    async fn save(&self, source: &str, last_post_id: u64) -> Result<()>;
+}
+
+#[derive(Default)]
+pub struct MemoryCursorStore {
+    cursors: Mutex<HashMap<String, u64>>,
+}
+
+#[async_trait::async_trait]
+impl CursorStore for MemoryCursorStore {
+    async fn load(&self, source: &str) -> Result<Option<u64>> {
+        Ok(self.cursors.lock().unwrap().get(source).copied())
+    }
+
+    async fn save(&self, source: &str, last_post_id: u64) -> Result<()> {
+        self.cursors
+            .lock()
+            .unwrap()
+            .insert(source.to_string(), last_post_id);
+        Ok(())
+    }
+}
+
+pub struct PgCursorStore {
+    pool: Pool<Postgres>,
+}
+
+impl PgCursorStore {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        PgCursorStore { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl CursorStore for PgCursorStore {
+    async fn load(&self, source: &str) -> Result<Option<u64>> {
+        let row: Option<(i64,)> =
+            sqlx::query_as("SELECT last_post_id FROM ingest_cursors WHERE source = $1")
+                .bind(source)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.map(|(id,)| id as u64))
+    }
+
+    async fn save(&self, source: &str, last_post_id: u64) -> Result<()> {
+        sqlx::query(
+            r#"INSERT INTO ingest_cursors (source, last_post_id) VALUES ($1, $2)
+               ON CONFLICT (source) DO UPDATE
+               SET last_post_id = GREATEST(ingest_cursors.last_post_id, EXCLUDED.last_post_id),
+                   updated_at = now()"#,
+        )
+        .bind(source)
+        .bind(last_post_id as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollMode {
+    // GET /posts.json every interval
+    LatestPosts,
+    // long-poll the message bus /latest channel and read /posts.json when
+    // it reports activity; falls back to the interval if the bus is disabled
+    MessageBus,
+}
+
+struct Seen {
+    ids: HashSet<u64>,
+    order: VecDeque<u64>,
+}
+
+impl Seen {
+    // false if already seen
+    fn insert(&mut self, id: u64) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > SEEN_CAPACITY {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+        true
+    }
+}
+
+// tails a forum and emits PostEvent::Created for each new post, once
+pub struct Poller {
+    client: ClientWithMiddleware,
+    base_url: String,
+    mode: PollMode,
+    interval: Duration,
+    cursors: Box<dyn CursorStore>,
+    cursor: tokio::sync::Mutex<Option<u64>>,
+    // advanced by poll_once, persisted by save_cursor
+    unsaved: Mutex<Option<u64>>,
+    seen: Mutex<Seen>,
+    bus_client_id: String,
+    bus_position: Mutex<i64>,
+}
+
+fn id_of<T: serde::de::DeserializeOwned>(post: &Value, field: &str) -> Option<T> {
+    serde_json::from_value(post.get(field)?.clone()).ok()
+}
+
+impl Poller {
+    pub fn new(
+        client: ClientWithMiddleware,
+        base_url: &str,
+        cursors: impl CursorStore + 'static,
+    ) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Poller {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            mode: PollMode::LatestPosts,
+            interval: Duration::from_secs(30),
+            cursors: Box::new(cursors),
+            cursor: tokio::sync::Mutex::new(None),
+            unsaved: Mutex::new(None),
+            seen: Mutex::new(Seen {
+                ids: HashSet::new(),
+                order: VecDeque::new(),
+            }),
+            bus_client_id: format!("forum-stream-{nanos:x}"),
+            bus_position: Mutex::new(-1),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: PollMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    async fn latest_posts(&self, before: Option<u64>) -> Result<Vec<Value>> {
+        let mut url = format!("{}/posts.json", self.base_url);
+        if let Some(before) = before {
+            url.push_str(&format!("?before={before}"));
+        }
+        let body: Value = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let posts = body
+            .get("latest_posts")
+            .and_then(|p| p.as_array())
+            .ok_or(ForumStreamError::MissingField("latest_posts"))?;
+        Ok(posts.clone())
+    }
+
+    // new posts since the cursor, oldest first. The very first run only
+    // records the newest id instead of replaying the forum's history. The
+    // cursor isn't persisted until save_cursor, so call that once the events
+    // are handed off; a crash in between redelivers them instead of losing them.
+    pub async fn poll_once(&self) -> Result<Vec<PostEvent>> {
+        let mut cursor = self.cursor.lock().await;
+        if cursor.is_none() {
+            *cursor = self.cursors.load(&self.base_url).await?;
+        }
+
+        let mut fresh: Vec<Value> = Vec::new();
+        let mut before = None;
+        let mut reached_cursor = false;
+        for _ in 0..MAX_CATCH_UP_PAGES {
+            let page = self.latest_posts(before).await?;
+            let oldest = page.iter().filter_map(|p| id_of::<u64>(p, "id")).min();
+            reached_cursor = match (*cursor, oldest) {
+                (Some(cursor), Some(oldest)) => oldest <= cursor,
+                _ => true,
+            };
+            fresh.extend(page.into_iter().filter(|p| {
+                let id = id_of::<u64>(p, "id").unwrap_or_default();
+                cursor.is_none_or(|c| id > c)
+            }));
+            if reached_cursor || cursor.is_none() {
+                break;
+            }
+            before = oldest;
+        }
+        // posts between the cursor and the oldest page fetched are skipped;
+        // ids are forum wide so this is an upper bound on what was missed
+        if let (false, Some(cursor), Some(oldest)) = (reached_cursor, *cursor, before) {
+            let gap = oldest.saturating_sub(cursor + 1);
+            warn!(
+                source = %self.base_url,
+                cursor,
+                oldest,
+                gap,
+                "catch-up page limit reached, skipping posts"
+            );
+            record_catch_up_gap(&self.base_url, gap);
+        }
+        fresh.sort_by_key(|p| id_of::<u64>(p, "id").unwrap_or_default());
+
+        let Some(newest) = fresh.last().and_then(|p| id_of::<u64>(p, "id")) else {
+            return Ok(Vec::new());
+        };
+        let first_run = cursor.is_none();
+        *cursor = Some(newest);
+        *self.unsaved.lock().unwrap() = Some(newest);
+        if first_run {
+            info!(source = %self.base_url, cursor = newest, "poller starting from latest post");
+            return Ok(Vec::new());
+        }
+
+        let mut seen = self.seen.lock().unwrap();
+        let events = fresh
+            .iter()
+            .filter(|p| seen.insert(id_of::<u64>(p, "id").unwrap_or_default()))
+            .filter_map(|p| {
+                Some(PostEvent::Created {
+                    post_id: id_of::<PostId>(p, "id")?,
+                    topic_id: Some(id_of::<TopicId>(p, "topic_id")?),
+                })
+            })
+            .collect();
+        Ok(events)
+    }
+
+    pub async fn save_cursor(&self) -> Result<()> {
+        let Some(cursor) = self.unsaved.lock().unwrap().take() else {
+            return Ok(());
+        };
+        if let Err(e) = self.cursors.save(&self.base_url, cursor).await {
+            // kept for the next attempt unless a newer cursor replaced it
+            self.unsaved.lock().unwrap().get_or_insert(cursor);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    // true when the bus reported activity on /latest; errors when the bus
+    // isn't reachable so the caller can fall back to the interval
+    async fn wait_for_activity(&self) -> Result<bool> {
+        let position = *self.bus_position.lock().unwrap();
+        let url = format!("{}/message-bus/{}/poll", self.base_url, self.bus_client_id);
+        let messages: Value = self
+            .client
+            .post(&url)
+            .header("Dont-Chunk", "true")
+            .form(&[("/latest", position.to_string())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut activity = false;
+        for message in messages.as_array().into_iter().flatten() {
+            match message.get("channel").and_then(|c| c.as_str()) {
+                // sent on the first poll with the channel's current position
+                Some("/__status") => {
+                    if let Some(pos) = message["data"]["/latest"].as_i64() {
+                        *self.bus_position.lock().unwrap() = pos;
+                    }
+                }
+                Some("/latest") => {
+                    if let Some(id) = message.get("message_id").and_then(|m| m.as_i64()) {
+                        *self.bus_position.lock().unwrap() = id;
+                    }
+                    activity = true;
+                }
+                _ => {}
+            }
+        }
+        Ok(activity)
+    }
+
+    pub fn spawn(self: &Arc<Self>, events: Sender<PostEvent>) -> tokio::task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                match this.poll_once().await {
+                    Ok(batch) => {
+                        for event in batch {
+                            if events.send(event).await.is_err() {
+                                // receiver gone, nobody to ingest for
+                                return;
+                            }
+                        }
+                        if let Err(e) = this.save_cursor().await {
+                            warn!(source = %this.base_url, error = %e, "could not save cursor");
+                        }
+                    }
+                    Err(e) => warn!(source = %this.base_url, error = %e, "poll failed"),
+                }
+                match this.mode {
+                    PollMode::LatestPosts => tokio::time::sleep(this.interval).await,
+                    PollMode::MessageBus => loop {
+                        let started = Instant::now();
+                        match this.wait_for_activity().await {
+                            Ok(true) => break,
+                            Ok(false) if started.elapsed() >= MIN_BUS_WAIT => continue,
+                            Ok(false) => {
+                                // not long-polling; don't spin against the forum
+                                tokio::time::sleep(this.interval).await;
+                                break;
+                            }
+                            Err(e) => {
+                                warn!(source = %this.base_url, error = %e, "message bus poll failed");
+                                tokio::time::sleep(this.interval).await;
+                                break;
+                            }
+                        }
+                    },
+                }
+            }
+        })
+    }
+}
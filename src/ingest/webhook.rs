@@ -0,0 +1,158 @@
+use discourse::{
+    bundle::PostData,
+    model::{PostId, TopicId, post::Post, topic::Topic},
+};
+use hmac::{Hmac, Mac};
+use http::HeaderMap;
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::{
+    error::{ForumStreamError, Result},
+    events::PostEvent,
+    post_stream::TopicPager,
+};
+
+pub const EVENT_HEADER: &str = "X-Discourse-Event";
+pub const SIGNATURE_HEADER: &str = "X-Discourse-Event-Signature";
+pub const INSTANCE_HEADER: &str = "X-Discourse-Instance";
+pub const EVENT_ID_HEADER: &str = "X-Discourse-Event-Id";
+
+#[derive(Debug, Clone)]
+pub enum WebhookPayload {
+    Post(Box<Post>),
+    Topic(Box<Topic>),
+    Ping,
+    // event types we don't model (users, likes, ...)
+    Other(Value),
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    // e.g. post_created, post_edited, topic_created
+    pub event: String,
+    // the forum's base url
+    pub instance: Option<String>,
+    // increments per delivery; useful to drop redeliveries
+    pub event_id: Option<u64>,
+    pub payload: WebhookPayload,
+    raw: Value,
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &'static str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+// X-Discourse-Event-Signature is "sha256=" + hex HMAC of the raw body
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> Result<()> {
+    let hex_digest = signature
+        .strip_prefix("sha256=")
+        .ok_or(ForumStreamError::InvalidSignature)?;
+    let digest = hex::decode(hex_digest).map_err(|_| ForumStreamError::InvalidSignature)?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| ForumStreamError::Config(e.to_string()))?;
+    mac.update(body);
+    // constant time
+    mac.verify_slice(&digest)
+        .map_err(|_| ForumStreamError::InvalidSignature)
+}
+
+// `secret` is the one configured on the forum's webhook; None skips
+// verification, which is only reasonable behind something that already did it
+pub fn parse(headers: &HeaderMap, body: &[u8], secret: Option<&str>) -> Result<WebhookDelivery> {
+    if let Some(secret) = secret {
+        let signature = header(headers, SIGNATURE_HEADER)
+            .ok_or(ForumStreamError::MissingHeader(SIGNATURE_HEADER))?;
+        verify_signature(secret, body, signature)?;
+    }
+    let event = header(headers, EVENT_HEADER)
+        .ok_or(ForumStreamError::MissingHeader(EVENT_HEADER))?
+        .to_string();
+    let raw: Value = serde_json::from_slice(body)?;
+
+    let payload = if event == "ping" {
+        WebhookPayload::Ping
+    } else if let Some(post) = raw.get("post") {
+        let post = serde_json::from_value(post.clone())
+            .map_err(|e| ForumStreamError::parse("webhook post", e))?;
+        WebhookPayload::Post(Box::new(post))
+    } else if let Some(topic) = raw.get("topic") {
+        let topic = serde_json::from_value(topic.clone())
+            .map_err(|e| ForumStreamError::parse("webhook topic", e))?;
+        WebhookPayload::Topic(Box::new(topic))
+    } else {
+        WebhookPayload::Other(raw.clone())
+    };
+
+    Ok(WebhookDelivery {
+        event,
+        instance: header(headers, INSTANCE_HEADER).map(|i| i.trim_end_matches('/').to_string()),
+        event_id: header(headers, EVENT_ID_HEADER).and_then(|id| id.parse().ok()),
+        payload,
+        raw,
+    })
+}
+
+impl WebhookDelivery {
+    fn id_field<T: serde::de::DeserializeOwned>(&self, object: &str, field: &str) -> Option<T> {
+        let value = self.raw.get(object)?.get(field)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    pub fn post_id(&self) -> Option<PostId> {
+        self.id_field("post", "id")
+    }
+
+    pub fn topic_id(&self) -> Option<TopicId> {
+        self.id_field("post", "topic_id")
+            .or_else(|| self.id_field("topic", "id"))
+    }
+
+    // the bus event a Pulsar producer would have published for this delivery;
+    // None for events the pipeline doesn't act on
+    pub fn post_event(&self) -> Option<PostEvent> {
+        match self.event.as_str() {
+            "post_created" => Some(PostEvent::Created {
+                post_id: self.post_id()?,
+                topic_id: Some(self.topic_id()?),
+            }),
+            "post_edited" => Some(PostEvent::Edited {
+                post_id: self.post_id()?,
+                topic_id: Some(self.topic_id()?),
+            }),
+            "post_destroyed" => Some(PostEvent::Deleted {
+                post_id: self.post_id()?,
+                deleted_by: None,
+            }),
+            "post_recovered" => Some(PostEvent::Recovered {
+                post_id: self.post_id()?,
+                topic_id: Some(self.topic_id()?),
+            }),
+            _ => None,
+        }
+    }
+
+    // the payload lacks the topic, category and replied-to post a PostData
+    // carries, so the post is fetched back through the same pager backfills use
+    pub async fn post_data<P: TopicPager>(&self, pager: &P) -> anyhow::Result<Option<PostData>> {
+        let (Some(post_id), Some(topic_id)) = (self.post_id(), self.topic_id()) else {
+            return Ok(None);
+        };
+        let posts = pager.fetch_posts(topic_id, &[post_id]).await?;
+        Ok(posts.into_iter().next())
+    }
+
+    pub fn post(&self) -> Option<&Post> {
+        match &self.payload {
+            WebhookPayload::Post(post) => Some(post),
+            _ => None,
+        }
+    }
+
+    pub fn topic(&self) -> Option<&Topic> {
+        match &self.payload {
+            WebhookPayload::Topic(topic) => Some(topic),
+            _ => None,
+        }
+    }
+}
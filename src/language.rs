@@ -0,0 +1,73 @@
+use discourse::bundle::PostData;
+use serde::{Deserialize, Serialize};
+use serenity::all::ChannelId;
+use whatlang::{Detector, Lang};
+
+use crate::md::html_to_md;
+
+// ISO 639-3 code ("fra", "eng", ...) if detection is reliable enough
+pub fn detect_language(text: &str, min_confidence: f64) -> Option<String> {
+    let info = Detector::new().detect(text)?;
+    if !info.is_reliable() && info.confidence() < min_confidence {
+        return None;
+    }
+    Some(info.lang().code().to_string())
+}
+
+pub fn detect_post_language(post_data: &PostData, min_confidence: f64) -> Option<String> {
+    let md = html_to_md(&post_data.post.cooked);
+    detect_language(&md, min_confidence)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LanguageRoute {
+    // ISO 639-3 codes or English names, e.g. "fra" or "French"
+    pub languages: Vec<String>,
+    pub channel_id: ChannelId,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LanguageRouting {
+    pub routes: Vec<LanguageRoute>,
+    pub default_channel: Option<ChannelId>,
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f64,
+}
+
+fn default_min_confidence() -> f64 {
+    0.5
+}
+
+fn matches_language(wanted: &str, code: &str) -> bool {
+    if wanted.eq_ignore_ascii_case(code) {
+        return true;
+    }
+    match Lang::from_code(code) {
+        Some(lang) => lang.eng_name().eq_ignore_ascii_case(wanted),
+        None => false,
+    }
+}
+
+impl LanguageRouting {
+    pub fn is_language(&self, text: &str, wanted: &str) -> bool {
+        match detect_language(text, self.min_confidence) {
+            Some(code) => matches_language(wanted, &code),
+            None => false,
+        }
+    }
+
+    pub fn route(&self, text: &str) -> Option<ChannelId> {
+        if let Some(code) = detect_language(text, self.min_confidence) {
+            for route in &self.routes {
+                if route.languages.iter().any(|l| matches_language(l, &code)) {
+                    return Some(route.channel_id);
+                }
+            }
+        }
+        self.default_channel
+    }
+
+    pub fn route_post(&self, post_data: &PostData) -> Option<ChannelId> {
+        self.route(&html_to_md(&post_data.post.cooked))
+    }
+}
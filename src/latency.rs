@@ -0,0 +1,109 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::ntfy;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LatencySlo {
+    pub p95_ms: u64,
+    // number of recent deliveries the percentiles are computed over
+    #[serde(default = "default_window")]
+    pub window: usize,
+    #[serde(default = "default_cooldown_secs")]
+    pub alert_cooldown_secs: u64,
+    pub alert_topic: String,
+}
+
+fn default_window() -> usize {
+    200
+}
+
+fn default_cooldown_secs() -> u64 {
+    900
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+}
+
+#[derive(Default)]
+struct TenantLatency {
+    samples: VecDeque<u64>,
+    last_alert: Option<Instant>,
+}
+
+pub struct LatencyTracker {
+    slo: LatencySlo,
+    tenants: HashMap<String, TenantLatency>,
+}
+
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+impl LatencyTracker {
+    pub fn new(slo: LatencySlo) -> Self {
+        LatencyTracker {
+            slo,
+            tenants: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, tenant: &str, source_at: DateTime<Utc>, delivered_at: DateTime<Utc>) {
+        let ms = (delivered_at - source_at).num_milliseconds().max(0) as u64;
+        let window = self.slo.window.max(1);
+        let entry = self.tenants.entry(tenant.to_string()).or_default();
+        entry.samples.push_back(ms);
+        while entry.samples.len() > window {
+            entry.samples.pop_front();
+        }
+    }
+
+    pub fn summary(&self, tenant: &str) -> Option<LatencySummary> {
+        let entry = self.tenants.get(tenant)?;
+        let mut sorted: Vec<u64> = entry.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        Some(LatencySummary {
+            samples: sorted.len(),
+            p50_ms: percentile(&sorted, 50.0),
+            p95_ms: percentile(&sorted, 95.0),
+        })
+    }
+
+    // alerts at most once per cooldown so a slow period doesn't flood ntfy
+    pub async fn check_slo(&mut self, tenant: &str) -> bool {
+        let Some(summary) = self.summary(tenant) else {
+            return false;
+        };
+        if summary.p95_ms <= self.slo.p95_ms {
+            return false;
+        }
+        let cooldown = Duration::from_secs(self.slo.alert_cooldown_secs);
+        let Some(entry) = self.tenants.get_mut(tenant) else {
+            return false;
+        };
+        if entry.last_alert.is_some_and(|t| t.elapsed() < cooldown) {
+            return false;
+        }
+        entry.last_alert = Some(Instant::now());
+        ntfy(
+            &format!(
+                "{tenant} delivery latency p95 {}ms exceeds SLO {}ms (p50 {}ms, {} samples)",
+                summary.p95_ms, self.slo.p95_ms, summary.p50_ms, summary.samples
+            ),
+            &self.slo.alert_topic,
+        )
+        .await;
+        true
+    }
+}
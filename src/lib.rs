@@ -2,4 +2,70 @@ pub mod discord;
 pub mod utils;
 pub mod md;
 pub mod flaresolverr_middleware;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod metadata;
+pub mod theme;
+pub mod preflight;
+pub mod reactions;
+pub mod template;
+pub mod edits;
+pub mod language;
+pub mod events;
+pub mod usage;
+pub mod bus;
+pub mod pipeline;
+pub mod backfill;
+#[cfg(feature = "preview-server")]
+pub mod preview;
+pub mod channel_topic;
+pub mod pause;
+pub mod emoji;
+pub mod poll;
+pub mod attachments;
+pub mod latency;
+pub mod shortener;
+pub mod webhook;
+pub mod matrix;
+pub mod fixtures;
+pub mod telegram;
+pub mod slack;
+pub mod wiki;
+pub mod formatter;
+pub mod rss_source;
+pub mod feed;
+pub mod notification_level;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod envelope;
+#[cfg(feature = "avro")]
+pub mod avro_codec;
+pub mod pulsar_ext;
+pub mod mapping_store;
+pub mod error;
+pub mod clearance_store;
+pub mod metrics;
+#[cfg(feature = "ntfy-layer")]
+pub mod ntfy_layer;
+pub mod notifier;
+pub mod sanitize;
+pub mod filter;
+pub mod moderation;
+pub mod i18n;
+pub mod normalize;
+#[cfg(feature = "testkit")]
+pub mod md_harness;
+pub mod post_stream;
+pub mod ingest;
+pub mod rate_limit;
+pub mod http_cache;
+pub mod robots;
+pub mod config;
+pub mod config_watch;
+pub mod router;
+pub mod content_filter;
+pub mod dedupe;
+pub mod outbox;
+pub mod send_queue;
+pub mod reverse;
+pub mod md_to_discourse;
+pub mod search;
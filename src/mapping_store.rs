@@ -0,0 +1,144 @@
+use serenity::all::{ChannelId, MessageId};
+use sqlx::{Pool, Postgres};
+
+use crate::discord::{DiscordMapping, TopicThreadMapping};
+
+// Discord snowflakes fit in 63 bits, so they round-trip through BIGINT
+fn to_db(id: u64) -> i64 {
+    id as i64
+}
+
+fn from_db(id: i64) -> u64 {
+    id as u64
+}
+
+pub struct MappingStore {
+    pool: Pool<Postgres>,
+}
+
+impl MappingStore {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        MappingStore { pool }
+    }
+
+    pub async fn insert(
+        &self,
+        post_id: i64,
+        message_id: MessageId,
+        channel_id: Option<ChannelId>,
+    ) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO discord_mappings (post_id, discord_message_id, discord_channel_id)
+               VALUES ($1, $2, $3)
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(post_id)
+        .bind(to_db(message_id.get()))
+        .bind(channel_id.map(|c| to_db(c.get())))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_mapping(&self, mapping: &DiscordMapping) -> anyhow::Result<()> {
+        let post_id: i64 = serde_json::from_value(serde_json::to_value(&mapping.post_id)?)?;
+        self.insert(post_id, mapping.discord_message_id, None).await
+    }
+
+    // a post can span several messages (text + image overflow), oldest first
+    pub async fn messages_for_post(&self, post_id: i64) -> anyhow::Result<Vec<MessageId>> {
+        let rows: Vec<i64> = sqlx::query_scalar(
+            "SELECT discord_message_id FROM discord_mappings WHERE post_id = $1 ORDER BY created_at",
+        )
+        .bind(post_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|id| MessageId::new(from_db(id)))
+            .collect())
+    }
+
+    // (post_id, message, channel) for every message of the given posts
+    pub async fn messages_for_posts(
+        &self,
+        post_ids: &[i64],
+    ) -> anyhow::Result<Vec<(i64, MessageId, Option<ChannelId>)>> {
+        let rows: Vec<(i64, i64, Option<i64>)> = sqlx::query_as(
+            r#"SELECT post_id, discord_message_id, discord_channel_id FROM discord_mappings
+               WHERE post_id = ANY($1) ORDER BY post_id, created_at"#,
+        )
+        .bind(post_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(post_id, message_id, channel_id)| {
+                (
+                    post_id,
+                    MessageId::new(from_db(message_id)),
+                    channel_id.map(|c| ChannelId::new(from_db(c))),
+                )
+            })
+            .collect())
+    }
+
+    pub async fn message_for_post(&self, post_id: i64) -> anyhow::Result<Option<MessageId>> {
+        Ok(self.messages_for_post(post_id).await?.into_iter().next())
+    }
+
+    pub async fn post_for_message(&self, message_id: MessageId) -> anyhow::Result<Option<i64>> {
+        let post_id: Option<i64> = sqlx::query_scalar(
+            "SELECT post_id FROM discord_mappings WHERE discord_message_id = $1 LIMIT 1",
+        )
+        .bind(to_db(message_id.get()))
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(post_id)
+    }
+
+    pub async fn delete_post(&self, post_id: i64) -> anyhow::Result<u64> {
+        let result = sqlx::query("DELETE FROM discord_mappings WHERE post_id = $1")
+            .bind(post_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn insert_thread(&self, topic_id: i64, thread_id: ChannelId) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"INSERT INTO topic_threads (topic_id, discord_thread_id)
+               VALUES ($1, $2)
+               ON CONFLICT (topic_id) DO UPDATE SET discord_thread_id = EXCLUDED.discord_thread_id"#,
+        )
+        .bind(topic_id)
+        .bind(to_db(thread_id.get()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn insert_thread_mapping(&self, mapping: &TopicThreadMapping) -> anyhow::Result<()> {
+        let topic_id: i64 = serde_json::from_value(serde_json::to_value(&mapping.topic_id)?)?;
+        self.insert_thread(topic_id, mapping.discord_thread_id)
+            .await
+    }
+
+    pub async fn thread_for_topic(&self, topic_id: i64) -> anyhow::Result<Option<ChannelId>> {
+        let thread: Option<i64> =
+            sqlx::query_scalar("SELECT discord_thread_id FROM topic_threads WHERE topic_id = $1")
+                .bind(topic_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(thread.map(|id| ChannelId::new(from_db(id))))
+    }
+
+    pub async fn topic_for_thread(&self, thread_id: ChannelId) -> anyhow::Result<Option<i64>> {
+        let topic: Option<i64> =
+            sqlx::query_scalar("SELECT topic_id FROM topic_threads WHERE discord_thread_id = $1")
+                .bind(to_db(thread_id.get()))
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(topic)
+    }
+}
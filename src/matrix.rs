@@ -0,0 +1,174 @@
+use discourse::bundle::PostData;
+use scraper::{ElementRef, Html, Node};
+use serde::Serialize;
+
+use crate::{
+    discord::extract_imgs_excluding_class, formatter::PostParts, theme::EmbedTheme,
+    utils::trim_to_n_chars,
+};
+
+// tags Matrix clients are expected to render in formatted_body
+const ALLOWED_TAGS: &[&str] = &[
+    "font",
+    "del",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "p",
+    "a",
+    "ul",
+    "ol",
+    "sup",
+    "sub",
+    "li",
+    "b",
+    "i",
+    "u",
+    "strong",
+    "em",
+    "strike",
+    "code",
+    "hr",
+    "br",
+    "div",
+    "table",
+    "thead",
+    "tbody",
+    "tr",
+    "th",
+    "td",
+    "caption",
+    "pre",
+    "span",
+    "details",
+    "summary",
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MatrixMessage {
+    pub msgtype: String,
+    pub body: String,
+    pub format: String,
+    pub formatted_body: String,
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn sanitize_children(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape_html(text)),
+            Node::Element(_) => {
+                if let Some(el) = ElementRef::wrap(child) {
+                    sanitize_element(el, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn sanitize_element(element: ElementRef, out: &mut String) {
+    let value = element.value();
+    let name = value.name();
+    if value
+        .attr("class")
+        .is_some_and(|c| c.split_whitespace().any(|c| c == "avatar"))
+    {
+        return;
+    }
+    match name {
+        // Matrix only renders mxc:// images, so link to the original instead
+        "img" => {
+            if let Some(src) = value.attr("src") {
+                let alt = value.attr("alt").unwrap_or("image");
+                out.push_str(&format!(
+                    r#"<a href="{}">{}</a>"#,
+                    escape_html(src),
+                    escape_html(alt)
+                ));
+            }
+        }
+        "a" => {
+            match value.attr("href") {
+                Some(href) => out.push_str(&format!(r#"<a href="{}">"#, escape_html(href))),
+                None => out.push_str("<a>"),
+            }
+            sanitize_children(element, out);
+            out.push_str("</a>");
+        }
+        "br" | "hr" => out.push_str(&format!("<{name}>")),
+        _ if ALLOWED_TAGS.contains(&name) => {
+            out.push_str(&format!("<{name}>"));
+            sanitize_children(element, out);
+            out.push_str(&format!("</{name}>"));
+        }
+        // unknown wrappers (aside, section, ...) are flattened
+        _ => sanitize_children(element, out),
+    }
+}
+
+pub fn sanitize_for_matrix(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    sanitize_children(fragment.root_element(), &mut out);
+    out
+}
+
+pub fn create_matrix_message(post_data: &PostData, theme: &EmbedTheme) -> Option<MatrixMessage> {
+    let PostParts {
+        title,
+        url,
+        author_name,
+        author_url,
+        content,
+        ..
+    } = PostParts::from_post_data(post_data, theme)?;
+
+    let body = format!(
+        "{title} — {author_name}\n{}\n{url}",
+        trim_to_n_chars(&content, 4000)
+    );
+
+    let mut html = format!(
+        r#"<p><strong><a href="{}">{}</a></strong> by <a href="{}">{}</a></p>"#,
+        escape_html(&url),
+        escape_html(&title),
+        escape_html(&author_url),
+        escape_html(&author_name),
+    );
+    if let Some(replying_to) = &post_data.replying_to_post {
+        html.push_str(&format!(
+            "<blockquote>{}<br>— {}</blockquote>",
+            sanitize_for_matrix(&replying_to.cooked),
+            escape_html(&replying_to.username)
+        ));
+    }
+    html.push_str(&sanitize_for_matrix(&post_data.post.cooked));
+
+    // admin actions are informational, not conversation
+    let msgtype = match post_data.post.post_type {
+        3 => "m.notice",
+        _ => "m.text",
+    };
+    Some(MatrixMessage {
+        msgtype: String::from(msgtype),
+        body,
+        format: String::from("org.matrix.custom.html"),
+        formatted_body: html,
+    })
+}
+
+// for bridges that upload images to the media repo and send m.image events
+pub fn get_matrix_image_urls(post_data: &PostData) -> Vec<String> {
+    extract_imgs_excluding_class(&post_data.post.cooked, "avatar")
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use html2md::Handle;
 use html2md::NodeData;
@@ -11,8 +12,15 @@ use html2md::TagHandlerFactory;
 use html2md::common::get_tag_attr;
 use html2md::walk;
 
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::emoji::display_emoji;
+use crate::i18n::Locale;
+use crate::normalize::normalize;
+use crate::poll::{PollType, selection_rule};
+use crate::sanitize::escape_markdown;
+
 #[derive(Default)]
 pub struct IgnoreHandler;
 
@@ -33,9 +41,19 @@ impl TagHandlerFactory for IgnoreFactory {
     }
 }
 
+// Discourse emoji are <img class="emoji" alt=":smile:">
+fn emoji_text(tag: &Handle) -> Option<String> {
+    if !has_class(tag, "emoji") {
+        return None;
+    }
+    let code = get_tag_attr(tag, "title").or_else(|| get_tag_attr(tag, "alt"))?;
+    Some(display_emoji(&code))
+}
+
 #[derive(Default)]
 pub struct CustomImgHandler {
     block_mode: bool,
+    options: Arc<MdOptions>,
 }
 
 impl TagHandler for CustomImgHandler {
@@ -48,37 +66,127 @@ impl TagHandler for CustomImgHandler {
             }
         }
 
-        // skip avatar images
-        if let Some(class) = get_tag_attr(tag, "class") {
-            if class == "avatar" {
-                return;
-            }
+        if self.options.skips_image(tag) {
+            return;
         }
 
-        printer.append_str("Image\n");
+        if let Some(emoji) = emoji_text(tag) {
+            printer.append_str(&emoji);
+            return;
+        }
+
+        printer.append_str(&format!("{}\n", self.render(tag)));
     }
 
     fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
 }
 
-pub struct CustomImgFactory;
+impl CustomImgHandler {
+    fn render(&self, tag: &Handle) -> String {
+        let options = &self.options;
+        let placeholder = &options.image_placeholder;
+        let alt = get_tag_attr(tag, "alt")
+            .map(|a| escape_markdown(a.trim()))
+            .filter(|a| !a.is_empty());
+        // lightbox images are already wrapped in a link to the full upload
+        let src = get_tag_attr(tag, "src")
+            .filter(|_| !inside_link(tag))
+            .map(|src| absolute_url(&src, options.base_url.as_ref()));
+        match options.image_style {
+            // with a base url the image is linked instead of just labelled
+            ImageStyle::Placeholder => match src.filter(|_| options.base_url.is_some()) {
+                Some(src) => format!("[{placeholder}]({src})"),
+                None => placeholder.clone(),
+            },
+            ImageStyle::Link => {
+                let label = match alt {
+                    Some(alt) => format!("{}: {alt}", placeholder.to_lowercase()),
+                    None => placeholder.to_lowercase(),
+                };
+                match src {
+                    Some(src) => format!("[{label}]({src})"),
+                    None => label,
+                }
+            }
+            ImageStyle::AltText => alt.unwrap_or_else(|| placeholder.clone()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CustomImgFactory {
+    pub options: Arc<MdOptions>,
+}
 impl TagHandlerFactory for CustomImgFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        return Box::new(CustomImgHandler::default());
+        return Box::new(CustomImgHandler {
+            options: self.options.clone(),
+            ..Default::default()
+        });
+    }
+}
+
+// forum-relative links (/t/123, /u/foo, /uploads/...) resolved against the
+// forum; absolute urls and in-page #anchors are left alone
+pub fn absolute_url(raw: &str, base: Option<&Url>) -> String {
+    let Some(base) = base else {
+        return raw.to_string();
+    };
+    if raw.is_empty() || raw.starts_with('#') || Url::parse(raw).is_ok() {
+        return raw.to_string();
+    }
+    match base.join(raw) {
+        Ok(url) => url.to_string(),
+        Err(_) => raw.to_string(),
     }
 }
 
 #[derive(Default)]
 pub struct CustomQuoteHandler {
     start_pos: usize,
+    // nested past max_quote_depth
+    omitted: bool,
+    options: Arc<MdOptions>,
+}
+
+pub const OMITTED_QUOTES: &str = "[earlier quotes omitted]";
+
+// 1 for a top-level quote; Discourse nests <aside class="quote"><blockquote>
+// so only blockquotes are counted
+fn quote_depth(tag: &Handle) -> usize {
+    let mut depth = 1;
+    let mut current = parent(tag);
+    while let Some(node) = current {
+        if matches!(
+            element_name(&node).as_deref(),
+            Some("blockquote") | Some("q")
+        ) {
+            depth += 1;
+        }
+        current = parent(&node);
+    }
+    depth
 }
 
 impl TagHandler for CustomQuoteHandler {
-    fn handle(&mut self, _tag: &Handle, printer: &mut StructuredPrinter) {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         self.start_pos = printer.data.len();
+        if let Some(marker) = self.options.quote_omitted(tag) {
+            self.omitted = true;
+            if marker {
+                printer.append_str(&format!("\n{OMITTED_QUOTES}\n"));
+            }
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        self.omitted
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if self.omitted {
+            return;
+        }
         let quote_content = &printer.data[self.start_pos..];
         let mut quoted = String::from("\n");
 
@@ -95,10 +203,51 @@ impl TagHandler for CustomQuoteHandler {
     }
 }
 
-pub struct CustomQuoteFactory;
+#[derive(Default)]
+pub struct CustomQuoteFactory {
+    pub options: Arc<MdOptions>,
+}
 impl TagHandlerFactory for CustomQuoteFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        return Box::new(CustomQuoteHandler::default());
+        return Box::new(CustomQuoteHandler {
+            options: self.options.clone(),
+            ..Default::default()
+        });
+    }
+}
+
+pub trait MentionResolver: Send + Sync {
+    // Discord user ID for a forum username
+    fn resolve(&self, username: &str) -> Option<u64>;
+}
+
+impl<F> MentionResolver for F
+where
+    F: Fn(&str) -> Option<u64> + Send + Sync,
+{
+    fn resolve(&self, username: &str) -> Option<u64> {
+        self(username)
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct MentionTable {
+    users: HashMap<String, u64>,
+}
+
+impl MentionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, username: &str, discord_id: u64) {
+        self.users.insert(username.to_lowercase(), discord_id);
+    }
+}
+
+impl MentionResolver for MentionTable {
+    fn resolve(&self, username: &str) -> Option<u64> {
+        self.users.get(&username.to_lowercase()).copied()
     }
 }
 
@@ -108,6 +257,10 @@ pub struct CustomAnchorHandler {
     url: String,
     emit_unchanged: bool,
     is_mention: bool,
+    // footnote "↩︎" links back into the post
+    is_backref: bool,
+    mentions: Option<Arc<dyn MentionResolver>>,
+    options: Arc<MdOptions>,
 }
 
 fn clean_url(raw: &str) -> String {
@@ -145,6 +298,10 @@ impl TagHandler for CustomAnchorHandler {
             self.emit_unchanged = true;
         }
 
+        if has_class(tag, "footnote-backref") {
+            self.is_backref = true;
+        }
+
         self.start_pos = printer.data.len();
 
         // try to extract a hyperlink
@@ -161,9 +318,14 @@ impl TagHandler for CustomAnchorHandler {
             }
             _ => String::new(),
         };
+        self.url = absolute_url(&self.url, self.options.base_url.as_ref());
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if self.is_backref {
+            printer.data.truncate(self.start_pos);
+            return;
+        }
         let end_pos = printer.data.len();
         let captured = &printer.data[self.start_pos..end_pos];
         let clean = clean_url(captured);
@@ -175,6 +337,15 @@ impl TagHandler for CustomAnchorHandler {
             }
         }
         if self.is_mention {
+            if let Some(resolver) = self.mentions.as_ref().or(self.options.mentions.as_ref()) {
+                let username = captured.trim().trim_start_matches('@').to_string();
+                let replacement = match resolver.resolve(&username) {
+                    Some(id) => format!("<@{id}>"),
+                    None => format!("**@{username}**"),
+                };
+                printer.data.truncate(self.start_pos);
+                printer.append_str(&replacement);
+            }
             return;
         }
         if !self.emit_unchanged {
@@ -185,10 +356,28 @@ impl TagHandler for CustomAnchorHandler {
     }
 }
 
-pub struct CustomAnchorFactory;
+#[derive(Default)]
+pub struct CustomAnchorFactory {
+    pub options: Arc<MdOptions>,
+}
 impl TagHandlerFactory for CustomAnchorFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        return Box::new(CustomAnchorHandler::default());
+        return Box::new(CustomAnchorHandler {
+            options: self.options.clone(),
+            ..Default::default()
+        });
+    }
+}
+
+pub struct MentionAnchorFactory {
+    pub resolver: Arc<dyn MentionResolver>,
+}
+impl TagHandlerFactory for MentionAnchorFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(CustomAnchorHandler {
+            mentions: Some(self.resolver.clone()),
+            ..Default::default()
+        });
     }
 }
 
@@ -199,28 +388,119 @@ impl TagHandlerFactory for DummyHandlerFactory {
     }
 }
 
+#[derive(Default)]
+pub struct SpoilerImgHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for SpoilerImgHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if self.options.skips_image(tag) {
+            return;
+        }
+
+        if let Some(emoji) = emoji_text(tag) {
+            printer.append_str(&emoji);
+            return;
+        }
+
+        // link instead of an inline image so it stays hidden until clicked
+        match get_tag_attr(tag, "src") {
+            Some(src) => {
+                let src = absolute_url(&src, self.options.base_url.as_ref());
+                printer.append_str(&format!("[spoiler]({src})\n"))
+            }
+            None => printer.append_str(&format!("{}\n", self.options.image_placeholder)),
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
+
+#[derive(Default)]
+pub struct SpoilerImgFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for SpoilerImgFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(SpoilerImgHandler {
+            options: self.options.clone(),
+        });
+    }
+}
+
 #[derive(Default)]
 pub struct DetailsHandler {
     start_pos: usize,
+    options: Arc<MdOptions>,
 }
 
 impl TagHandler for DetailsHandler {
-    fn handle(&mut self, _tag: &Handle, printer: &mut StructuredPrinter) {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
         self.start_pos = printer.data.len();
+
+        let mut custom: HashMap<String, Box<dyn TagHandlerFactory>> = HashMap::new();
+        custom.insert(
+            String::from("img"),
+            Box::new(SpoilerImgFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("blockquote"),
+            Box::new(CustomQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("q"),
+            Box::new(CustomQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("a"),
+            Box::new(CustomAnchorFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(String::from("summary"), Box::new(DummyHandlerFactory));
+        custom.insert(
+            String::from("details"),
+            Box::new(DetailsFactory {
+                options: self.options.clone(),
+            }),
+        );
+
+        walk(tag, printer, &custom);
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
         let content = &printer.data[self.start_pos..].to_string();
 
         printer.data.truncate(self.start_pos);
-        printer.append_str(&format!("||{}||", &content));
+        match self.options.spoiler_style {
+            SpoilerStyle::Bars => printer.append_str(&format!("||{}||", &content)),
+            SpoilerStyle::Plain => printer.append_str(content),
+            SpoilerStyle::Placeholder => printer.append_str("[spoiler]"),
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        return true;
     }
 }
 
-pub struct DetailsFactory;
+#[derive(Default)]
+pub struct DetailsFactory {
+    pub options: Arc<MdOptions>,
+}
 impl TagHandlerFactory for DetailsFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        return Box::new(DetailsHandler::default());
+        return Box::new(DetailsHandler {
+            options: self.options.clone(),
+            ..Default::default()
+        });
     }
 }
 fn escape_discord_markdown(s: &str) -> String {
@@ -237,12 +517,574 @@ fn escape_discord_markdown(s: &str) -> String {
     escaped
 }
 
+fn element_name(handle: &Handle) -> Option<String> {
+    match handle.data {
+        NodeData::Element { ref name, .. } => Some(name.local.to_string()),
+        _ => None,
+    }
+}
+
+fn parent(handle: &Handle) -> Option<Handle> {
+    let weak = handle.parent.take();
+    let parent = weak.as_ref().and_then(|w| w.upgrade());
+    handle.parent.set(weak);
+    parent
+}
+
+fn inside_link(handle: &Handle) -> bool {
+    let mut current = parent(handle);
+    while let Some(node) = current {
+        if element_name(&node).as_deref() == Some("a") {
+            return true;
+        }
+        current = parent(&node);
+    }
+    false
+}
+
+fn has_class(handle: &Handle, class: &str) -> bool {
+    get_tag_attr(handle, "class").is_some_and(|c| c.split_whitespace().any(|c| c == class))
+}
+
+fn find_first(handle: &Handle, pred: &dyn Fn(&Handle) -> bool) -> Option<Handle> {
+    for child in handle.children.borrow().iter() {
+        if pred(child) {
+            return Some(child.clone());
+        }
+        if let Some(found) = find_first(child, pred) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn text_content(handle: &Handle) -> String {
+    let mut ret = String::new();
+    if let NodeData::Text { ref contents } = handle.data {
+        ret.push_str(&contents.borrow());
+    }
+    for child in handle.children.borrow().iter() {
+        ret.push_str(&text_content(child));
+    }
+    ret
+}
+
+#[derive(Default)]
+pub struct OneboxHandler;
+
+impl TagHandler for OneboxHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let heading = find_first(tag, &|h| {
+            matches!(element_name(h).as_deref(), Some("h3") | Some("h4"))
+        });
+        let title = heading
+            .as_ref()
+            .map(|h| text_content(h).trim().to_string())
+            .filter(|t| !t.is_empty());
+        let url = get_tag_attr(tag, "data-onebox-src")
+            .or_else(|| {
+                heading
+                    .as_ref()
+                    .and_then(|h| find_first(h, &|a| element_name(a).as_deref() == Some("a")))
+                    .and_then(|a| get_tag_attr(&a, "href"))
+            })
+            .or_else(|| {
+                find_first(tag, &|a| element_name(a).as_deref() == Some("a"))
+                    .and_then(|a| get_tag_attr(&a, "href"))
+            });
+        let summary = find_first(tag, &|p| element_name(p).as_deref() == Some("p"))
+            .map(|p| {
+                text_content(&p)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|s| !s.is_empty());
+
+        let mut rendered = String::from("\n");
+        match (title, &url) {
+            (Some(title), Some(url)) => rendered.push_str(&format!("> **[{title}]({url})**\n")),
+            (Some(title), None) => rendered.push_str(&format!("> **{title}**\n")),
+            (None, Some(url)) => rendered.push_str(&format!("> {url}\n")),
+            (None, None) => {}
+        }
+        if let Some(summary) = summary {
+            let summary = if summary.chars().count() > 200 {
+                let mut trimmed: String = summary.chars().take(199).collect();
+                trimmed.push('…');
+                trimmed
+            } else {
+                summary
+            };
+            rendered.push_str(&format!("> {summary}\n"));
+        }
+        printer.append_str(&rendered);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+const TABLE_COLUMN_MAX: usize = 20;
+
+fn collect_rows(handle: &Handle, rows: &mut Vec<(bool, Vec<String>)>) {
+    for child in handle.children.borrow().iter() {
+        match element_name(child).as_deref() {
+            Some("tr") => {
+                let mut cells = Vec::new();
+                let mut header = false;
+                for cell in child.children.borrow().iter() {
+                    match element_name(cell).as_deref() {
+                        Some("th") => {
+                            header = true;
+                            cells.push(text_content(cell));
+                        }
+                        Some("td") => cells.push(text_content(cell)),
+                        _ => {}
+                    }
+                }
+                rows.push((header, cells));
+            }
+            _ => collect_rows(child, rows),
+        }
+    }
+}
+
+fn fit_cell(text: &str, width: usize) -> String {
+    let text: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let len = text.chars().count();
+    if len > width {
+        let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        cut.push('…');
+        cut
+    } else {
+        format!("{text}{}", " ".repeat(width - len))
+    }
+}
+
+#[derive(Default)]
+pub struct TableHandler;
+
+impl TagHandler for TableHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let mut rows = Vec::new();
+        collect_rows(tag, &mut rows);
+        let columns = rows.iter().map(|(_, r)| r.len()).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        let mut widths = vec![1; columns];
+        for (_, row) in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                let len = cell
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .chars()
+                    .count();
+                widths[i] = widths[i].max(len.min(TABLE_COLUMN_MAX));
+            }
+        }
+
+        let mut out = String::from("\n```\n");
+        for (header, row) in &rows {
+            let cells: Vec<String> = (0..columns)
+                .map(|i| fit_cell(row.get(i).map(String::as_str).unwrap_or(""), widths[i]))
+                .collect();
+            out.push_str(cells.join(" | ").trim_end());
+            out.push('\n');
+            if *header {
+                let rule: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+                out.push_str(&rule.join("-+-"));
+                out.push('\n');
+            }
+        }
+        out.push_str("```\n");
+        printer.append_str(&out);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+pub struct TableFactory;
+impl TagHandlerFactory for TableFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(TableHandler::default());
+    }
+}
+
+// discourse-math: <span class="math">/<div class="math"> for MathJax,
+// <span class="katex"> (which embeds MathML) for KaTeX
+fn is_math(tag: &Handle) -> bool {
+    has_class(tag, "math") || has_class(tag, "katex") || has_class(tag, "katex-display")
+}
+
+fn tex_source(tag: &Handle) -> String {
+    // KaTeX and most MathML keep the original TeX in an annotation
+    let annotation = find_first(tag, &|h| {
+        element_name(h).as_deref() == Some("annotation")
+            && get_tag_attr(h, "encoding").as_deref() == Some("application/x-tex")
+    });
+    let raw = match annotation {
+        Some(annotation) => text_content(&annotation),
+        None => text_content(tag),
+    };
+    let raw = raw.trim();
+    // MathJax sources keep their delimiters
+    let raw = raw
+        .strip_prefix("\\[")
+        .and_then(|r| r.strip_suffix("\\]"))
+        .or_else(|| raw.strip_prefix("$$").and_then(|r| r.strip_suffix("$$")))
+        .or_else(|| raw.strip_prefix("\\(").and_then(|r| r.strip_suffix("\\)")))
+        .or_else(|| raw.strip_prefix('$').and_then(|r| r.strip_suffix('$')))
+        .unwrap_or(raw);
+    raw.trim().to_string()
+}
+
+fn render_math(tag: &Handle, block: bool, options: &MdOptions) -> String {
+    let tex = tex_source(tag);
+    if tex.is_empty() {
+        return String::new();
+    }
+    let code = if block {
+        format!("\n```tex\n{tex}\n```\n")
+    } else {
+        // backticks would end the inline code early
+        format!("`{}`", tex.replace('`', "'"))
+    };
+    match &options.math_renderer {
+        Some(template) => {
+            let encoded: String = url::form_urlencoded::byte_serialize(tex.as_bytes()).collect();
+            let url = template.replace("{tex}", &encoded.replace('+', "%20"));
+            let link = format!("[{}]({url})", tex.replace(['[', ']'], ""));
+            if block { format!("\n{link}\n") } else { link }
+        }
+        None => code,
+    }
+}
+
+#[derive(Default)]
+pub struct MathHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for MathHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let mathml = element_name(tag).as_deref() == Some("math");
+        let block = has_class(tag, "katex-display")
+            || (mathml && get_tag_attr(tag, "display").as_deref() == Some("block"));
+        if mathml || is_math(tag) {
+            printer.append_str(&render_math(tag, block, &self.options));
+            return;
+        }
+        // ordinary span
+        let mut factories = profile_factories(&self.options);
+        factories.remove("span");
+        walk(tag, printer, &factories);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct MathFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for MathFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(MathHandler {
+            options: self.options.clone(),
+        });
+    }
+}
+
+// renders <ul>/<ol> itself so nesting, <ol start> and multi-paragraph
+// items survive; each list indents relative to its parent item
+#[derive(Default)]
+pub struct ListHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for ListHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if has_class(tag, "footnotes-list") {
+            self.footnotes(tag, printer);
+            return;
+        }
+
+        let ordered = element_name(tag).as_deref() == Some("ol");
+        let mut ordinal: i64 = get_tag_attr(tag, "start")
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(1);
+        let factories = profile_factories(&self.options);
+
+        let mut out = String::new();
+        for item in tag.children.borrow().iter() {
+            if element_name(item).as_deref() != Some("li") {
+                continue;
+            }
+            // <li value="n"> resets the count
+            if let Some(value) = get_tag_attr(item, "value").and_then(|v| v.trim().parse().ok()) {
+                ordinal = value;
+            }
+            let marker = if ordered {
+                format!("{ordinal}. ")
+            } else {
+                String::from("- ")
+            };
+            ordinal += 1;
+
+            let start = printer.data.len();
+            for child in item.children.borrow().iter() {
+                walk(child, printer, &factories);
+            }
+            let content = printer.data[start..].to_string();
+            printer.data.truncate(start);
+
+            let indent = " ".repeat(marker.chars().count());
+            let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+            out.push_str(&marker);
+            out.push_str(lines.next().map(str::trim).unwrap_or_default());
+            out.push('\n');
+            for line in lines {
+                out.push_str(&indent);
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+        }
+
+        if !printer.data.is_empty() && !printer.data.ends_with('\n') {
+            printer.append_str("\n");
+        }
+        printer.append_str(&out);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+impl ListHandler {
+    // the list at the end of the post, as compact "[1] ..." subtext lines
+    fn footnotes(&self, tag: &Handle, printer: &mut StructuredPrinter) {
+        let factories = profile_factories(&self.options);
+        let mut out = String::from("\n");
+        let mut number = 1;
+        for item in tag.children.borrow().iter() {
+            if element_name(item).as_deref() != Some("li") {
+                continue;
+            }
+            let start = printer.data.len();
+            for child in item.children.borrow().iter() {
+                walk(child, printer, &factories);
+            }
+            let content = printer.data[start..].to_string();
+            printer.data.truncate(start);
+            let text = content.split_whitespace().collect::<Vec<_>>().join(" ");
+            out.push_str(&format!("-# [{number}] {text}\n"));
+            number += 1;
+        }
+        printer.append_str(&out);
+    }
+}
+
+#[derive(Default)]
+pub struct ListFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for ListFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(ListHandler {
+            options: self.options.clone(),
+        });
+    }
+}
+
+// footnote references become plain [1] markers; other <sup> is left to
+// the default handler
+#[derive(Default)]
+pub struct SupHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for SupHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if has_class(tag, "footnote-ref") {
+            let text = text_content(tag);
+            let number = text.trim().trim_start_matches('[').trim_end_matches(']');
+            printer.append_str(&format!("[{number}]"));
+            return;
+        }
+        let mut factories = profile_factories(&self.options);
+        factories.remove("sup");
+        walk(tag, printer, &factories);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct SupFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for SupFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(SupHandler {
+            options: self.options.clone(),
+        });
+    }
+}
+
+// drops the separator Discourse puts above the footnotes
+#[derive(Default)]
+pub struct HrHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for HrHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if has_class(tag, "footnotes-sep") {
+            return;
+        }
+        let mut factories = profile_factories(&self.options);
+        factories.remove("hr");
+        walk(tag, printer, &factories);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct HrFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for HrFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(HrHandler {
+            options: self.options.clone(),
+        });
+    }
+}
+
+#[derive(Default)]
+pub struct DivHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for DivHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if is_math(tag) {
+            printer.append_str(&render_math(tag, true, &self.options));
+            return;
+        }
+
+        if !has_class(tag, "poll") {
+            // ordinary div, let the default handler deal with it
+            let mut factories = profile_factories(&self.options);
+            factories.remove("div");
+            walk(tag, printer, &factories);
+            return;
+        }
+
+        let poll_type = PollType::parse(&get_tag_attr(tag, "data-poll-type").unwrap_or_default());
+        let min = get_tag_attr(tag, "data-poll-min").and_then(|v| v.parse().ok());
+        let max = get_tag_attr(tag, "data-poll-max").and_then(|v| v.parse().ok());
+
+        let mut options = Vec::new();
+        if let Some(list) = find_first(tag, &|h| {
+            matches!(element_name(h).as_deref(), Some("ul") | Some("ol"))
+        }) {
+            for item in list.children.borrow().iter() {
+                if element_name(item).as_deref() == Some("li") {
+                    options.push(text_content(item).trim().to_string());
+                }
+            }
+        }
+
+        let mut out = format!("\n📊 **Poll** ({})\n", selection_rule(poll_type, min, max));
+        for (i, option) in options.iter().enumerate() {
+            match poll_type {
+                PollType::RankedChoice => out.push_str(&format!("{}. {option}\n", i + 1)),
+                PollType::Multiple => out.push_str(&format!("☐ {option}\n")),
+                PollType::Regular => out.push_str(&format!("○ {option}\n")),
+            }
+        }
+        printer.append_str(&out);
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct DivFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for DivFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(DivHandler {
+            options: self.options.clone(),
+        });
+    }
+}
+
 #[derive(Default)]
 pub struct AsideHandler {
     username_raw: Option<String>,
+    onebox: Option<OneboxHandler>,
+    // omitted by depth or collapsed to a single line
+    omitted: bool,
+    options: Arc<MdOptions>,
 }
 impl TagHandler for AsideHandler {
     fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        // oneboxes share the <aside> tag with quotes
+        if has_class(tag, "onebox") {
+            let mut onebox = OneboxHandler::default();
+            onebox.handle(tag, printer);
+            self.onebox = Some(onebox);
+            return;
+        }
+
+        if let Some(marker) = self.options.quote_omitted(tag) {
+            self.omitted = true;
+            if marker {
+                printer.append_str(&format!("\n{OMITTED_QUOTES}\n"));
+            }
+            return;
+        }
+
+        if self.options.quote_style == QuoteStyle::Collapsed {
+            self.omitted = true;
+            printer.append_str(&format!("\n{}\n", self.collapsed_line(tag)));
+            return;
+        }
+
         let mut custom: HashMap<String, Box<dyn TagHandlerFactory>> = HashMap::new();
 
         // if let Some(username) = get_tag_attr(tag, "data-username") {
@@ -251,22 +1093,70 @@ impl TagHandler for AsideHandler {
         self.username_raw = get_tag_attr(tag, "data-username");
 
         custom.insert(String::from("div"), Box::new(IgnoreFactory));
-        custom.insert(String::from("img"), Box::new(CustomImgFactory));
-        custom.insert(String::from("q"), Box::new(CustomQuoteFactory));
-        custom.insert(String::from("cite"), Box::new(CustomQuoteFactory));
-        custom.insert(String::from("quote"), Box::new(CustomQuoteFactory));
-        custom.insert(String::from("a"), Box::new(CustomAnchorFactory));
+        custom.insert(
+            String::from("img"),
+            Box::new(CustomImgFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("q"),
+            Box::new(CustomQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("cite"),
+            Box::new(CustomQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("quote"),
+            Box::new(CustomQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("a"),
+            Box::new(CustomAnchorFactory {
+                options: self.options.clone(),
+            }),
+        );
         custom.insert(String::from("summary"), Box::new(DummyHandlerFactory));
-        custom.insert(String::from("details"), Box::new(DetailsFactory));
-        custom.insert(String::from("blockquote"), Box::new(CustomQuoteFactory));
+        custom.insert(
+            String::from("details"),
+            Box::new(DetailsFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("blockquote"),
+            Box::new(CustomQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
 
         walk(tag, printer, &custom);
     }
 
     fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if let Some(onebox) = &mut self.onebox {
+            onebox.after_handle(printer);
+            return;
+        }
+        if self.omitted {
+            return;
+        }
         if let Some(username_raw) = &self.username_raw {
             let username = escape_discord_markdown(username_raw);
-            printer.append_str(&format!("⤷ quoting: {}\n", username));
+            // None keeps the built-in English label
+            match &self.options.locale {
+                Some(locale) => {
+                    printer.append_str(&format!("{}\n", locale.format("quote.quoting", &username)))
+                }
+                None => printer.append_str(&format!("⤷ quoting: {}\n", username)),
+            }
         }
     }
 
@@ -275,24 +1165,493 @@ impl TagHandler for AsideHandler {
     }
 }
 
-pub struct AsideFactory;
+impl AsideHandler {
+    fn collapsed_line(&self, tag: &Handle) -> String {
+        let english = Locale::english();
+        let locale = self.options.locale.as_deref().unwrap_or(&english);
+        let label = match get_tag_attr(tag, "data-username") {
+            Some(username) => locale.format("quote.collapsed", &escape_discord_markdown(&username)),
+            None => locale.get("quote.collapsed.anonymous"),
+        };
+        let text = find_first(tag, &|h| element_name(h).as_deref() == Some("blockquote"))
+            .map(|quote| text_content(&quote))
+            .unwrap_or_default();
+        let excerpt = escape_markdown(&first_sentence(&text, COLLAPSED_EXCERPT_CHARS));
+        let line = format!("{label} {excerpt}");
+
+        let topic = get_tag_attr(tag, "data-topic");
+        let post = get_tag_attr(tag, "data-post").unwrap_or_else(|| String::from("1"));
+        match (&self.options.base_url, topic) {
+            (Some(base), Some(topic)) => {
+                let url = absolute_url(&format!("/t/{topic}/{post}"), Some(base));
+                format!("[{line}]({url})")
+            }
+            _ => line,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AsideFactory {
+    pub options: Arc<MdOptions>,
+}
 impl TagHandlerFactory for AsideFactory {
     fn instantiate(&self) -> Box<dyn TagHandler> {
-        return Box::new(AsideHandler::default());
+        return Box::new(AsideHandler {
+            options: self.options.clone(),
+            ..AsideHandler::default()
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderProfile {
+    #[default]
+    Standard,
+    // screen-reader friendly: inline alt text, explicit quote markers, no decorative emoji
+    Accessible,
+}
+
+impl RenderProfile {
+    pub fn html_to_md(&self, html: &str) -> String {
+        html_to_md_with(
+            html,
+            &MdOptions {
+                profile: *self,
+                ..MdOptions::default()
+            },
+        )
+    }
+
+    pub fn html_to_md_in(&self, html: &str, locale: &Locale, base_url: &str) -> String {
+        let options = MdOptions::for_forum(base_url)
+            .with_profile(*self)
+            .with_locale(locale.clone());
+        html_to_md_with(html, &options)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteStyle {
+    #[default]
+    Full,
+    // one line with the first sentence, linked to the quoted post
+    Collapsed,
+}
+
+pub const COLLAPSED_EXCERPT_CHARS: usize = 80;
+
+// "First sentence of the post…"; whitespace is collapsed and the ellipsis
+// marks anything dropped
+pub fn first_sentence(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut sentence = String::new();
+    for c in text.chars() {
+        sentence.push(c);
+        if matches!(c, '.' | '!' | '?') {
+            break;
+        }
+    }
+    if sentence.chars().count() > max_chars {
+        let cut: String = sentence.chars().take(max_chars).collect();
+        return format!("{}…", cut.trim_end());
+    }
+    if sentence.len() < text.len() {
+        sentence.push('…');
+    }
+    sentence
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageStyle {
+    // "Image", linked when a base url is known
+    #[default]
+    Placeholder,
+    // [image: alt text](src), for text-only targets that would otherwise
+    // lose the url
+    Link,
+    AltText,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SpoilerStyle {
+    // ||hidden||
+    #[default]
+    Bars,
+    // for targets without spoiler syntax
+    Plain,
+    Placeholder,
+}
+
+// rendering context threaded into every handler
+#[derive(Clone)]
+pub struct MdOptions {
+    pub profile: RenderProfile,
+    // relative links and uploads are resolved against this
+    pub base_url: Option<Url>,
+    // None leaves nesting as deep as the post has it
+    pub max_quote_depth: Option<usize>,
+    pub quote_style: QuoteStyle,
+    pub spoiler_style: SpoilerStyle,
+    // e.g. "https://latex.codecogs.com/png.image?{tex}"; None keeps the TeX
+    // as inline code
+    pub math_renderer: Option<String>,
+    // see normalize::normalize
+    pub normalize: bool,
+    pub image_style: ImageStyle,
+    pub image_placeholder: String,
+    // images with any of these classes are dropped
+    pub skip_image_classes: Vec<String>,
+    // None keeps the built-in English labels
+    pub locale: Option<Arc<Locale>>,
+    pub mentions: Option<Arc<dyn MentionResolver>>,
+}
+
+impl Default for MdOptions {
+    fn default() -> Self {
+        MdOptions {
+            profile: RenderProfile::Standard,
+            base_url: None,
+            max_quote_depth: None,
+            quote_style: QuoteStyle::Full,
+            spoiler_style: SpoilerStyle::Bars,
+            math_renderer: None,
+            normalize: true,
+            image_style: ImageStyle::Placeholder,
+            image_placeholder: String::from("Image"),
+            skip_image_classes: vec![String::from("avatar")],
+            locale: None,
+            mentions: None,
+        }
+    }
+}
+
+impl MdOptions {
+    // an unparseable base url leaves relative links as they are
+    pub fn for_forum(base_url: &str) -> Self {
+        MdOptions {
+            base_url: Url::parse(base_url).ok(),
+            ..MdOptions::default()
+        }
+    }
+
+    pub fn with_profile(mut self, profile: RenderProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_max_quote_depth(mut self, depth: usize) -> Self {
+        self.max_quote_depth = Some(depth);
+        self
+    }
+
+    pub fn with_quote_style(mut self, style: QuoteStyle) -> Self {
+        self.quote_style = style;
+        self
+    }
+
+    pub fn with_spoiler_style(mut self, style: SpoilerStyle) -> Self {
+        self.spoiler_style = style;
+        self
+    }
+
+    pub fn with_math_renderer(mut self, template: &str) -> Self {
+        self.math_renderer = Some(template.to_string());
+        self
+    }
+
+    pub fn with_image_style(mut self, style: ImageStyle) -> Self {
+        self.image_style = style;
+        self
+    }
+
+    pub fn with_image_placeholder(mut self, placeholder: &str) -> Self {
+        self.image_placeholder = placeholder.to_string();
+        self
+    }
+
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(Arc::new(locale));
+        self
+    }
+
+    pub fn with_mentions(mut self, resolver: Arc<dyn MentionResolver>) -> Self {
+        self.mentions = Some(resolver);
+        self
+    }
+
+    // Some(true) for the outermost quote to drop, which leaves the marker;
+    // anything nested deeper is dropped silently
+    fn quote_omitted(&self, tag: &Handle) -> Option<bool> {
+        let max = self.max_quote_depth?;
+        let depth = quote_depth(tag);
+        (depth > max).then_some(depth == max + 1)
+    }
+
+    fn skips_image(&self, tag: &Handle) -> bool {
+        self.skip_image_classes.iter().any(|c| has_class(tag, c))
+    }
+}
+
+#[derive(Default)]
+pub struct AccessibleImgHandler {
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for AccessibleImgHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if self.options.skips_image(tag) || has_class(tag, "emoji") {
+            return;
+        }
+        match get_tag_attr(tag, "alt").filter(|a| !a.trim().is_empty()) {
+            Some(alt) => printer.append_str(&format!("[Image: {}]\n", alt.trim())),
+            None => printer.append_str("[Image without description]\n"),
+        }
+    }
+
+    fn after_handle(&mut self, _printer: &mut StructuredPrinter) {}
+}
+
+#[derive(Default)]
+pub struct AccessibleImgFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for AccessibleImgFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(AccessibleImgHandler {
+            options: self.options.clone(),
+        });
     }
 }
 
+#[derive(Default)]
+pub struct AccessibleQuoteHandler {
+    username: Option<String>,
+    onebox: Option<OneboxHandler>,
+    options: Arc<MdOptions>,
+}
+
+impl TagHandler for AccessibleQuoteHandler {
+    fn handle(&mut self, tag: &Handle, printer: &mut StructuredPrinter) {
+        if has_class(tag, "onebox") {
+            let mut onebox = OneboxHandler::default();
+            onebox.handle(tag, printer);
+            self.onebox = Some(onebox);
+            return;
+        }
+        self.username = get_tag_attr(tag, "data-username").map(|u| escape_discord_markdown(&u));
+        match &self.username {
+            Some(u) => printer.append_str(&format!("\nQuote from {u} begins:\n")),
+            None => printer.append_str("\nQuote begins:\n"),
+        }
+
+        let mut custom: HashMap<String, Box<dyn TagHandlerFactory>> = HashMap::new();
+        custom.insert(String::from("div"), Box::new(IgnoreFactory));
+        custom.insert(
+            String::from("img"),
+            Box::new(AccessibleImgFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(
+            String::from("a"),
+            Box::new(CustomAnchorFactory {
+                options: self.options.clone(),
+            }),
+        );
+        custom.insert(String::from("blockquote"), Box::new(DummyHandlerFactory));
+        custom.insert(
+            String::from("aside"),
+            Box::new(AccessibleQuoteFactory {
+                options: self.options.clone(),
+            }),
+        );
+        walk(tag, printer, &custom);
+    }
+
+    fn after_handle(&mut self, printer: &mut StructuredPrinter) {
+        if self.onebox.is_some() {
+            return;
+        }
+        match &self.username {
+            Some(u) => printer.append_str(&format!("\nQuote from {u} ends.\n")),
+            None => printer.append_str("\nQuote ends.\n"),
+        }
+    }
+
+    fn skip_descendants(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct AccessibleQuoteFactory {
+    pub options: Arc<MdOptions>,
+}
+impl TagHandlerFactory for AccessibleQuoteFactory {
+    fn instantiate(&self) -> Box<dyn TagHandler> {
+        return Box::new(AccessibleQuoteHandler {
+            options: self.options.clone(),
+            ..Default::default()
+        });
+    }
+}
+
+pub fn html_to_md_accessible(html: &str) -> String {
+    RenderProfile::Accessible.html_to_md(html)
+}
+
 pub fn html_to_md(html: &str) -> String {
+    html_to_md_with(html, &MdOptions::default())
+}
+
+pub fn html_to_md_with(html: &str, options: &MdOptions) -> String {
+    let options = Arc::new(options.clone());
+    let tag_factory = profile_factories(&options);
+    let md = parse_html_custom(html, &tag_factory);
+    if options.normalize {
+        normalize(&md)
+    } else {
+        md
+    }
+}
+
+// the factory map for options.profile; handlers that walk their own children
+// must use this rather than default_factories or nested content loses the
+// profile's overrides
+fn profile_factories(options: &Arc<MdOptions>) -> HashMap<String, Box<dyn TagHandlerFactory>> {
+    let mut tag_factory = default_factories(options);
+    if options.profile == RenderProfile::Accessible {
+        tag_factory.insert(
+            String::from("img"),
+            Box::new(AccessibleImgFactory {
+                options: options.clone(),
+            }),
+        );
+        tag_factory.insert(
+            String::from("aside"),
+            Box::new(AccessibleQuoteFactory {
+                options: options.clone(),
+            }),
+        );
+        tag_factory.insert(
+            String::from("blockquote"),
+            Box::new(AccessibleQuoteFactory {
+                options: options.clone(),
+            }),
+        );
+    }
+    tag_factory
+}
+
+// relative links and images are resolved against the forum, e.g.
+// html_to_md_with_base(&post.cooked, &post_data.base_url)
+pub fn html_to_md_with_base(html: &str, base_url: &str) -> String {
+    html_to_md_with(html, &MdOptions::for_forum(base_url))
+}
+
+// quote attributions ("quoting ...") in the forum's language
+pub fn html_to_md_localized(html: &str, locale: &Locale, base_url: &str) -> String {
+    html_to_md_with(
+        html,
+        &MdOptions::for_forum(base_url).with_locale(locale.clone()),
+    )
+}
+
+// @mentions become <@id> when resolvable and bold text otherwise
+pub fn html_to_md_with_mentions(html: &str, resolver: Arc<dyn MentionResolver>) -> String {
+    html_to_md_with(html, &MdOptions::default().with_mentions(resolver))
+}
+
+fn default_factories(options: &Arc<MdOptions>) -> HashMap<String, Box<dyn TagHandlerFactory>> {
     let mut tag_factory: HashMap<String, Box<dyn TagHandlerFactory>> = HashMap::new();
-    tag_factory.insert(String::from("img"), Box::new(CustomImgFactory));
-    tag_factory.insert(String::from("blockquote"), Box::new(CustomQuoteFactory));
-    tag_factory.insert(String::from("q"), Box::new(CustomQuoteFactory));
-    tag_factory.insert(String::from("cite"), Box::new(CustomQuoteFactory));
-    tag_factory.insert(String::from("quote"), Box::new(CustomQuoteFactory));
-    tag_factory.insert(String::from("a"), Box::new(CustomAnchorFactory));
+    tag_factory.insert(
+        String::from("img"),
+        Box::new(CustomImgFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("blockquote"),
+        Box::new(CustomQuoteFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("q"),
+        Box::new(CustomQuoteFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("cite"),
+        Box::new(CustomQuoteFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("quote"),
+        Box::new(CustomQuoteFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("a"),
+        Box::new(CustomAnchorFactory {
+            options: options.clone(),
+        }),
+    );
     tag_factory.insert(String::from("summary"), Box::new(DummyHandlerFactory));
-    tag_factory.insert(String::from("details"), Box::new(DetailsFactory));
-    tag_factory.insert(String::from("aside"), Box::new(AsideFactory));
-
-    parse_html_custom(html, &tag_factory)
+    tag_factory.insert(
+        String::from("details"),
+        Box::new(DetailsFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("aside"),
+        Box::new(AsideFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(String::from("table"), Box::new(TableFactory));
+    for tag in ["ul", "ol"] {
+        tag_factory.insert(
+            String::from(tag),
+            Box::new(ListFactory {
+                options: options.clone(),
+            }),
+        );
+    }
+    tag_factory.insert(
+        String::from("sup"),
+        Box::new(SupFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory.insert(
+        String::from("hr"),
+        Box::new(HrFactory {
+            options: options.clone(),
+        }),
+    );
+    for tag in ["span", "math"] {
+        tag_factory.insert(
+            String::from(tag),
+            Box::new(MathFactory {
+                options: options.clone(),
+            }),
+        );
+    }
+    tag_factory.insert(
+        String::from("div"),
+        Box::new(DivFactory {
+            options: options.clone(),
+        }),
+    );
+    tag_factory
 }
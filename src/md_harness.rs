@@ -0,0 +1,169 @@
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::md::{MdOptions, html_to_md_with};
+
+// conversion only ever drops markup, but labels ("⤷ quoting: ...", absolute
+// links, footnote markers) can add a little
+pub const LENGTH_SLACK: usize = 256;
+
+// cooked html samples in the fixtures::capture_fixture format
+pub const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/md");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    Panicked(String),
+    TooLong { len: usize, max: usize },
+    // odd number of ``` fences
+    UnclosedCodeBlock,
+    // odd number of a delimiter outside code, e.g. "||" or "**"
+    Unbalanced(&'static str),
+    Mismatch { expected: String, actual: String },
+    // an "expected.contains" fragment that isn't in the output
+    Missing(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Panicked(msg) => write!(f, "html_to_md panicked: {msg}"),
+            Violation::TooLong { len, max } => write!(f, "output is {len} chars, max {max}"),
+            Violation::UnclosedCodeBlock => write!(f, "unclosed code block"),
+            Violation::Unbalanced(delim) => write!(f, "unbalanced {delim}"),
+            Violation::Mismatch { expected, actual } => {
+                write!(f, "expected {expected:?}, got {actual:?}")
+            }
+            Violation::Missing(fragment) => write!(f, "missing {fragment:?}"),
+        }
+    }
+}
+
+// text outside ``` blocks and `inline code`, where delimiters are literal
+fn outside_code(md: &str) -> String {
+    let mut ret = String::new();
+    for (i, block) in md.split("```").enumerate() {
+        if i % 2 == 1 {
+            continue;
+        }
+        for (j, part) in block.split('`').enumerate() {
+            if j % 2 == 0 {
+                ret.push_str(part);
+            }
+        }
+    }
+    ret
+}
+
+// count of unescaped occurrences
+fn count_delim(text: &str, delim: &str) -> usize {
+    let mut count = 0;
+    let mut rest = text;
+    while let Some(pos) = rest.find(delim) {
+        if !rest[..pos].ends_with('\\') {
+            count += 1;
+        }
+        rest = &rest[pos + delim.len()..];
+    }
+    count
+}
+
+pub fn check_markdown(md: &str, max_len: usize) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let len = md.chars().count();
+    if len > max_len {
+        violations.push(Violation::TooLong { len, max: max_len });
+    }
+    if md.matches("```").count() % 2 != 0 {
+        violations.push(Violation::UnclosedCodeBlock);
+        // everything after the open fence is code, nothing else is meaningful
+        return violations;
+    }
+    let text = outside_code(md);
+    for delim in ["||", "**", "~~", "__"] {
+        if count_delim(&text, delim) % 2 != 0 {
+            violations.push(Violation::Unbalanced(delim));
+        }
+    }
+    violations
+}
+
+// never panics itself; a panicking conversion is reported as a violation
+pub fn check_html(html: &str, options: &MdOptions) -> (String, Vec<Violation>) {
+    let result = catch_unwind(AssertUnwindSafe(|| html_to_md_with(html, options)));
+    match result {
+        Ok(md) => {
+            let max_len = html.chars().count() + LENGTH_SLACK;
+            let violations = check_markdown(&md, max_len);
+            (md, violations)
+        }
+        Err(panic) => {
+            let msg = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            (String::new(), vec![Violation::Panicked(msg)])
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FixtureResult {
+    pub path: PathBuf,
+    pub violations: Vec<Violation>,
+}
+
+// replays fixtures written by fixtures::capture_fixture; "expected.markdown"
+// is compared and each of "expected.contains" looked for when present, the
+// invariants are always checked
+pub fn replay_fixtures(dir: &Path) -> anyhow::Result<Vec<FixtureResult>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut results = Vec::new();
+    for path in paths {
+        let fixture: Value = serde_json::from_slice(&std::fs::read(&path)?)?;
+        let cooked = fixture["post"]["cooked"].as_str().unwrap_or_default();
+        let options = match fixture["source"].as_str() {
+            Some(source) => MdOptions::for_forum(source),
+            None => MdOptions::default(),
+        };
+        let (md, mut violations) = check_html(cooked, &MdOptions::default());
+        if let Some(expected) = fixture["expected"]["markdown"].as_str() {
+            if expected != md {
+                violations.push(Violation::Mismatch {
+                    expected: expected.to_string(),
+                    actual: md.clone(),
+                });
+            }
+        }
+        let fragments = fixture["expected"]["contains"]
+            .as_array()
+            .into_iter()
+            .flatten();
+        for fragment in fragments.filter_map(|f| f.as_str()) {
+            if !md.contains(fragment) {
+                violations.push(Violation::Missing(fragment.to_string()));
+            }
+        }
+        // the forum-aware options must hold the same invariants
+        let (_, forum_violations) = check_html(cooked, &options);
+        for violation in forum_violations {
+            if !violations.contains(&violation) {
+                violations.push(violation);
+            }
+        }
+        results.push(FixtureResult { path, violations });
+    }
+    Ok(results)
+}
+
+pub fn replay_corpus() -> anyhow::Result<Vec<FixtureResult>> {
+    replay_fixtures(Path::new(CORPUS_DIR))
+}
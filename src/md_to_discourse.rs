@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serenity::all::Message;
+
+// names for the ids in Discord's <@..>, <@&..> and <#..> syntax
+#[derive(Debug, Clone, Default)]
+pub struct DiscordContext {
+    pub users: HashMap<u64, String>,
+    pub roles: HashMap<u64, String>,
+    pub channels: HashMap<u64, String>,
+}
+
+impl DiscordContext {
+    // the users Discord resolved for us; roles and channels only come with
+    // the guild cache, so they fall back to generic names
+    pub fn from_message(message: &Message) -> Self {
+        let users = message
+            .mentions
+            .iter()
+            .map(|u| {
+                let name = u.global_name.clone().unwrap_or_else(|| u.name.clone());
+                (u.id.get(), name)
+            })
+            .collect();
+        DiscordContext {
+            users,
+            ..Default::default()
+        }
+    }
+}
+
+// angle-bracket syntax that isn't html: mentions, custom emoji, timestamps
+// and embed-suppressed links
+static TOKEN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"<(?:(@[!&]?|#)(\d+)|a?:(\w+):\d+|t:(-?\d+)(?::([tTdDfFR]))?|(https?://[^\s>]+))>")
+        .unwrap()
+});
+static SPOILER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\|\|(.+?)\|\|").unwrap());
+static UNDERLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"__(.+?)__").unwrap());
+static PLACEHOLDER: Lazy<Regex> = Lazy::new(|| Regex::new("\u{E000}(\\d+)\u{E001}").unwrap());
+
+// a forum user or group of the same name must not get pinged, and stray
+// html must not render
+fn escape(text: &str) -> String {
+    text.replace('@', "\\@").replace('<', "\\<")
+}
+
+fn render_token(caps: &Captures, ctx: &DiscordContext) -> String {
+    if let (Some(kind), Some(id)) = (caps.get(1), caps.get(2)) {
+        let id: u64 = id.as_str().parse().unwrap_or_default();
+        let (map, prefix, unknown) = match kind.as_str() {
+            "@&" => (&ctx.roles, "@", "role"),
+            "#" => (&ctx.channels, "#", "channel"),
+            _ => (&ctx.users, "@", "user"),
+        };
+        let name = map.get(&id).map(String::as_str).unwrap_or(unknown);
+        // shown as text, so it stays a name and not a forum mention
+        return format!("**{}**", escape(&format!("{prefix}{name}")));
+    }
+    if let Some(name) = caps.get(3) {
+        return format!(":{}:", name.as_str().to_lowercase());
+    }
+    if let Some(ts) = caps.get(4) {
+        let Some(at) = ts
+            .as_str()
+            .parse::<i64>()
+            .ok()
+            .and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+        else {
+            return String::new();
+        };
+        let date = at.format("%Y-%m-%d");
+        // the date-only styles
+        return match caps.get(5).map(|s| s.as_str()) {
+            Some("d") | Some("D") => format!("[date={date} timezone=\"UTC\"]"),
+            _ => format!(
+                "[date={date} time={} timezone=\"UTC\"]",
+                at.format("%H:%M:%S")
+            ),
+        };
+    }
+    caps.get(6)
+        .map(|url| url.as_str().to_string())
+        .unwrap_or_default()
+}
+
+// plain text between code spans
+fn convert_text(text: &str, ctx: &DiscordContext) -> String {
+    let mut tokens = Vec::new();
+    let text = TOKEN.replace_all(text, |caps: &Captures| {
+        tokens.push(render_token(caps, ctx));
+        format!("\u{E000}{}\u{E001}", tokens.len() - 1)
+    });
+    let text = escape(&text);
+    let text = SPOILER.replace_all(&text, "[spoiler]$1[/spoiler]");
+    // Discord's __ is underline, Discourse's is bold
+    let text = UNDERLINE.replace_all(&text, "[u]$1[/u]");
+    PLACEHOLDER
+        .replace_all(&text, |caps: &Captures| {
+            let i: usize = caps[1].parse().unwrap_or_default();
+            tokens.get(i).cloned().unwrap_or_default()
+        })
+        .into_owned()
+}
+
+fn convert_line(line: &str, ctx: &DiscordContext) -> String {
+    // -# subtext has no markdown equivalent
+    if let Some(rest) = line.strip_prefix("-# ") {
+        return format!("<small>{}</small>", convert_line(rest, ctx));
+    }
+    // `code` is copied as is, everything else converted
+    let mut ret = String::new();
+    for (i, part) in line.split('`').enumerate() {
+        if i > 0 {
+            ret.push('`');
+        }
+        if i % 2 == 1 {
+            ret.push_str(part);
+        } else {
+            ret.push_str(&convert_text(part, ctx));
+        }
+    }
+    ret
+}
+
+// Discord markdown to Discourse raw markdown; the inverse of html_to_md.
+// Code blocks and spans are left untouched.
+pub fn md_to_discourse(raw: &str) -> String {
+    md_to_discourse_with(raw, &DiscordContext::default())
+}
+
+pub fn md_to_discourse_with(raw: &str, ctx: &DiscordContext) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    // >>> quotes everything after it
+    let mut quote_rest = false;
+    for line in raw.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            lines.push(line.to_string());
+            continue;
+        }
+        if in_code {
+            lines.push(line.to_string());
+            continue;
+        }
+        let mut line = line;
+        let mut quoted = quote_rest;
+        if let Some(rest) = line.strip_prefix(">>> ") {
+            quote_rest = true;
+            quoted = true;
+            line = rest;
+        } else if let Some(rest) = line.strip_prefix("> ") {
+            quoted = true;
+            line = rest;
+        }
+        let converted = convert_line(line, ctx);
+        if quoted {
+            lines.push(format!("> {converted}"));
+        } else {
+            lines.push(converted);
+        }
+    }
+    // an unclosed fence would swallow the rest of the post
+    if in_code {
+        lines.push(String::from("```"));
+    }
+    lines.join("\n")
+}
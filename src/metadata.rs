@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+pub const BREADCRUMB_SEPARATOR: &str = " › ";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CategoryMeta {
+    pub id: u64,
+    pub name: String,
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub parent_category_id: Option<u64>,
+}
+
+#[derive(Default)]
+pub struct MetadataCache {
+    categories: RwLock<HashMap<u64, CategoryMeta>>,
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_category(&self, category: CategoryMeta) {
+        let mut categories = self.categories.write().unwrap();
+        categories.insert(category.id, category);
+    }
+
+    // accepts either /site.json (flat "categories") or /categories.json
+    // ("category_list" with nested "subcategory_list")
+    pub fn load_categories(&self, json: &Value) -> usize {
+        let list = json
+            .get("categories")
+            .or_else(|| json.get("category_list").and_then(|l| l.get("categories")));
+        let mut count = 0;
+        if let Some(Value::Array(list)) = list {
+            for c in list {
+                count += self.load_category_tree(c, None);
+            }
+        }
+        count
+    }
+
+    fn load_category_tree(&self, value: &Value, parent: Option<u64>) -> usize {
+        let mut count = 0;
+        if let Ok(mut category) = serde_json::from_value::<CategoryMeta>(value.clone()) {
+            if category.parent_category_id.is_none() {
+                category.parent_category_id = parent;
+            }
+            let id = category.id;
+            self.insert_category(category);
+            count += 1;
+            if let Some(Value::Array(subs)) = value.get("subcategory_list") {
+                for sub in subs {
+                    count += self.load_category_tree(sub, Some(id));
+                }
+            }
+        }
+        count
+    }
+
+    pub fn category(&self, id: u64) -> Option<CategoryMeta> {
+        self.categories.read().unwrap().get(&id).cloned()
+    }
+
+    // root first, leaf last
+    pub fn category_path(&self, id: u64) -> Vec<CategoryMeta> {
+        let categories = self.categories.read().unwrap();
+        let mut path = Vec::new();
+        let mut current = Some(id);
+        while let Some(id) = current {
+            // guard against cycles in bad data
+            if path.iter().any(|c: &CategoryMeta| c.id == id) {
+                break;
+            }
+            match categories.get(&id) {
+                Some(category) => {
+                    current = category.parent_category_id;
+                    path.push(category.clone());
+                }
+                None => break,
+            }
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn breadcrumb(&self, id: u64) -> Option<String> {
+        let path = self.category_path(id);
+        if path.is_empty() {
+            return None;
+        }
+        let names: Vec<&str> = path.iter().map(|c| c.name.as_str()).collect();
+        Some(names.join(BREADCRUMB_SEPARATOR))
+    }
+
+    // "Releases" matches "Releases" and every subcategory of it,
+    // "Releases › Beta" only matches that subtree
+    pub fn matches_category_path(&self, id: u64, pattern: &str) -> bool {
+        let path = self.category_path(id);
+        let wanted: Vec<&str> = pattern
+            .split(['›', '>', '/'])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        if wanted.is_empty() || wanted.len() > path.len() {
+            return false;
+        }
+        wanted
+            .iter()
+            .zip(path.iter())
+            .all(|(w, c)| c.name.eq_ignore_ascii_case(w) || c.slug.eq_ignore_ascii_case(w))
+    }
+}
@@ -0,0 +1,181 @@
+// Prometheus instrumentation. Without the `metrics` feature every recorder is
+// a no-op so call sites don't need their own cfg guards.
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use once_cell::sync::Lazy;
+    use prometheus::{
+        Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+        Registry, TextEncoder,
+    };
+
+    pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+    fn register<T: prometheus::core::Collector + Clone + 'static>(metric: T) -> T {
+        // only fails on duplicate names, which would be a bug in this file
+        let _ = REGISTRY.register(Box::new(metric.clone()));
+        metric
+    }
+
+    pub static SOLVES: Lazy<IntCounterVec> = Lazy::new(|| {
+        register(
+            IntCounterVec::new(
+                Opts::new("forum_stream_solves_total", "Cloudflare challenge solves"),
+                &["result"],
+            )
+            .unwrap(),
+        )
+    });
+
+    pub static SOLVE_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+        register(
+            HistogramVec::new(
+                HistogramOpts::new("forum_stream_solve_seconds", "Time spent in a solve")
+                    .buckets(vec![1.0, 2.5, 5.0, 10.0, 20.0, 40.0, 60.0]),
+                &["result"],
+            )
+            .unwrap(),
+        )
+    });
+
+    pub static RESPONSES: Lazy<IntCounterVec> = Lazy::new(|| {
+        register(
+            IntCounterVec::new(
+                Opts::new(
+                    "forum_stream_upstream_responses_total",
+                    "Responses from the forum by status",
+                ),
+                &["status", "challenged"],
+            )
+            .unwrap(),
+        )
+    });
+
+    pub static EMBEDS: Lazy<IntCounterVec> = Lazy::new(|| {
+        register(
+            IntCounterVec::new(
+                Opts::new("forum_stream_embeds_total", "Embeds built per formatter"),
+                &["formatter"],
+            )
+            .unwrap(),
+        )
+    });
+
+    pub static SERIALIZE_FAILURES: Lazy<IntCounter> = Lazy::new(|| {
+        register(
+            IntCounter::new(
+                "forum_stream_pulsar_serialize_failures_total",
+                "Pulsar messages that failed to serialize or deserialize",
+            )
+            .unwrap(),
+        )
+    });
+
+    pub static CATCH_UP_GAPS: Lazy<IntCounterVec> = Lazy::new(|| {
+        register(
+            IntCounterVec::new(
+                Opts::new(
+                    "forum_stream_catch_up_skipped_posts_total",
+                    "Post ids a poller skipped past after hitting its catch-up page limit",
+                ),
+                &["source"],
+            )
+            .unwrap(),
+        )
+    });
+
+    pub static POOL_CONNECTIONS: Lazy<IntGaugeVec> = Lazy::new(|| {
+        register(
+            IntGaugeVec::new(
+                Opts::new(
+                    "forum_stream_tenant_pool_connections",
+                    "Tenant pool connections",
+                ),
+                &["tenant", "state"],
+            )
+            .unwrap(),
+        )
+    });
+
+    pub fn render() -> String {
+        let mut buf = Vec::new();
+        let encoder = TextEncoder::new();
+        if encoder.encode(&REGISTRY.gather(), &mut buf).is_err() {
+            return String::new();
+        }
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+fn result_label(ok: bool) -> &'static str {
+    if ok { "ok" } else { "error" }
+}
+
+pub fn record_solve(ok: bool, elapsed: std::time::Duration) {
+    #[cfg(feature = "metrics")]
+    {
+        let label = result_label(ok);
+        imp::SOLVES.with_label_values(&[label]).inc();
+        imp::SOLVE_SECONDS
+            .with_label_values(&[label])
+            .observe(elapsed.as_secs_f64());
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (result_label(ok), elapsed);
+}
+
+// every upstream response, so 403/429 rates can be derived from the total
+pub fn record_response(status: u16, challenged: bool) {
+    #[cfg(feature = "metrics")]
+    imp::RESPONSES
+        .with_label_values(&[
+            &status.to_string(),
+            if challenged { "true" } else { "false" },
+        ])
+        .inc();
+    #[cfg(not(feature = "metrics"))]
+    let _ = (status, challenged);
+}
+
+pub fn record_embeds(formatter: &str, count: usize) {
+    #[cfg(feature = "metrics")]
+    imp::EMBEDS
+        .with_label_values(&[formatter])
+        .inc_by(count as u64);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (formatter, count);
+}
+
+pub fn record_serialize_failure() {
+    #[cfg(feature = "metrics")]
+    imp::SERIALIZE_FAILURES.inc();
+}
+
+pub fn record_catch_up_gap(source: &str, gap: u64) {
+    #[cfg(feature = "metrics")]
+    imp::CATCH_UP_GAPS.with_label_values(&[source]).inc_by(gap);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (source, gap);
+}
+
+pub fn record_pool_usage(tenant: &str, size: u32, idle: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        imp::POOL_CONNECTIONS
+            .with_label_values(&[tenant, "open"])
+            .set(size as i64);
+        imp::POOL_CONNECTIONS
+            .with_label_values(&[tenant, "idle"])
+            .set(idle as i64);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (tenant, size, idle);
+}
+
+// Prometheus text exposition format; empty without the feature
+pub fn render() -> String {
+    #[cfg(feature = "metrics")]
+    return imp::render();
+    #[cfg(not(feature = "metrics"))]
+    String::new()
+}
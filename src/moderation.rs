@@ -0,0 +1,190 @@
+use discourse::bundle::PostData;
+use serde::{Deserialize, Serialize};
+use serenity::all::{CreateEmbed, CreateEmbedFooter, Timestamp};
+
+use crate::{i18n::Locale, sanitize::escape_markdown};
+
+pub const FLAG_RAISED_COLOR: u32 = 0xE67E22;
+pub const FLAG_RESOLVED_COLOR: u32 = 0x2ECC71;
+pub const POST_HIDDEN_COLOR: u32 = 0x95A5A6;
+pub const USER_SILENCED_COLOR: u32 = 0xF1C40F;
+pub const USER_SUSPENDED_COLOR: u32 = 0xE74C3C;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagResolution {
+    Agreed,
+    Disagreed,
+    Ignored,
+}
+
+// review-queue and user-penalty events; these come from discourse's
+// reviewable/user webhooks rather than from posts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ModerationEvent {
+    FlagRaised {
+        post_url: String,
+        topic_title: String,
+        flagged_by: String,
+        // "off_topic", "inappropriate", "spam", ...
+        flag_type: String,
+        message: Option<String>,
+    },
+    FlagResolved {
+        post_url: String,
+        topic_title: String,
+        resolution: FlagResolution,
+        resolved_by: String,
+    },
+    PostHidden {
+        post_url: String,
+        topic_title: String,
+        author: String,
+        reason: Option<String>,
+    },
+    UserSilenced {
+        username: String,
+        by: String,
+        reason: Option<String>,
+        until: Option<String>,
+    },
+    UserSuspended {
+        username: String,
+        by: String,
+        reason: Option<String>,
+        until: Option<String>,
+    },
+}
+
+fn flag_type_label(flag_type: &str) -> String {
+    match flag_type {
+        "off_topic" => String::from("Off-topic"),
+        "inappropriate" => String::from("Inappropriate"),
+        "spam" => String::from("Spam"),
+        "illegal" => String::from("Illegal"),
+        "notify_moderators" => String::from("Something else"),
+        other => other.replace('_', " "),
+    }
+}
+
+fn penalty_description(username: &str, by: &str, reason: &Option<String>) -> String {
+    let mut ret = format!(
+        "**{}** by {}",
+        escape_markdown(username),
+        escape_markdown(by)
+    );
+    if let Some(reason) = reason.as_deref().filter(|r| !r.is_empty()) {
+        ret.push_str(&format!("\n> {reason}"));
+    }
+    ret
+}
+
+pub fn create_moderation_embed(event: &ModerationEvent, base_url: &str) -> CreateEmbed {
+    let embed = CreateEmbed::new().timestamp(Timestamp::now());
+    match event {
+        ModerationEvent::FlagRaised {
+            post_url,
+            topic_title,
+            flagged_by,
+            flag_type,
+            message,
+        } => {
+            let mut description = format!(
+                "Flagged as **{}** by {}",
+                flag_type_label(flag_type),
+                escape_markdown(flagged_by)
+            );
+            if let Some(message) = message.as_deref().filter(|m| !m.is_empty()) {
+                description.push_str(&format!("\n> {message}"));
+            }
+            embed
+                .title(format!("🚩 Flag raised: {topic_title}"))
+                .url(post_url)
+                .description(description)
+                .color(FLAG_RAISED_COLOR)
+        }
+        ModerationEvent::FlagResolved {
+            post_url,
+            topic_title,
+            resolution,
+            resolved_by,
+        } => {
+            let verb = match resolution {
+                FlagResolution::Agreed => "Agreed with",
+                FlagResolution::Disagreed => "Disagreed with",
+                FlagResolution::Ignored => "Ignored",
+            };
+            embed
+                .title(format!("Flag resolved: {topic_title}"))
+                .url(post_url)
+                .description(format!("{verb} by {}", escape_markdown(resolved_by)))
+                .color(FLAG_RESOLVED_COLOR)
+        }
+        ModerationEvent::PostHidden {
+            post_url,
+            topic_title,
+            author,
+            reason,
+        } => {
+            let mut description = format!("Post by {} was hidden", escape_markdown(author));
+            if let Some(reason) = reason.as_deref().filter(|r| !r.is_empty()) {
+                description.push_str(&format!("\n> {reason}"));
+            }
+            embed
+                .title(format!("Post hidden: {topic_title}"))
+                .url(post_url)
+                .description(description)
+                .color(POST_HIDDEN_COLOR)
+        }
+        ModerationEvent::UserSilenced {
+            username,
+            by,
+            reason,
+            until,
+        } => {
+            let mut embed = embed
+                .title("User silenced")
+                .url(format!("{base_url}/u/{username}"))
+                .description(penalty_description(username, by, reason))
+                .color(USER_SILENCED_COLOR);
+            if let Some(until) = until {
+                embed = embed.footer(CreateEmbedFooter::new(format!("until {until}")));
+            }
+            embed
+        }
+        ModerationEvent::UserSuspended {
+            username,
+            by,
+            reason,
+            until,
+        } => {
+            let mut embed = embed
+                .title("User suspended")
+                .url(format!("{base_url}/u/{username}"))
+                .description(penalty_description(username, by, reason))
+                .color(USER_SUSPENDED_COLOR);
+            if let Some(until) = until {
+                embed = embed.footer(CreateEmbedFooter::new(format!("until {until}")));
+            }
+            embed
+        }
+    }
+}
+
+// small-action (post_type 3) posts, e.g. "closed this topic"
+pub fn admin_action_description(post_data: &PostData) -> String {
+    admin_action_description_in(post_data, &Locale::english())
+}
+
+pub fn admin_action_description_in(post_data: &PostData, locale: &Locale) -> String {
+    let Some(code) = post_data.post.action_code.as_deref() else {
+        return String::new();
+    };
+    let key = format!("action.{code}");
+    let who_key = format!("{key}.who");
+    match post_data.post.action_code_who.as_deref() {
+        Some(who) if locale.has(&who_key) => locale.format(&who_key, &escape_markdown(who)),
+        _ => locale.get(&key),
+    }
+}
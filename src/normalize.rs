@@ -0,0 +1,160 @@
+use unicode_normalization::UnicodeNormalization;
+
+// applied to html_to_md output: entities that survived double-escaping,
+// UTF-8 read as Latin-1/Windows-1252 ("â¤·"), invisible characters and NFC
+pub fn normalize(text: &str) -> String {
+    // code blocks may be showing entities on purpose
+    let text = text
+        .split("```")
+        .enumerate()
+        .map(|(i, part)| {
+            if i % 2 == 0 {
+                decode_entities(part)
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("```");
+    let text = fix_mojibake(&text);
+    let text = strip_invisible(&text);
+    text.nfc().collect()
+}
+
+const NAMED_ENTITIES: &[(&str, char)] = &[
+    ("nbsp", ' '),
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("hellip", '…'),
+    ("mdash", '—'),
+    ("ndash", '–'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("zwj", '\u{200D}'),
+];
+
+// &name; and &#123; / &#x1F600; forms; anything unrecognized is kept as is
+pub fn decode_entities(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        ret.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                ret.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                ret.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    ret.push_str(rest);
+    ret
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    NAMED_ENTITIES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, c)| *c)
+}
+
+// Windows-1252 characters in the 0x80..0x9F range
+const CP1252: &[(char, u8)] = &[
+    ('€', 0x80),
+    ('‚', 0x82),
+    ('ƒ', 0x83),
+    ('„', 0x84),
+    ('…', 0x85),
+    ('†', 0x86),
+    ('‡', 0x87),
+    ('ˆ', 0x88),
+    ('‰', 0x89),
+    ('Š', 0x8A),
+    ('‹', 0x8B),
+    ('Œ', 0x8C),
+    ('Ž', 0x8E),
+    ('‘', 0x91),
+    ('’', 0x92),
+    ('“', 0x93),
+    ('”', 0x94),
+    ('•', 0x95),
+    ('–', 0x96),
+    ('—', 0x97),
+    ('˜', 0x98),
+    ('™', 0x99),
+    ('š', 0x9A),
+    ('›', 0x9B),
+    ('œ', 0x9C),
+    ('ž', 0x9E),
+    ('Ÿ', 0x9F),
+];
+
+fn as_byte(c: char) -> Option<u8> {
+    if (c as u32) <= 0xFF {
+        return Some(c as u32 as u8);
+    }
+    CP1252.iter().find(|(ch, _)| *ch == c).map(|(_, b)| *b)
+}
+
+// only complete multi-byte sequences are repaired, so legitimate Latin-1
+// text like "café" is left alone
+pub fn fix_mojibake(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut ret = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let len = match as_byte(chars[i]) {
+            Some(0xC2..=0xDF) => 2,
+            Some(0xE0..=0xEF) => 3,
+            Some(0xF0..=0xF4) => 4,
+            _ => 1,
+        };
+        if len > 1 && i + len <= chars.len() {
+            let bytes: Option<Vec<u8>> = chars[i..i + len].iter().map(|c| as_byte(*c)).collect();
+            if let Some(Ok(s)) = bytes.map(String::from_utf8) {
+                ret.push_str(&s);
+                i += len;
+                continue;
+            }
+        }
+        ret.push(chars[i]);
+        i += 1;
+    }
+    ret
+}
+
+// control characters other than newlines and tabs, zero-width spaces, BOMs
+// and soft hyphens; ZWJ stays since emoji sequences depend on it
+pub fn strip_invisible(text: &str) -> String {
+    text.chars()
+        .filter(|c| match c {
+            '\n' | '\t' => true,
+            '\u{200B}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' => false,
+            c => !c.is_control(),
+        })
+        .map(|c| if c == '\u{00A0}' { ' ' } else { c })
+        .collect()
+}
@@ -0,0 +1,97 @@
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationLevel {
+    Muted,
+    Regular,
+    Tracking,
+    Watching,
+    WatchingFirstPost,
+}
+
+impl NotificationLevel {
+    pub fn as_discourse(&self) -> u8 {
+        match self {
+            NotificationLevel::Muted => 0,
+            NotificationLevel::Regular => 1,
+            NotificationLevel::Tracking => 2,
+            NotificationLevel::Watching => 3,
+            NotificationLevel::WatchingFirstPost => 4,
+        }
+    }
+}
+
+// the forum account the bridge acts as
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ServiceAccount {
+    pub api_key: String,
+    pub api_username: String,
+}
+
+async fn post_level(
+    client: &ClientWithMiddleware,
+    url: String,
+    account: &ServiceAccount,
+    level: NotificationLevel,
+) -> anyhow::Result<()> {
+    client
+        .post(url)
+        .header("Api-Key", &account.api_key)
+        .header("Api-Username", &account.api_username)
+        .form(&[("notification_level", level.as_discourse().to_string())])
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+pub async fn set_topic_notification_level(
+    client: &ClientWithMiddleware,
+    base_url: &str,
+    account: &ServiceAccount,
+    topic_id: u64,
+    level: NotificationLevel,
+) -> anyhow::Result<()> {
+    let url = format!("{base_url}/t/{topic_id}/notifications");
+    post_level(client, url, account, level).await
+}
+
+pub async fn set_category_notification_level(
+    client: &ClientWithMiddleware,
+    base_url: &str,
+    account: &ServiceAccount,
+    category_id: u64,
+    level: NotificationLevel,
+) -> anyhow::Result<()> {
+    let url = format!("{base_url}/category/{category_id}/notifications");
+    post_level(client, url, account, level).await
+}
+
+// watching every routed topic makes the forum's webhooks fire reliably for it;
+// returns the topics that couldn't be updated
+pub async fn auto_watch_topics(
+    client: &ClientWithMiddleware,
+    base_url: &str,
+    account: &ServiceAccount,
+    topic_ids: &[u64],
+) -> Vec<u64> {
+    let mut failed = Vec::new();
+    for topic_id in topic_ids {
+        let result = set_topic_notification_level(
+            client,
+            base_url,
+            account,
+            *topic_id,
+            NotificationLevel::Watching,
+        )
+        .await;
+        if let Err(e) = result {
+            warn!(topic_id = *topic_id, error = %e, "failed to watch topic");
+            failed.push(*topic_id);
+        }
+    }
+    failed
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+use reqwest::Client;
+use serde_json::json;
+
+use crate::error::ForumStreamError;
+
+pub const DEFAULT_NTFY_URL: &str = "https://ntfy.themadseventeen.xyz";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Min = 1,
+    Low = 2,
+    Default = 3,
+    High = 4,
+    Urgent = 5,
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub message: String,
+    pub title: Option<String>,
+    // falls back to the backend's default topic
+    pub topic: Option<String>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+}
+
+impl Notification {
+    pub fn new(message: &str) -> Self {
+        Notification {
+            message: message.to_string(),
+            title: None,
+            topic: None,
+            priority: None,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn topic(mut self, topic: &str) -> Self {
+        self.topic = Some(topic.to_string());
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+pub trait Alerter: Send + Sync {
+    async fn notify(&self, notification: &Notification) -> Result<(), ForumStreamError>;
+}
+
+// lets a shared alerter (e.g. a BatchingAlerter with a flusher) be installed globally
+#[async_trait::async_trait]
+impl<T: Alerter + ?Sized> Alerter for Arc<T> {
+    async fn notify(&self, notification: &Notification) -> Result<(), ForumStreamError> {
+        (**self).notify(notification).await
+    }
+}
+
+// ntfy.sh or a self-hosted ntfy server
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    client: Client,
+    base_url: String,
+    token: Option<String>,
+    default_topic: String,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Notifier::new(DEFAULT_NTFY_URL, "forum-stream-errors")
+    }
+}
+
+impl Notifier {
+    pub fn new(base_url: &str, default_topic: &str) -> Self {
+        Notifier {
+            client: Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+            default_topic: default_topic.to_string(),
+        }
+    }
+
+    // NTFY_URL, NTFY_TOKEN and NTFY_TOPIC, each optional
+    pub fn from_env() -> Self {
+        let base_url = std::env::var("NTFY_URL").unwrap_or_else(|_| DEFAULT_NTFY_URL.to_string());
+        let topic =
+            std::env::var("NTFY_TOPIC").unwrap_or_else(|_| String::from("forum-stream-errors"));
+        let mut notifier = Notifier::new(&base_url, &topic);
+        notifier.token = std::env::var("NTFY_TOKEN").ok();
+        notifier
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Alerter for Notifier {
+    async fn notify(&self, notification: &Notification) -> Result<(), ForumStreamError> {
+        let topic = notification.topic.as_deref().unwrap_or(&self.default_topic);
+        let mut req = self
+            .client
+            .post(format!("{}/{topic}", self.base_url))
+            .body(notification.message.clone());
+        if let Some(token) = &self.token {
+            req = req.bearer_auth(token);
+        }
+        if let Some(title) = &notification.title {
+            req = req.header("Title", title);
+        }
+        if let Some(priority) = notification.priority {
+            req = req.header("Priority", (priority as u8).to_string());
+        }
+        if !notification.tags.is_empty() {
+            req = req.header("Tags", notification.tags.join(","));
+        }
+        req.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+pub struct PushoverAlerter {
+    client: Client,
+    token: String,
+    user: String,
+}
+
+impl PushoverAlerter {
+    pub fn new(token: &str, user: &str) -> Self {
+        PushoverAlerter {
+            client: Client::new(),
+            token: token.to_string(),
+            user: user.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Alerter for PushoverAlerter {
+    async fn notify(&self, notification: &Notification) -> Result<(), ForumStreamError> {
+        // pushover priorities run -2..=2
+        let priority = notification.priority.unwrap_or(Priority::Default) as i8 - 3;
+        let mut form = vec![
+            ("token", self.token.clone()),
+            ("user", self.user.clone()),
+            ("message", notification.message.clone()),
+            ("priority", priority.to_string()),
+        ];
+        if let Some(title) = &notification.title {
+            form.push(("title", title.clone()));
+        }
+        self.client
+            .post("https://api.pushover.net/1/messages.json")
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+pub struct DiscordWebhookAlerter {
+    client: Client,
+    webhook_url: String,
+}
+
+impl DiscordWebhookAlerter {
+    pub fn new(webhook_url: &str) -> Self {
+        DiscordWebhookAlerter {
+            client: Client::new(),
+            webhook_url: webhook_url.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Alerter for DiscordWebhookAlerter {
+    async fn notify(&self, notification: &Notification) -> Result<(), ForumStreamError> {
+        let mut content = String::new();
+        if let Some(title) = &notification.title {
+            content.push_str(&format!("**{title}**\n"));
+        }
+        content.push_str(&notification.message);
+        if !notification.tags.is_empty() {
+            content.push_str(&format!("\n-# {}", notification.tags.join(", ")));
+        }
+        self.client
+            .post(&self.webhook_url)
+            .json(&json!({
+                "content": content,
+                "allowed_mentions": { "parse": [] },
+            }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+static GLOBAL: OnceCell<Box<dyn Alerter>> = OnceCell::new();
+
+// call once at startup; false if something already initialized it
+pub fn init_global(alerter: impl Alerter + 'static) -> bool {
+    GLOBAL.set(Box::new(alerter)).is_ok()
+}
+
+pub fn global() -> &'static dyn Alerter {
+    GLOBAL
+        .get_or_init(|| Box::new(Notifier::from_env()))
+        .as_ref()
+}
+
+struct Pending {
+    notification: Notification,
+    first_sent: Instant,
+    suppressed: u32,
+}
+
+struct BatchState {
+    recent: HashMap<(Option<String>, String), Pending>,
+    sent_in_window: Vec<Instant>,
+    dropped: u32,
+}
+
+// identical messages inside `window` collapse into one with a repeat count,
+// and at most `max_per_minute` notifications go out regardless of content
+pub struct BatchingAlerter<A: Alerter> {
+    inner: A,
+    window: Duration,
+    max_per_minute: usize,
+    state: Mutex<BatchState>,
+}
+
+impl<A: Alerter> BatchingAlerter<A> {
+    pub fn new(inner: A, window: Duration, max_per_minute: usize) -> Self {
+        BatchingAlerter {
+            inner,
+            window,
+            max_per_minute,
+            state: Mutex::new(BatchState {
+                recent: HashMap::new(),
+                sent_in_window: Vec::new(),
+                dropped: 0,
+            }),
+        }
+    }
+
+    fn take_slot(&self, state: &mut BatchState, now: Instant) -> bool {
+        let minute = Duration::from_secs(60);
+        state
+            .sent_in_window
+            .retain(|sent| now.duration_since(*sent) < minute);
+        if state.sent_in_window.len() >= self.max_per_minute {
+            return false;
+        }
+        state.sent_in_window.push(now);
+        true
+    }
+
+    // removes the entries whose window has closed, returning a summary for
+    // each one that suppressed repeats
+    fn take_expired(&self, state: &mut BatchState, now: Instant) -> Vec<Notification> {
+        let expired: Vec<_> = state
+            .recent
+            .iter()
+            .filter(|(_, p)| now.duration_since(p.first_sent) >= self.window)
+            .map(|(k, _)| k.clone())
+            .collect();
+        let mut summaries = Vec::new();
+        for key in expired {
+            let Some(pending) = state.recent.remove(&key) else {
+                continue;
+            };
+            if pending.suppressed > 0 && self.take_slot(state, now) {
+                let mut notification = pending.notification;
+                notification.message = format!(
+                    "{} (repeated {} more times)",
+                    notification.message, pending.suppressed
+                );
+                summaries.push(notification);
+            }
+        }
+        summaries
+    }
+
+    // sends the repeat counts of windows that have closed; run periodically
+    // so the tail end of a storm is still reported
+    pub async fn flush(&self) {
+        let summaries = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let mut summaries = self.take_expired(&mut state, now);
+            if state.dropped > 0 && self.take_slot(&mut state, now) {
+                summaries.push(
+                    Notification::new(&format!(
+                        "{} notifications dropped by rate limit",
+                        state.dropped
+                    ))
+                    .priority(Priority::Low),
+                );
+                state.dropped = 0;
+            }
+            summaries
+        };
+        self.send_all(summaries).await;
+    }
+
+    async fn send_all(&self, notifications: Vec<Notification>) {
+        for notification in notifications {
+            let _ = self.inner.notify(&notification).await;
+        }
+    }
+
+    pub fn spawn_flusher(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()>
+    where
+        A: 'static,
+    {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                this.flush().await;
+            }
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl<A: Alerter> Alerter for BatchingAlerter<A> {
+    async fn notify(&self, notification: &Notification) -> Result<(), ForumStreamError> {
+        let (summaries, send) = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            // closed windows are settled here too, so their counts aren't lost
+            // when this notification replaces one and `recent` stays bounded
+            // without a flusher
+            let summaries = self.take_expired(&mut state, now);
+            let key = (notification.topic.clone(), notification.message.clone());
+            if let Some(pending) = state.recent.get_mut(&key) {
+                // take_expired left only open windows
+                pending.suppressed += 1;
+                (summaries, false)
+            } else if !self.take_slot(&mut state, now) {
+                state.dropped += 1;
+                (summaries, false)
+            } else {
+                state.recent.insert(
+                    key,
+                    Pending {
+                        notification: notification.clone(),
+                        first_sent: now,
+                        suppressed: 0,
+                    },
+                );
+                (summaries, true)
+            }
+        };
+        self.send_all(summaries).await;
+        if !send {
+            return Ok(());
+        }
+        self.inner.notify(notification).await
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt::Write;
+
+use tracing::{Event, Level, Subscriber, field::Field, field::Visit};
+use tracing_subscriber::{Layer, layer::Context};
+
+use crate::utils::ntfy;
+
+// forwards error-level events to an ntfy topic, e.g.
+// tracing_subscriber::registry().with(fmt::layer()).with(NtfyLayer::new("forum-stream-errors"))
+pub struct NtfyLayer {
+    topic: String,
+    level: Level,
+}
+
+impl NtfyLayer {
+    pub fn new(topic: &str) -> Self {
+        NtfyLayer {
+            topic: topic.to_string(),
+            level: Level::ERROR,
+        }
+    }
+
+    // WARN also forwards challenges and failed proxies, which gets noisy
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for NtfyLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // lower levels are more verbose
+        if *event.metadata().level() > self.level {
+            return;
+        }
+        // nowhere to send from outside the runtime
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let message = format!(
+            "[{}] {}{}",
+            event.metadata().target(),
+            visitor.message,
+            visitor.fields
+        );
+        let topic = self.topic.clone();
+        handle.spawn(async move {
+            ntfy(&message, &topic).await;
+        });
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use serenity::all::{ChannelId, CreateEmbed, Http, MessageId};
+use sqlx::{PgConnection, Pool, Postgres};
+use tracing::{info, warn};
+
+use crate::error::Result;
+
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 8;
+// how long a claimed row is left alone before another worker may retry it
+pub const CLAIM_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone)]
+pub struct OutboxMessage {
+    pub id: i64,
+    pub post_id: i64,
+    pub channel_id: ChannelId,
+    pub payload: Value,
+    pub attempts: i32,
+}
+
+// the message body Discord expects; the nonce makes Discord drop a resend of
+// the same outbox row within its dedupe window
+fn message_payload(id: i64, embeds: &[CreateEmbed]) -> Value {
+    json!({
+        "embeds": embeds,
+        "allowed_mentions": { "parse": [] },
+        "nonce": format!("outbox-{id}"),
+        "enforce_nonce": true,
+    })
+}
+
+// takes a connection so it can run inside the caller's transaction, next to
+// whatever else records the post as handled
+pub async fn enqueue(
+    conn: &mut PgConnection,
+    post_id: i64,
+    channel_id: ChannelId,
+    embeds: &[CreateEmbed],
+) -> Result<i64> {
+    let id: i64 = sqlx::query_scalar(
+        r#"INSERT INTO outbox (post_id, discord_channel_id, payload)
+           VALUES ($1, $2, '') RETURNING id"#,
+    )
+    .bind(post_id)
+    .bind(channel_id.get() as i64)
+    .fetch_one(&mut *conn)
+    .await?;
+    sqlx::query("UPDATE outbox SET payload = $2 WHERE id = $1")
+        .bind(id)
+        .bind(serde_json::to_string(&message_payload(id, embeds))?)
+        .execute(&mut *conn)
+        .await?;
+    Ok(id)
+}
+
+#[async_trait::async_trait]
+pub trait OutboxSender: Send + Sync {
+    async fn send(&self, message: &OutboxMessage) -> anyhow::Result<Vec<MessageId>>;
+}
+
+#[async_trait::async_trait]
+impl OutboxSender for Http {
+    async fn send(&self, message: &OutboxMessage) -> anyhow::Result<Vec<MessageId>> {
+        let sent = self
+            .send_message(message.channel_id, Vec::new(), &message.payload)
+            .await?;
+        Ok(vec![sent.id])
+    }
+}
+
+// 30s, 1m, 2m, ... capped at an hour
+fn backoff(attempts: i32) -> Duration {
+    let secs = 30u64.saturating_mul(1 << attempts.clamp(0, 7) as u64);
+    Duration::from_secs(secs.min(3600))
+}
+
+pub struct Outbox {
+    pool: Pool<Postgres>,
+    max_attempts: i32,
+    batch_size: i64,
+}
+
+impl Outbox {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Outbox {
+            pool,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            batch_size: 20,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub async fn enqueue(
+        &self,
+        post_id: i64,
+        channel_id: ChannelId,
+        embeds: &[CreateEmbed],
+    ) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let id = enqueue(&mut tx, post_id, channel_id, embeds).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    // only the oldest pending row of each channel is claimable, so a channel's
+    // posts go out in order even with several workers, and one that's backing
+    // off holds the rest of its channel back. SKIP LOCKED lets those workers
+    // drain one outbox without stepping on each other
+    async fn claim(&self) -> Result<Vec<OutboxMessage>> {
+        let rows: Vec<(i64, i64, i64, String, i32)> = sqlx::query_as(
+            r#"UPDATE outbox
+               SET locked_until = now() + make_interval(secs => $2), attempts = attempts + 1
+               WHERE id IN (
+                   SELECT id FROM outbox o
+                   WHERE delivered_at IS NULL AND failed_at IS NULL
+                     AND next_attempt_at <= now()
+                     AND (locked_until IS NULL OR locked_until < now())
+                     AND NOT EXISTS (
+                         SELECT 1 FROM outbox earlier
+                         WHERE earlier.discord_channel_id = o.discord_channel_id
+                           AND earlier.id < o.id
+                           AND earlier.delivered_at IS NULL AND earlier.failed_at IS NULL
+                     )
+                   ORDER BY id
+                   LIMIT $1
+                   FOR UPDATE SKIP LOCKED
+               )
+               RETURNING id, post_id, discord_channel_id, payload, attempts"#,
+        )
+        .bind(self.batch_size)
+        .bind(CLAIM_TIMEOUT.as_secs_f64())
+        .fetch_all(&self.pool)
+        .await?;
+        let mut messages = Vec::with_capacity(rows.len());
+        for (id, post_id, channel_id, payload, attempts) in rows {
+            messages.push(OutboxMessage {
+                id,
+                post_id,
+                channel_id: ChannelId::new(channel_id as u64),
+                payload: serde_json::from_str(&payload)?,
+                attempts,
+            });
+        }
+        messages.sort_by_key(|m| m.id);
+        Ok(messages)
+    }
+
+    // mapping rows and the delivered mark commit together, so a delivered
+    // message is never sent again by this outbox
+    async fn mark_delivered(&self, message: &OutboxMessage, sent: &[MessageId]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for message_id in sent {
+            sqlx::query(
+                r#"INSERT INTO discord_mappings (post_id, discord_message_id, discord_channel_id)
+                   VALUES ($1, $2, $3)
+                   ON CONFLICT DO NOTHING"#,
+            )
+            .bind(message.post_id)
+            .bind(message_id.get() as i64)
+            .bind(message.channel_id.get() as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query(
+            r#"UPDATE outbox SET delivered_at = now(), locked_until = NULL, last_error = NULL
+               WHERE id = $1"#,
+        )
+        .bind(message.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn mark_failed(&self, message: &OutboxMessage, error: &str) -> Result<()> {
+        let give_up = message.attempts >= self.max_attempts;
+        sqlx::query(
+            r#"UPDATE outbox
+               SET locked_until = NULL, last_error = $2,
+                   next_attempt_at = now() + make_interval(secs => $3),
+                   failed_at = CASE WHEN $4 THEN now() ELSE NULL END
+               WHERE id = $1"#,
+        )
+        .bind(message.id)
+        .bind(error)
+        .bind(backoff(message.attempts).as_secs_f64())
+        .bind(give_up)
+        .execute(&self.pool)
+        .await?;
+        if give_up {
+            warn!(
+                id = message.id,
+                post_id = message.post_id,
+                error,
+                "outbox message failed for good"
+            );
+        }
+        Ok(())
+    }
+
+    // one claim-send-mark pass; the number of messages delivered
+    pub async fn drain_once(&self, sender: &dyn OutboxSender) -> Result<usize> {
+        let mut delivered = 0;
+        for message in self.claim().await? {
+            match sender.send(&message).await {
+                Ok(sent) => {
+                    self.mark_delivered(&message, &sent).await?;
+                    delivered += 1;
+                }
+                Err(e) => self.mark_failed(&message, &e.to_string()).await?,
+            }
+        }
+        Ok(delivered)
+    }
+
+    // rows that ran out of attempts go back in the queue
+    pub async fn retry_failed(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"UPDATE outbox SET failed_at = NULL, attempts = 0, next_attempt_at = now()
+               WHERE failed_at IS NOT NULL AND delivered_at IS NULL"#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub fn spawn_worker(
+        self: &Arc<Self>,
+        sender: Arc<dyn OutboxSender>,
+        interval: Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        let this = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                // a pass sends at most one message per channel, so keep going
+                // while anything was delivered
+                let mut delivered = 0;
+                loop {
+                    match this.drain_once(sender.as_ref()).await {
+                        Ok(n) if n > 0 => delivered += n,
+                        Ok(_) => {
+                            if delivered > 0 {
+                                info!(delivered, "outbox drained");
+                            }
+                            break;
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "outbox drain failed");
+                            break;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use sqlx::{Pool, Postgres};
+
+pub async fn set_paused(
+    pool: &Pool<Postgres>,
+    paused: bool,
+    reason: Option<&str>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO tenant_state (id, paused, reason, changed_at)
+           VALUES (TRUE, $1, $2, now())
+           ON CONFLICT (id) DO UPDATE SET paused = $1, reason = $2, changed_at = now()"#,
+    )
+    .bind(paused)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn is_paused(pool: &Pool<Postgres>) -> anyhow::Result<bool> {
+    let paused: Option<bool> = sqlx::query_scalar("SELECT paused FROM tenant_state WHERE id")
+        .fetch_optional(pool)
+        .await?;
+    Ok(paused.unwrap_or(false))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission<T> {
+    // not paused, handle it now
+    Forward(T),
+    Buffered,
+    // buffer is full, the event is lost
+    Dropped(T),
+}
+
+// holds events while a tenant is paused and hands them back in order on resume
+pub struct PauseGate<T> {
+    paused: bool,
+    limit: usize,
+    buffer: VecDeque<T>,
+    dropped: u64,
+}
+
+impl<T> PauseGate<T> {
+    pub fn new(limit: usize) -> Self {
+        PauseGate {
+            paused: false,
+            limit,
+            buffer: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) -> Vec<T> {
+        self.paused = false;
+        self.buffer.drain(..).collect()
+    }
+
+    pub fn admit(&mut self, event: T) -> Admission<T> {
+        if !self.paused {
+            return Admission::Forward(event);
+        }
+        if self.buffer.len() >= self.limit {
+            self.dropped += 1;
+            return Admission::Dropped(event);
+        }
+        self.buffer.push_back(event);
+        Admission::Buffered
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // pick up pause/resume done through the CLI or another process
+    pub async fn sync(&mut self, pool: &Pool<Postgres>) -> anyhow::Result<Vec<T>> {
+        let paused = is_paused(pool).await?;
+        if paused && !self.paused {
+            self.pause();
+        } else if !paused && self.paused {
+            return Ok(self.resume());
+        }
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use discourse::{bundle::PostData, model::PostId};
+use serde::Deserialize;
+use serenity::all::CreateEmbed;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::{bus::MemoryBus, discord::create_embeds, filter::PostFilter, theme::EmbedTheme};
+
+#[async_trait::async_trait]
+pub trait PostSource: Send + Sync {
+    async fn poll(&self) -> anyhow::Result<Vec<PostData>>;
+}
+
+pub struct RenderedPost {
+    pub post_id: PostId,
+    pub embeds: Vec<CreateEmbed>,
+}
+
+#[async_trait::async_trait]
+pub trait Delivery: Send + Sync {
+    async fn deliver(&self, post: RenderedPost) -> anyhow::Result<()>;
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EmbeddedPipelineConfig {
+    pub poll_interval_secs: u64,
+    pub source_buffer: usize,
+    pub delivery_buffer: usize,
+    pub theme: EmbedTheme,
+    pub filter: PostFilter,
+}
+
+impl Default for EmbeddedPipelineConfig {
+    fn default() -> Self {
+        EmbeddedPipelineConfig {
+            poll_interval_secs: 30,
+            source_buffer: 256,
+            delivery_buffer: 256,
+            theme: EmbedTheme::default(),
+            filter: PostFilter::default(),
+        }
+    }
+}
+
+impl EmbeddedPipelineConfig {
+    pub fn from_toml_str(s: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+}
+
+// source -> render -> deliver in one process, no broker
+pub struct EmbeddedPipeline {
+    config: EmbeddedPipelineConfig,
+    source: Arc<dyn PostSource>,
+    delivery: Arc<dyn Delivery>,
+}
+
+impl EmbeddedPipeline {
+    pub fn new(
+        config: EmbeddedPipelineConfig,
+        source: Arc<dyn PostSource>,
+        delivery: Arc<dyn Delivery>,
+    ) -> Self {
+        EmbeddedPipeline {
+            config,
+            source,
+            delivery,
+        }
+    }
+
+    // runs until a stage stops, and returns an error naming it; never Ok
+    pub async fn run(self) -> anyhow::Result<()> {
+        let (post_tx, mut post_rx) = MemoryBus::<PostData>::new(self.config.source_buffer).split();
+        let (render_tx, mut render_rx) =
+            MemoryBus::<RenderedPost>::new(self.config.delivery_buffer).split();
+        let mut tasks = JoinSet::new();
+        let mut stages = HashMap::new();
+
+        let source = self.source.clone();
+        let interval = Duration::from_secs(self.config.poll_interval_secs.max(1));
+        let handle = tasks.spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match source.poll().await {
+                    Ok(posts) => {
+                        for post in posts {
+                            if post_tx.send(post).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "embedded pipeline: source poll failed"),
+                }
+            }
+        });
+        stages.insert(handle.id(), "source");
+
+        let theme = self.config.theme.clone();
+        let filter = self.config.filter.clone();
+        let handle = tasks.spawn(async move {
+            while let Some(post_data) = post_rx.recv().await {
+                let Some(post_data) = filter.apply(&post_data, None) else {
+                    continue;
+                };
+                let embeds = match create_embeds(&post_data, &theme) {
+                    Ok(embeds) => embeds,
+                    Err(e) => {
+                        warn!(post_id = %post_data.post.id, error = %e, "could not render post");
+                        continue;
+                    }
+                };
+                let rendered = RenderedPost {
+                    post_id: post_data.post.id,
+                    embeds,
+                };
+                if render_tx.send(rendered).await.is_err() {
+                    return;
+                }
+            }
+        });
+        stages.insert(handle.id(), "render");
+
+        let delivery = self.delivery.clone();
+        let handle = tasks.spawn(async move {
+            while let Some(rendered) = render_rx.recv().await {
+                if let Err(e) = delivery.deliver(rendered).await {
+                    warn!(error = %e, "embedded pipeline: delivery failed");
+                }
+            }
+        });
+        stages.insert(handle.id(), "delivery");
+
+        // any stage exiting means the pipeline is broken
+        let stopped = tasks.join_next_with_id().await;
+        tasks.shutdown().await;
+        match stopped {
+            Some(Ok((id, ()))) => {
+                anyhow::bail!("embedded pipeline: {} stage stopped", stages[&id])
+            }
+            Some(Err(e)) => {
+                anyhow::bail!("embedded pipeline: {} stage failed: {e}", stages[&e.id()])
+            }
+            None => anyhow::bail!("embedded pipeline: no stages running"),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollType {
+    Regular,
+    Multiple,
+    RankedChoice,
+}
+
+impl PollType {
+    pub fn parse(s: &str) -> PollType {
+        match s {
+            "multiple" => PollType::Multiple,
+            "ranked_choice" => PollType::RankedChoice,
+            _ => PollType::Regular,
+        }
+    }
+}
+
+pub fn selection_rule(poll_type: PollType, min: Option<u64>, max: Option<u64>) -> String {
+    match poll_type {
+        PollType::Regular => String::from("pick one"),
+        PollType::RankedChoice => String::from("rank the options in order of preference"),
+        PollType::Multiple => match (min, max) {
+            (Some(min), Some(max)) if min == max => format!("pick exactly {max}"),
+            (Some(min), Some(max)) if min > 1 => format!("pick {min} to {max}"),
+            (_, Some(max)) => format!("pick up to {max}"),
+            (Some(min), None) => format!("pick at least {min}"),
+            (None, None) => String::from("pick any"),
+        },
+    }
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+// standings from the "polls" array of a post's JSON; cooked HTML has no counts
+pub fn render_poll_standings(poll: &Value) -> Option<String> {
+    let poll_type = PollType::parse(poll.get("type").and_then(|t| t.as_str()).unwrap_or(""));
+    let options = poll.get("options")?.as_array()?;
+    let voters = poll.get("voters").and_then(|v| v.as_u64()).unwrap_or(0);
+    let min = poll.get("min").and_then(|v| v.as_u64());
+    let max = poll.get("max").and_then(|v| v.as_u64());
+
+    let mut out = format!(
+        "📊 **Poll** ({}, {voters} voter{})\n",
+        selection_rule(poll_type, min, max),
+        if voters == 1 { "" } else { "s" }
+    );
+
+    if poll_type == PollType::RankedChoice {
+        let winner = poll
+            .get("ranked_choice_outcome")
+            .and_then(|o| o.get("winning_candidate"))
+            .and_then(|c| c.get("html"))
+            .and_then(|h| h.as_str());
+        for (i, option) in options.iter().enumerate() {
+            let text = strip_tags(option.get("html").and_then(|h| h.as_str()).unwrap_or(""));
+            out.push_str(&format!("{}. {text}\n", i + 1));
+        }
+        match winner {
+            Some(w) => out.push_str(&format!("🏆 Leading: {}\n", strip_tags(w))),
+            None if voters > 0 => out.push_str("No majority yet\n"),
+            None => {}
+        }
+        return Some(out);
+    }
+
+    let mut rows: Vec<(String, u64)> = options
+        .iter()
+        .map(|o| {
+            (
+                strip_tags(o.get("html").and_then(|h| h.as_str()).unwrap_or("")),
+                o.get("votes").and_then(|v| v.as_u64()).unwrap_or(0),
+            )
+        })
+        .collect();
+    rows.sort_by(|a, b| b.1.cmp(&a.1));
+    // multiple-choice percentages are per voter, so they can sum past 100
+    let total: u64 = match poll_type {
+        PollType::Multiple => voters,
+        _ => rows.iter().map(|r| r.1).sum(),
+    };
+    for (text, votes) in rows {
+        let pct = if total > 0 { votes * 100 / total } else { 0 };
+        out.push_str(&format!("• {text} — {votes} ({pct}%)\n"));
+    }
+    Some(out)
+}
+
+pub fn poll_standings_for_post(post: &Value) -> Vec<String> {
+    match post.get("polls") {
+        Some(Value::Array(polls)) => polls.iter().filter_map(render_poll_standings).collect(),
+        _ => Vec::new(),
+    }
+}
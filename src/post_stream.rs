@@ -0,0 +1,222 @@
+use std::sync::{Arc, Mutex};
+
+use discourse::{
+    bundle::PostData,
+    model::{PostId, TopicId},
+};
+use futures::{Stream, StreamExt, TryStreamExt, stream};
+use reqwest_middleware::ClientWithMiddleware;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::{
+    discord::create_embeds, filter::PostFilter, pipeline::RenderedPost, theme::EmbedTheme,
+};
+
+// Discourse returns at most 20 posts per /t/{id}/posts.json request
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+
+// the two calls needed to walk a topic: the full id list (post_stream.stream
+// in /t/{id}.json) and the posts for a batch of ids
+#[async_trait::async_trait]
+pub trait TopicPager: Send + Sync {
+    async fn post_ids(&self, topic_id: TopicId) -> anyhow::Result<Vec<PostId>>;
+    async fn fetch_posts(&self, topic_id: TopicId, ids: &[PostId])
+    -> anyhow::Result<Vec<PostData>>;
+}
+
+// TopicPager over the forum's JSON API: /t/{id}.json for the id list and
+// /t/{id}/posts.json?post_ids[]= for each page
+pub struct ForumTopicPager {
+    client: ClientWithMiddleware,
+    base_url: String,
+    // (topic id, topic, category) of the last topic paged, so a backfill
+    // doesn't refetch them for every page
+    last_topic: Mutex<Option<(String, Value, Value)>>,
+}
+
+impl ForumTopicPager {
+    pub fn new(client: ClientWithMiddleware, base_url: &str) -> Self {
+        ForumTopicPager {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            last_topic: Mutex::new(None),
+        }
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, String)]) -> anyhow::Result<Value> {
+        let url = format!("{}{path}", self.base_url);
+        let res = self.client.get(&url).query(query).send().await?;
+        Ok(res.error_for_status()?.json().await?)
+    }
+
+    async fn topic(&self, topic_id: TopicId) -> anyhow::Result<(Value, Value)> {
+        let key = topic_id.to_string();
+        if let Some((cached, topic, category)) = self.last_topic.lock().unwrap().as_ref() {
+            if *cached == key {
+                return Ok((topic.clone(), category.clone()));
+            }
+        }
+        let mut topic = self.get(&format!("/t/{topic_id}.json"), &[]).await?;
+        // the full stream is only needed for post_ids
+        if let Some(obj) = topic.as_object_mut() {
+            obj.remove("post_stream");
+        }
+        let category = match topic.get("category_id").and_then(Value::as_u64) {
+            Some(id) => self.get(&format!("/c/{id}/show.json"), &[]).await?["category"].take(),
+            None => Value::Null,
+        };
+        *self.last_topic.lock().unwrap() = Some((key, topic.clone(), category.clone()));
+        Ok((topic, category))
+    }
+}
+
+#[async_trait::async_trait]
+impl TopicPager for ForumTopicPager {
+    async fn post_ids(&self, topic_id: TopicId) -> anyhow::Result<Vec<PostId>> {
+        let mut topic = self.get(&format!("/t/{topic_id}.json"), &[]).await?;
+        Ok(serde_json::from_value(
+            topic["post_stream"]["stream"].take(),
+        )?)
+    }
+
+    async fn fetch_posts(
+        &self,
+        topic_id: TopicId,
+        ids: &[PostId],
+    ) -> anyhow::Result<Vec<PostData>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (topic, category) = self.topic(topic_id).await?;
+        let query: Vec<(&str, String)> = ids
+            .iter()
+            .map(|id| ("post_ids[]", id.to_string()))
+            .collect();
+        let mut page = self
+            .get(&format!("/t/{topic_id}/posts.json"), &query)
+            .await?;
+        let posts = match page["post_stream"]["posts"].take() {
+            Value::Array(posts) => posts,
+            _ => Vec::new(),
+        };
+        let mut ret = Vec::with_capacity(posts.len());
+        for post in &posts {
+            // only resolved when the replied-to post is on the same page
+            let replying_to = post
+                .get("reply_to_post_number")
+                .and_then(Value::as_u64)
+                .and_then(|n| posts.iter().find(|p| p["post_number"].as_u64() == Some(n)))
+                .cloned()
+                .unwrap_or(Value::Null);
+            ret.push(serde_json::from_value(json!({
+                "post": post,
+                "topic": topic,
+                "category": category,
+                "replying_to_post": replying_to,
+                "base_url": self.base_url,
+            }))?);
+        }
+        Ok(ret)
+    }
+}
+
+// lazily pages through a topic so only one page is held in memory at a time,
+// e.g. for backfilling topics with thousands of posts
+pub struct PostStream<P: TopicPager> {
+    pager: Arc<P>,
+    topic_id: TopicId,
+    page_size: usize,
+    // resume a backfill after the last post already delivered
+    skip: usize,
+}
+
+struct PageState<P: TopicPager> {
+    pager: Arc<P>,
+    topic_id: TopicId,
+    page_size: usize,
+    ids: Option<Vec<PostId>>,
+    cursor: usize,
+}
+
+impl<P: TopicPager + 'static> PostStream<P> {
+    pub fn new(pager: Arc<P>, topic_id: TopicId) -> Self {
+        PostStream {
+            pager,
+            topic_id,
+            page_size: DEFAULT_PAGE_SIZE,
+            skip: 0,
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn skip(mut self, posts: usize) -> Self {
+        self.skip = posts;
+        self
+    }
+
+    // whole pages; a failed fetch ends the stream after yielding the error
+    pub fn pages(self) -> impl Stream<Item = anyhow::Result<Vec<PostData>>> + Send {
+        let state = PageState {
+            pager: self.pager,
+            topic_id: self.topic_id,
+            page_size: self.page_size,
+            ids: None,
+            cursor: self.skip,
+        };
+        stream::try_unfold(state, |mut state| async move {
+            if state.ids.is_none() {
+                state.ids = Some(state.pager.post_ids(state.topic_id).await?);
+            }
+            let ids = state.ids.as_deref().unwrap_or_default();
+            if state.cursor >= ids.len() {
+                return Ok(None);
+            }
+            let end = (state.cursor + state.page_size).min(ids.len());
+            let mut page = state
+                .pager
+                .fetch_posts(state.topic_id, &ids[state.cursor..end])
+                .await?;
+            page.sort_by_key(|p| p.post.post_number);
+            state.cursor = end;
+            Ok(Some((page, state)))
+        })
+    }
+
+    pub fn posts(self) -> impl Stream<Item = anyhow::Result<PostData>> + Send {
+        self.pages()
+            .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    // rendered with `theme`; filtered-out posts are skipped and posts that
+    // fail to render are logged and skipped, like the embedded pipeline does
+    pub fn embeds(
+        self,
+        theme: EmbedTheme,
+        filter: PostFilter,
+    ) -> impl Stream<Item = anyhow::Result<RenderedPost>> + Send {
+        self.posts().filter_map(move |post_data| {
+            let rendered = match post_data {
+                Err(e) => Some(Err(e)),
+                Ok(post_data) => filter.apply(&post_data, None).and_then(|post_data| {
+                    match create_embeds(&post_data, &theme) {
+                        Ok(embeds) => Some(Ok(RenderedPost {
+                            post_id: post_data.post.id,
+                            embeds,
+                        })),
+                        Err(e) => {
+                            warn!(post_id = %post_data.post.id, error = %e, "could not render post");
+                            None
+                        }
+                    }
+                }),
+            };
+            async move { rendered }
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt;
+use std::sync::Arc;
+
+use discourse::model::post::Post;
+use reqwest::{Client, cookie::Jar};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{flaresolverr_middleware::FlaresolverrMiddleware, md::html_to_md};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PreflightTenant {
+    pub name: String,
+    pub base_url: String,
+    pub flaresolverr_url: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreflightStage {
+    Flaresolverr,
+    FetchLatest,
+    ParsePost,
+    Convert,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StageResult {
+    pub stage: PreflightStage,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PreflightReport {
+    pub tenant: String,
+    pub stages: Vec<StageResult>,
+}
+
+impl PreflightReport {
+    pub fn passed(&self) -> bool {
+        !self.stages.is_empty() && self.stages.iter().all(|s| s.passed)
+    }
+
+    fn pass(&mut self, stage: PreflightStage, detail: impl Into<String>) {
+        self.stages.push(StageResult {
+            stage,
+            passed: true,
+            detail: detail.into(),
+        });
+    }
+
+    fn fail(&mut self, stage: PreflightStage, detail: impl Into<String>) -> PreflightReport {
+        self.stages.push(StageResult {
+            stage,
+            passed: false,
+            detail: detail.into(),
+        });
+        self.clone()
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "preflight for {}", self.tenant)?;
+        for stage in &self.stages {
+            let mark = if stage.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "  [{mark}] {:?}: {}", stage.stage, stage.detail)?;
+        }
+        Ok(())
+    }
+}
+
+async fn get_json(client: &ClientWithMiddleware, url: &str) -> Result<Value, String> {
+    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("{url} returned {status}"));
+    }
+    resp.json::<Value>().await.map_err(|e| e.to_string())
+}
+
+pub async fn preflight(tenant: &PreflightTenant) -> PreflightReport {
+    let mut report = PreflightReport {
+        tenant: tenant.name.clone(),
+        stages: Vec::new(),
+    };
+    let base_url = tenant.base_url.trim_end_matches('/');
+
+    let cookie_jar = Arc::new(Jar::default());
+    let middleware = match FlaresolverrMiddleware::new(
+        Client::new(),
+        cookie_jar.clone(),
+        tenant.flaresolverr_url.clone(),
+    )
+    .await
+    {
+        Ok(m) => m,
+        Err(e) => return report.fail(PreflightStage::Flaresolverr, e.to_string()),
+    };
+    report.pass(PreflightStage::Flaresolverr, &tenant.flaresolverr_url);
+
+    let inner = match Client::builder().cookie_provider(cookie_jar).build() {
+        Ok(c) => c,
+        Err(e) => return report.fail(PreflightStage::FetchLatest, e.to_string()),
+    };
+    let client = ClientBuilder::new(inner).with(middleware).build();
+
+    let latest = match get_json(&client, &format!("{base_url}/latest.json")).await {
+        Ok(v) => v,
+        Err(e) => return report.fail(PreflightStage::FetchLatest, e),
+    };
+    let topic_id = latest
+        .get("topic_list")
+        .and_then(|l| l.get("topics"))
+        .and_then(|t| t.as_array())
+        .and_then(|t| t.first())
+        .and_then(|t| t.get("id"))
+        .and_then(|id| id.as_u64());
+    let topic_id = match topic_id {
+        Some(id) => id,
+        None => return report.fail(PreflightStage::FetchLatest, "no topics in /latest.json"),
+    };
+    report.pass(
+        PreflightStage::FetchLatest,
+        format!("latest topic {topic_id}"),
+    );
+
+    let topic = match get_json(&client, &format!("{base_url}/t/{topic_id}.json")).await {
+        Ok(v) => v,
+        Err(e) => return report.fail(PreflightStage::ParsePost, e),
+    };
+    let raw_post = topic
+        .get("post_stream")
+        .and_then(|s| s.get("posts"))
+        .and_then(|p| p.as_array())
+        .and_then(|p| p.first())
+        .cloned();
+    let post = match raw_post.map(serde_json::from_value::<Post>) {
+        Some(Ok(post)) => post,
+        Some(Err(e)) => return report.fail(PreflightStage::ParsePost, e.to_string()),
+        None => return report.fail(PreflightStage::ParsePost, "topic has no posts"),
+    };
+    report.pass(
+        PreflightStage::ParsePost,
+        format!("post #{}", post.post_number),
+    );
+
+    let md = html_to_md(&post.cooked);
+    if md.trim().is_empty() && !post.cooked.trim().is_empty() {
+        return report.fail(PreflightStage::Convert, "conversion produced no output");
+    }
+    report.pass(
+        PreflightStage::Convert,
+        format!("{} chars", md.chars().count()),
+    );
+
+    report
+}
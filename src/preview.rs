@@ -0,0 +1,127 @@
+use axum::{Json, Router, http::StatusCode, routing::post};
+use discourse::bundle::PostData;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    discord::create_embeds,
+    md::{html_to_md, html_to_md_with_base},
+    theme::EmbedTheme,
+};
+
+#[derive(Deserialize)]
+pub struct HtmlRequest {
+    pub cooked: String,
+}
+
+#[derive(Deserialize)]
+pub struct PostRequest {
+    pub post_data: PostData,
+    #[serde(default)]
+    pub theme: EmbedTheme,
+}
+
+#[derive(Serialize)]
+pub struct PreviewResponse {
+    pub markdown: String,
+    pub embeds: Vec<Value>,
+    pub html: String,
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// rough approximation of Discord's markdown rendering, good enough to eyeball
+pub fn markdown_to_preview_html(md: &str) -> String {
+    static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+)\)").unwrap());
+    static BOLD: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*(.+?)\*\*").unwrap());
+    static ITALIC: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*(.+?)\*").unwrap());
+    static SPOILER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\|\|(.+?)\|\|").unwrap());
+    static CODE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`]+)`").unwrap());
+
+    let mut out = String::new();
+    let mut in_fence = false;
+    for line in md.lines() {
+        if line.trim_start().starts_with("```") {
+            out.push_str(if in_fence { "</pre>" } else { "<pre>" });
+            in_fence = !in_fence;
+            continue;
+        }
+        let escaped = escape_html(line);
+        if in_fence {
+            out.push_str(&escaped);
+            out.push('\n');
+            continue;
+        }
+        let (quoted, body) = match escaped.strip_prefix("&gt; ") {
+            Some(rest) => (true, rest.to_string()),
+            None => (false, escaped),
+        };
+        let body = CODE.replace_all(&body, "<code>$1</code>");
+        let body = LINK.replace_all(&body, r#"<a href="$2">$1</a>"#);
+        let body = BOLD.replace_all(&body, "<b>$1</b>");
+        let body = ITALIC.replace_all(&body, "<i>$1</i>");
+        let body = SPOILER.replace_all(&body, r#"<span class="spoiler">$1</span>"#);
+        if quoted {
+            out.push_str(&format!("<blockquote>{body}</blockquote>"));
+        } else {
+            out.push_str(&format!("<div>{body}</div>"));
+        }
+    }
+    if in_fence {
+        out.push_str("</pre>");
+    }
+    format!(
+        "<div class=\"discord-embed\" style=\"border-left:4px solid #0277BD;padding:8px;font-family:sans-serif;background:#2f3136;color:#dcddde\">{out}</div>"
+    )
+}
+
+async fn render_html(Json(req): Json<HtmlRequest>) -> Json<PreviewResponse> {
+    let markdown = html_to_md(&req.cooked);
+    let html = markdown_to_preview_html(&markdown);
+    Json(PreviewResponse {
+        embeds: vec![json_embed(&markdown)],
+        markdown,
+        html,
+    })
+}
+
+fn json_embed(description: &str) -> Value {
+    serde_json::json!({ "description": description })
+}
+
+async fn render_post(
+    Json(req): Json<PostRequest>,
+) -> Result<Json<PreviewResponse>, (StatusCode, String)> {
+    let embeds = create_embeds(&req.post_data, &req.theme)
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+    let embeds: Vec<Value> = embeds
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap_or(Value::Null))
+        .collect();
+    let markdown = html_to_md_with_base(&req.post_data.post.cooked, &req.post_data.base_url);
+    let html = markdown_to_preview_html(&markdown);
+    Ok(Json(PreviewResponse {
+        markdown,
+        embeds,
+        html,
+    }))
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/render/html", post(render_html))
+        .route("/render/post", post(render_post))
+}
+
+pub async fn serve(addr: &str) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router()).await?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+
+use futures::TryStreamExt;
+use pulsar::{
+    Consumer, DeserializeMessage, Error as PulsarError, Executor, Producer, consumer::Message,
+};
+
+// failing messages tracked at once; a message nacked here and then handled by
+// another consumer is never seen again, so its count has to age out
+const MAX_TRACKED_ATTEMPTS: usize = 1024;
+
+// wraps a consumer so poison messages end up in a dead-letter topic
+// instead of being redelivered forever
+pub struct DeadLetterConsumer<T: DeserializeMessage, Exe: Executor> {
+    consumer: Consumer<T, Exe>,
+    dead_letter: Producer<Exe>,
+    max_attempts: u32,
+    attempts: HashMap<String, u32>,
+    // insertion order of `attempts`, oldest first
+    order: VecDeque<String>,
+}
+
+impl<T, U, E, Exe> DeadLetterConsumer<T, Exe>
+where
+    T: DeserializeMessage<Output = Result<U, E>>,
+    E: Display,
+    Exe: Executor,
+{
+    pub fn new(consumer: Consumer<T, Exe>, dead_letter: Producer<Exe>, max_attempts: u32) -> Self {
+        DeadLetterConsumer {
+            consumer,
+            dead_letter,
+            max_attempts: max_attempts.max(1),
+            attempts: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn record_attempt(&mut self, key: &str) -> u32 {
+        if !self.attempts.contains_key(key) {
+            self.order.push_back(key.to_string());
+            while self.order.len() > MAX_TRACKED_ATTEMPTS {
+                if let Some(old) = self.order.pop_front() {
+                    self.attempts.remove(&old);
+                }
+            }
+        }
+        let attempts = self.attempts.entry(key.to_string()).or_insert(0);
+        *attempts += 1;
+        *attempts
+    }
+
+    fn forget(&mut self, key: &str) {
+        if self.attempts.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+
+    async fn dead_letter(
+        &mut self,
+        msg: &Message<T>,
+        error: String,
+        attempts: u32,
+    ) -> Result<(), PulsarError> {
+        let mut properties = HashMap::new();
+        properties.insert(String::from("source_topic"), msg.topic.clone());
+        properties.insert(String::from("error"), error);
+        properties.insert(String::from("attempts"), attempts.to_string());
+        let message = pulsar::producer::Message {
+            payload: msg.payload.data.clone(),
+            properties,
+            ..Default::default()
+        };
+        self.dead_letter.send_non_blocking(message).await?.await?;
+        Ok(())
+    }
+
+    // next successfully deserialized message; the caller acks it once handled
+    pub async fn next(&mut self) -> Option<Result<(Message<T>, U), PulsarError>> {
+        loop {
+            let msg = match self.consumer.try_next().await {
+                Ok(Some(msg)) => msg,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            let key = format!("{:?}", msg.message_id());
+            match msg.deserialize() {
+                Ok(value) => {
+                    self.forget(&key);
+                    return Some(Ok((msg, value)));
+                }
+                Err(e) => {
+                    let attempts = self.record_attempt(&key);
+                    if attempts < self.max_attempts {
+                        if let Err(e) = self.consumer.nack(&msg).await {
+                            return Some(Err(e.into()));
+                        }
+                        continue;
+                    }
+                    self.forget(&key);
+                    if let Err(e) = self.dead_letter(&msg, e.to_string(), attempts).await {
+                        return Some(Err(e));
+                    }
+                    if let Err(e) = self.consumer.ack(&msg).await {
+                        return Some(Err(e.into()));
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn ack(&mut self, msg: &Message<T>) -> Result<(), PulsarError> {
+        self.forget(&format!("{:?}", msg.message_id()));
+        self.consumer.ack(msg).await.map_err(Into::into)
+    }
+
+    pub async fn nack(&mut self, msg: &Message<T>) -> Result<(), PulsarError> {
+        self.forget(&format!("{:?}", msg.message_id()));
+        self.consumer.nack(msg).await.map_err(Into::into)
+    }
+}
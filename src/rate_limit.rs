@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use http::Extensions;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Request, Response, StatusCode};
+use reqwest_middleware::{Middleware, Next};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    // sustained rate per host
+    pub requests_per_second: f64,
+    // requests allowed back to back before the rate applies
+    pub burst: u32,
+    // how often a 429/503 with Retry-After is retried before giving up
+    pub max_retries: u32,
+    // a longer Retry-After is returned to the caller instead of waited out
+    pub max_retry_after_secs: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            requests_per_second: 2.0,
+            burst: 5,
+            max_retries: 2,
+            max_retry_after_secs: 120,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    // set from Retry-After; nothing goes to the host before this
+    blocked_until: Option<Instant>,
+}
+
+// token bucket per host. Add it after FlaresolverrMiddleware so the requests
+// that middleware replays after a solve are throttled as well:
+//
+//     ClientBuilder::new(client).with(flaresolverr).with(rate_limit).build()
+pub struct RateLimitMiddleware {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+// seconds or an HTTP date, relative to now
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+
+impl RateLimitMiddleware {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimitMiddleware {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // takes a token for `host`, returning how long to wait before sending
+    async fn reserve(&self, host: &str) -> Duration {
+        let now = Instant::now();
+        let rate = self.config.requests_per_second.max(f64::MIN_POSITIVE);
+        let burst = self.config.burst.max(1) as f64;
+        let mut buckets = self.buckets.lock().await;
+        let bucket = buckets.entry(host.to_string()).or_insert(Bucket {
+            tokens: burst,
+            refilled: now,
+            blocked_until: None,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.refilled = now;
+        // may go negative: later callers queue behind the ones already waiting
+        bucket.tokens -= 1.0;
+
+        let mut wait = if bucket.tokens < 0.0 {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        } else {
+            Duration::ZERO
+        };
+        if let Some(until) = bucket.blocked_until {
+            if until > now {
+                wait = wait.max(until - now);
+            } else {
+                bucket.blocked_until = None;
+            }
+        }
+        wait
+    }
+
+    async fn block(&self, host: &str, wait: Duration) {
+        let until = Instant::now() + wait;
+        let mut buckets = self.buckets.lock().await;
+        if let Some(bucket) = buckets.get_mut(host) {
+            if bucket.blocked_until.is_none_or(|b| b < until) {
+                bucket.blocked_until = Some(until);
+            }
+        }
+    }
+
+    fn retry_after(&self, res: &Response) -> Option<Duration> {
+        if !matches!(
+            res.status(),
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            return None;
+        }
+        let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?;
+        parse_retry_after(value, Utc::now())
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RateLimitMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let host = req.url().host_str().unwrap_or_default().to_string();
+        let max_retry_after = Duration::from_secs(self.config.max_retry_after_secs);
+        let mut attempt = 0;
+        let mut req = req;
+        loop {
+            let wait = self.reserve(&host).await;
+            if !wait.is_zero() {
+                debug!(%host, ?wait, "rate limited");
+                tokio::time::sleep(wait).await;
+            }
+            // streaming bodies can't be replayed, send them once
+            let Some(retry) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+            let res = next.clone().run(req, extensions).await?;
+            let Some(retry_after) = self.retry_after(&res) else {
+                return Ok(res);
+            };
+            // keep everyone else off the host for that long, too
+            self.block(&host, retry_after).await;
+            if attempt >= self.config.max_retries || retry_after > max_retry_after {
+                warn!(%host, status = %res.status(), ?retry_after, "giving up on throttled request");
+                return Ok(res);
+            }
+            warn!(%host, status = %res.status(), ?retry_after, "throttled, retrying");
+            attempt += 1;
+            req = retry;
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::emoji::display_emoji;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Reaction {
+    pub emoji: String,
+    pub count: u64,
+}
+
+// discourse-reactions: "reactions": [{"id": "heart", "type": "emoji", "count": 3}]
+// retort: "retorts": [{"emoji": "+1", "usernames": ["a", "b"]}]
+pub fn extract_reactions(post: &Value) -> Vec<Reaction> {
+    let mut ret: Vec<Reaction> = Vec::new();
+    if let Some(Value::Array(reactions)) = post.get("reactions") {
+        for r in reactions {
+            let emoji = r.get("id").and_then(|v| v.as_str());
+            let count = r.get("count").and_then(|v| v.as_u64()).unwrap_or(0);
+            if let Some(emoji) = emoji {
+                if count > 0 {
+                    ret.push(Reaction {
+                        emoji: emoji.to_string(),
+                        count,
+                    });
+                }
+            }
+        }
+    }
+    if let Some(Value::Array(retorts)) = post.get("retorts") {
+        for r in retorts {
+            let emoji = r.get("emoji").and_then(|v| v.as_str());
+            let count = r
+                .get("usernames")
+                .and_then(|v| v.as_array())
+                .map(|u| u.len() as u64)
+                .unwrap_or(0);
+            if let Some(emoji) = emoji {
+                if count > 0 {
+                    ret.push(Reaction {
+                        emoji: emoji.to_string(),
+                        count,
+                    });
+                }
+            }
+        }
+    }
+    ret.sort_by(|a, b| b.count.cmp(&a.count));
+    ret
+}
+
+pub fn reactions_line(reactions: &[Reaction]) -> Option<String> {
+    if reactions.is_empty() {
+        return None;
+    }
+    let parts: Vec<String> = reactions
+        .iter()
+        .map(|r| format!("{} {}", display_emoji(&r.emoji), r.count))
+        .collect();
+    Some(parts.join(" · "))
+}
@@ -0,0 +1,117 @@
+use reqwest_middleware::ClientWithMiddleware;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::all::{Message, User};
+
+use crate::{
+    mapping_store::MappingStore,
+    md_to_discourse::{DiscordContext, md_to_discourse, md_to_discourse_with},
+};
+
+// body for POST /posts.json
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CreatePostPayload {
+    pub raw: String,
+    pub topic_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to_post_number: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ReverseOptions {
+    // posts are made with one API user, so say who actually wrote it
+    pub attribution: bool,
+    // Discourse rejects shorter posts (min_post_length, 20 by default)
+    pub min_length: usize,
+}
+
+impl Default for ReverseOptions {
+    fn default() -> Self {
+        ReverseOptions {
+            attribution: true,
+            min_length: 20,
+        }
+    }
+}
+
+// where a mirrored post lives on the forum
+#[async_trait::async_trait]
+pub trait PostLocator: Send + Sync {
+    // (topic_id, post_number)
+    async fn locate(&self, post_id: i64) -> anyhow::Result<Option<(u64, u64)>>;
+}
+
+// GET /posts/{id}.json
+pub struct ForumPostLocator {
+    client: ClientWithMiddleware,
+    base_url: String,
+}
+
+impl ForumPostLocator {
+    pub fn new(client: ClientWithMiddleware, base_url: &str) -> Self {
+        ForumPostLocator {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PostLocator for ForumPostLocator {
+    async fn locate(&self, post_id: i64) -> anyhow::Result<Option<(u64, u64)>> {
+        let url = format!("{}/posts/{post_id}.json", self.base_url);
+        let res = self.client.get(&url).send().await?;
+        if res.status().as_u16() == 404 {
+            return Ok(None);
+        }
+        let post: Value = res.error_for_status()?.json().await?;
+        let topic_id = post.get("topic_id").and_then(|v| v.as_u64());
+        let post_number = post.get("post_number").and_then(|v| v.as_u64());
+        Ok(topic_id.zip(post_number))
+    }
+}
+
+fn display_name(user: &User) -> &str {
+    user.global_name.as_deref().unwrap_or(&user.name)
+}
+
+// None if `message` isn't a reply to a mirrored post, the post is gone, or
+// the text is too short for the forum to accept
+pub async fn reply_payload(
+    message: &Message,
+    store: &MappingStore,
+    locator: &dyn PostLocator,
+    options: &ReverseOptions,
+) -> anyhow::Result<Option<CreatePostPayload>> {
+    let Some(replied_to) = message
+        .message_reference
+        .as_ref()
+        .and_then(|r| r.message_id)
+    else {
+        return Ok(None);
+    };
+    let Some(post_id) = store.post_for_message(replied_to).await? else {
+        return Ok(None);
+    };
+    let Some((topic_id, post_number)) = locator.locate(post_id).await? else {
+        return Ok(None);
+    };
+
+    let ctx = DiscordContext::from_message(message);
+    let body = md_to_discourse_with(message.content.trim(), &ctx);
+    if body.chars().count() < options.min_length {
+        return Ok(None);
+    }
+    let raw = if options.attribution {
+        let author = md_to_discourse(display_name(&message.author));
+        format!("*{author} on Discord:*\n\n{body}")
+    } else {
+        body
+    };
+    Ok(Some(CreatePostPayload {
+        raw,
+        topic_id,
+        reply_to_post_number: Some(post_number),
+    }))
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http::Extensions;
+use reqwest::{Method, Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::error::ForumStreamError;
+
+pub const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+// an unreachable robots.txt blocks the host (RFC 9309), so retry it sooner
+pub const UNREACHABLE_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rules {
+    // (allow, pattern)
+    patterns: Vec<(bool, String)>,
+    pub crawl_delay: Option<Duration>,
+}
+
+// `*` matches anything, a trailing `$` anchors the end
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let Some(mut rest) = path.strip_prefix(parts[0]) else {
+        return false;
+    };
+    if parts.len() == 1 {
+        return !anchored || rest.is_empty();
+    }
+    for (i, part) in parts[1..].iter().enumerate() {
+        if anchored && i == parts.len() - 2 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+impl Rules {
+    pub fn allow_all() -> Self {
+        Rules::default()
+    }
+
+    pub fn disallow_all() -> Self {
+        Rules {
+            patterns: vec![(false, String::from("/"))],
+            crawl_delay: None,
+        }
+    }
+
+    // the longest matching pattern wins, Allow on a tie
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.patterns {
+            if !matches(pattern, path) {
+                continue;
+            }
+            let len = pattern.len();
+            best = match best {
+                Some((best_len, best_allow))
+                    if best_len > len || (best_len == len && best_allow) =>
+                {
+                    Some((best_len, best_allow))
+                }
+                _ => Some((len, *allow)),
+            };
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    // (user agents, rules), in file order
+    groups: Vec<(Vec<String>, Rules)>,
+}
+
+impl Robots {
+    pub fn parse(text: &str) -> Self {
+        let mut groups: Vec<(Vec<String>, Rules)> = Vec::new();
+        // consecutive User-agent lines share one group
+        let mut in_agents = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agents || groups.is_empty() {
+                        groups.push((Vec::new(), Rules::default()));
+                    }
+                    in_agents = true;
+                    if let Some((agents, _)) = groups.last_mut() {
+                        agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_agents = false;
+                    // an empty Disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    if let Some((_, rules)) = groups.last_mut() {
+                        rules.patterns.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_agents = false;
+                    let delay = value.parse::<f64>().ok().filter(|d| *d >= 0.0);
+                    if let (Some((_, rules)), Some(delay)) = (groups.last_mut(), delay) {
+                        rules.crawl_delay = Some(Duration::from_secs_f64(delay));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Robots { groups }
+    }
+
+    // the group naming the most specific part of `user_agent`, else `*`
+    pub fn rules_for(&self, user_agent: &str) -> Rules {
+        let token = user_agent
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let named = self
+            .groups
+            .iter()
+            .filter(|(agents, _)| {
+                agents
+                    .iter()
+                    .any(|a| a != "*" && token.contains(a.as_str()))
+            })
+            .max_by_key(|(agents, _)| agents.iter().map(|a| a.len()).max());
+        let group = named.or_else(|| {
+            self.groups
+                .iter()
+                .find(|(agents, _)| agents.iter().any(|a| a == "*"))
+        });
+        group.map(|(_, rules)| rules.clone()).unwrap_or_default()
+    }
+}
+
+struct HostState {
+    rules: Rules,
+    fetched: Instant,
+    ttl: Duration,
+    last_request: Option<Instant>,
+}
+
+// fetches each host's robots.txt through the rest of the stack, then refuses
+// disallowed paths and spaces requests by Crawl-delay. Meant for clients that
+// crawl on their own, like ingest::poller::Poller
+pub struct RobotsMiddleware {
+    user_agent: String,
+    // used when robots.txt doesn't set one
+    min_delay: Option<Duration>,
+    hosts: Mutex<HashMap<String, HostState>>,
+    // one per host, so a robots.txt is only loaded once without blocking
+    // requests to other hosts
+    fetching: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl RobotsMiddleware {
+    pub fn new(user_agent: &str) -> Self {
+        RobotsMiddleware {
+            user_agent: user_agent.to_string(),
+            min_delay: None,
+            hosts: Mutex::new(HashMap::new()),
+            fetching: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_min_delay(mut self, delay: Duration) -> Self {
+        self.min_delay = Some(delay);
+        self
+    }
+
+    async fn is_stale(&self, host: &str) -> bool {
+        self.hosts
+            .lock()
+            .await
+            .get(host)
+            .is_none_or(|state| state.fetched.elapsed() >= state.ttl)
+    }
+
+    async fn fetch(
+        &self,
+        url: &Url,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> (Rules, Duration) {
+        let mut robots_url = url.clone();
+        robots_url.set_path("/robots.txt");
+        robots_url.set_query(None);
+        robots_url.set_fragment(None);
+        let req = Request::new(Method::GET, robots_url.clone());
+        let res = match next.run(req, extensions).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!(url = %robots_url, error = %e, "robots.txt unreachable, pausing host");
+                return (Rules::disallow_all(), UNREACHABLE_TTL);
+            }
+        };
+        let status = res.status();
+        if status.is_server_error() {
+            warn!(url = %robots_url, %status, "robots.txt unreachable, pausing host");
+            return (Rules::disallow_all(), UNREACHABLE_TTL);
+        }
+        if !status.is_success() {
+            // no robots.txt, no restrictions
+            return (Rules::allow_all(), ROBOTS_TTL);
+        }
+        match res.text().await {
+            Ok(text) => {
+                let rules = Robots::parse(&text).rules_for(&self.user_agent);
+                info!(url = %robots_url, delay = ?rules.crawl_delay, "loaded robots.txt");
+                (rules, ROBOTS_TTL)
+            }
+            Err(_) => (Rules::disallow_all(), UNREACHABLE_TTL),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RobotsMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let url = req.url().clone();
+        let Some(host) = url.host_str().map(String::from) else {
+            return next.run(req, extensions).await;
+        };
+        if url.path() == "/robots.txt" {
+            return next.run(req, extensions).await;
+        }
+
+        if self.is_stale(&host).await {
+            let gate = self
+                .fetching
+                .lock()
+                .await
+                .entry(host.clone())
+                .or_default()
+                .clone();
+            let _fetching = gate.lock().await;
+            // another request may have loaded it while we waited
+            if self.is_stale(&host).await {
+                let (rules, ttl) = self.fetch(&url, extensions, next.clone()).await;
+                let mut hosts = self.hosts.lock().await;
+                let last_request = hosts.get(&host).and_then(|s| s.last_request);
+                hosts.insert(
+                    host.clone(),
+                    HostState {
+                        rules,
+                        fetched: Instant::now(),
+                        ttl,
+                        last_request,
+                    },
+                );
+            }
+        }
+
+        let mut hosts = self.hosts.lock().await;
+        let Some(state) = hosts.get_mut(&host) else {
+            return next.run(req, extensions).await;
+        };
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{query}", url.path()),
+            None => url.path().to_string(),
+        };
+        if !state.rules.is_allowed(&path) {
+            let err = ForumStreamError::RobotsDisallowed(url.to_string());
+            return Err(reqwest_middleware::Error::Middleware(err.into()));
+        }
+
+        let delay = match (state.rules.crawl_delay, self.min_delay) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        let mut wait = Duration::ZERO;
+        if let (Some(delay), Some(last)) = (delay, state.last_request) {
+            wait = (last + delay).saturating_duration_since(Instant::now());
+        }
+        // claim the slot before sleeping so other requests queue behind it
+        state.last_request = Some(Instant::now() + wait);
+        drop(hosts);
+        if !wait.is_zero() {
+            debug!(%host, ?wait, "crawl-delay");
+            tokio::time::sleep(wait).await;
+        }
+
+        next.run(req, extensions).await
+    }
+}
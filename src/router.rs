@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+use discourse::bundle::PostData;
+use serde::{Deserialize, Serialize};
+use serenity::all::ChannelId;
+
+use crate::{error::ForumStreamError, metadata::MetadataCache};
+
+// every condition that is set has to hold; an empty match matches everything
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct RouteMatch {
+    // also matches subcategories when a MetadataCache is given
+    pub category: Option<u64>,
+    pub tag: Option<String>,
+    pub username: Option<String>,
+    // 1 regular, 2 whisper, 3 small action, 4 moderator action
+    pub post_type: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteRule {
+    #[serde(default)]
+    pub when: RouteMatch,
+    pub channel: ChannelId,
+    // keep evaluating later rules after this one matched, to post in
+    // several channels
+    #[serde(default, rename = "continue")]
+    pub fall_through: bool,
+}
+
+// in rule order; see RouteRule::from_str for the one-line form
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Router {
+    pub rules: Vec<RouteRule>,
+}
+
+impl RouteMatch {
+    pub fn matches(&self, post_data: &PostData, cache: Option<&MetadataCache>) -> bool {
+        let post = &post_data.post;
+        if let Some(category) = self.category {
+            let own = post_data.category.id;
+            let in_path =
+                cache.is_some_and(|c| c.category_path(own).iter().any(|p| p.id == category));
+            if own != category && !in_path {
+                return false;
+            }
+        }
+        if let Some(tag) = &self.tag {
+            if !post_data
+                .topic
+                .tags
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(tag))
+            {
+                return false;
+            }
+        }
+        if let Some(username) = &self.username {
+            if !post.username.eq_ignore_ascii_case(username) {
+                return false;
+            }
+        }
+        if let Some(post_type) = self.post_type {
+            if post.post_type as i64 != post_type as i64 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Router {
+    // destination channels, in rule order without duplicates; empty when no
+    // rule matched and the caller should use its own default
+    pub fn route(&self, post_data: &PostData, cache: Option<&MetadataCache>) -> Vec<ChannelId> {
+        let mut channels = Vec::new();
+        for rule in &self.rules {
+            if !rule.when.matches(post_data, cache) {
+                continue;
+            }
+            if !channels.contains(&rule.channel) {
+                channels.push(rule.channel);
+            }
+            if !rule.fall_through {
+                break;
+            }
+        }
+        channels
+    }
+
+    pub fn parse_lines(s: &str) -> Result<Self, ForumStreamError> {
+        let rules = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(RouteRule::from_str)
+            .collect::<Result<_, _>>()?;
+        Ok(Router { rules })
+    }
+}
+
+fn parse_channel(s: &str) -> Option<ChannelId> {
+    // raw id or a <#id> mention copied from Discord
+    let s = s.trim();
+    let id = s
+        .strip_prefix("<#")
+        .and_then(|s| s.strip_suffix('>'))
+        .unwrap_or(s);
+    id.parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(ChannelId::new)
+}
+
+// "tag=release category=5 -> 1234", "username=system -> <#1234>",
+// "post_type=4 ->> 1234" (->> keeps evaluating, like `continue = true`)
+impl FromStr for RouteRule {
+    type Err = ForumStreamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| ForumStreamError::Config(format!("route `{s}`: {reason}"));
+        let (conditions, channel, fall_through) = if let Some((c, ch)) = s.split_once("->>") {
+            (c, ch, true)
+        } else if let Some((c, ch)) = s.split_once("->").or_else(|| s.split_once('→')) {
+            (c, ch, false)
+        } else {
+            return Err(invalid("missing `->`"));
+        };
+        let channel = parse_channel(channel).ok_or_else(|| invalid("bad channel id"))?;
+
+        let mut when = RouteMatch::default();
+        for condition in conditions.split([',', ' ']).filter(|c| !c.is_empty()) {
+            let (key, value) = condition
+                .split_once('=')
+                .ok_or_else(|| invalid("conditions look like key=value"))?;
+            match key {
+                "category" => {
+                    when.category = Some(value.parse().map_err(|_| invalid("bad category id"))?)
+                }
+                "tag" => when.tag = Some(value.to_string()),
+                "username" => when.username = Some(value.trim_start_matches('@').to_string()),
+                "post_type" => {
+                    when.post_type = Some(value.parse().map_err(|_| invalid("bad post_type"))?)
+                }
+                other => return Err(invalid(&format!("unknown condition `{other}`"))),
+            }
+        }
+        Ok(RouteRule {
+            when,
+            channel,
+            fall_through,
+        })
+    }
+}
+
+impl fmt::Display for RouteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut conditions = Vec::new();
+        if let Some(category) = self.when.category {
+            conditions.push(format!("category={category}"));
+        }
+        if let Some(tag) = &self.when.tag {
+            conditions.push(format!("tag={tag}"));
+        }
+        if let Some(username) = &self.when.username {
+            conditions.push(format!("username={username}"));
+        }
+        if let Some(post_type) = self.when.post_type {
+            conditions.push(format!("post_type={post_type}"));
+        }
+        conditions.push(String::from(if self.fall_through { "->>" } else { "->" }));
+        write!(f, "{} {}", conditions.join(" "), self.channel)
+    }
+}
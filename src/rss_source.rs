@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use reqwest_middleware::ClientWithMiddleware;
+use rss::Channel;
+use serenity::all::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter};
+
+use crate::{md::html_to_md, utils::trim_to_n_chars};
+
+// what can be recovered from a feed item when the JSON API is unavailable
+#[derive(Debug, Clone)]
+pub struct RssPost {
+    pub topic_title: String,
+    pub link: String,
+    pub username: Option<String>,
+    pub cooked: String,
+    pub published: Option<DateTime<Utc>>,
+    pub topic_id: Option<u64>,
+    pub post_number: Option<u64>,
+}
+
+// /t/{slug}/{topic_id}/{post_number}, post number optional
+fn parse_topic_link(link: &str) -> (Option<u64>, Option<u64>) {
+    let Ok(url) = url::Url::parse(link) else {
+        return (None, None);
+    };
+    let segments: Vec<&str> = url.path_segments().map(|s| s.collect()).unwrap_or_default();
+    let Some(t) = segments.iter().position(|s| *s == "t") else {
+        return (None, None);
+    };
+    let numbers: Vec<u64> = segments[t + 1..]
+        .iter()
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    (numbers.first().copied(), numbers.get(1).copied())
+}
+
+pub fn parse_feed(xml: &[u8]) -> anyhow::Result<Vec<RssPost>> {
+    let channel = Channel::read_from(xml)?;
+    let mut ret = Vec::new();
+    for item in channel.items() {
+        let Some(link) = item.link() else {
+            continue;
+        };
+        let (topic_id, post_number) = parse_topic_link(link);
+        let username = item
+            .dublin_core_ext()
+            .and_then(|dc| dc.creators().first())
+            .or(item.author())
+            .map(|c| c.trim_start_matches('@').to_string());
+        let published = item
+            .pub_date()
+            .and_then(|d| DateTime::parse_from_rfc2822(d).ok())
+            .map(|d| d.with_timezone(&Utc));
+        ret.push(RssPost {
+            topic_title: item.title().unwrap_or_default().to_string(),
+            link: link.to_string(),
+            username,
+            cooked: item.description().unwrap_or_default().to_string(),
+            published,
+            topic_id,
+            post_number,
+        });
+    }
+    Ok(ret)
+}
+
+// degraded-mode source for when the API is blocked but feeds are public
+pub struct RssSource {
+    client: ClientWithMiddleware,
+    base_url: String,
+}
+
+impl RssSource {
+    pub fn new(client: ClientWithMiddleware, base_url: &str) -> Self {
+        RssSource {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn fetch(&self, path: &str) -> anyhow::Result<Vec<RssPost>> {
+        let body = self
+            .client
+            .get(format!("{}{path}", self.base_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        parse_feed(&body)
+    }
+
+    pub async fn latest(&self) -> anyhow::Result<Vec<RssPost>> {
+        self.fetch("/latest.rss").await
+    }
+
+    pub async fn topic(&self, topic_id: u64) -> anyhow::Result<Vec<RssPost>> {
+        self.fetch(&format!("/t/{topic_id}.rss")).await
+    }
+}
+
+impl RssPost {
+    pub fn to_embed(&self) -> CreateEmbed {
+        let title = match self.post_number {
+            Some(n) => format!("{} #{n}", self.topic_title),
+            None => self.topic_title.clone(),
+        };
+        let description = trim_to_n_chars(&html_to_md(&self.cooked), 1900);
+        let mut embed = CreateEmbed::new()
+            .title(title)
+            .url(&self.link)
+            .description(description)
+            .footer(CreateEmbedFooter::new("via RSS"));
+        if let Some(username) = &self.username {
+            embed = embed.author(CreateEmbedAuthor::new(username));
+        }
+        if let Some(published) = self.published {
+            embed = embed.timestamp(published);
+        }
+        embed
+    }
+}
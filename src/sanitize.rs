@@ -0,0 +1,104 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// zero-width space; breaks the mention without visibly changing the text
+const ZWSP: char = '\u{200B}';
+
+pub const INVITE_REPLACEMENT: &str = "[invite removed]";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SanitizePolicy {
+    // @everyone / @here
+    pub neutralize_mass_mentions: bool,
+    // raw <@id>, <@&role> and <#channel> syntax typed into a post; turn this
+    // off when content comes from html_to_md_with_mentions
+    pub neutralize_discord_mentions: bool,
+    pub strip_invites: bool,
+    pub escape_titles: bool,
+}
+
+impl Default for SanitizePolicy {
+    fn default() -> Self {
+        SanitizePolicy {
+            neutralize_mass_mentions: true,
+            neutralize_discord_mentions: true,
+            strip_invites: true,
+            escape_titles: true,
+        }
+    }
+}
+
+impl SanitizePolicy {
+    // for trusted forums that want content passed through verbatim
+    pub fn permissive() -> Self {
+        SanitizePolicy {
+            neutralize_mass_mentions: false,
+            neutralize_discord_mentions: false,
+            strip_invites: false,
+            escape_titles: false,
+        }
+    }
+
+    pub fn sanitize_content(&self, text: &str) -> String {
+        let mut ret = text.to_string();
+        if self.strip_invites {
+            ret = strip_invites(&ret);
+        }
+        if self.neutralize_mass_mentions {
+            ret = neutralize_mass_mentions(&ret);
+        }
+        if self.neutralize_discord_mentions {
+            ret = neutralize_discord_mentions(&ret);
+        }
+        ret
+    }
+
+    pub fn sanitize_title(&self, title: &str) -> String {
+        let title = self.sanitize_content(title);
+        if self.escape_titles {
+            escape_markdown(&title)
+        } else {
+            title
+        }
+    }
+}
+
+pub fn neutralize_mass_mentions(text: &str) -> String {
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)@(everyone|here)\b").unwrap());
+    RE.replace_all(text, |caps: &regex::Captures| {
+        format!("@{ZWSP}{}", &caps[1])
+    })
+    .into_owned()
+}
+
+pub fn neutralize_discord_mentions(text: &str) -> String {
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<([@#])([!&]?\d+)>").unwrap());
+    RE.replace_all(text, |caps: &regex::Captures| {
+        format!("<{}{ZWSP}{}>", &caps[1], &caps[2])
+    })
+    .into_owned()
+}
+
+pub fn strip_invites(text: &str) -> String {
+    static RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?i)(https?://)?(www\.)?(discord\.gg|discord(app)?\.com/invite|dsc\.gg)/[a-z0-9-]+",
+        )
+        .unwrap()
+    });
+    RE.replace_all(text, INVITE_REPLACEMENT).into_owned()
+}
+
+// for usernames and titles dropped into markdown
+pub fn escape_markdown(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '[' | ']') {
+            ret.push('\\');
+        }
+        ret.push(c);
+    }
+    ret
+}
@@ -0,0 +1,157 @@
+use discourse::bundle::PostData;
+use reqwest::Url;
+use serenity::all::{CreateEmbed, GuildId};
+use sqlx::{Pool, Postgres};
+
+use crate::{
+    database::TenantManager, error::Result, md::html_to_md_with_base, utils::trim_to_n_chars,
+};
+
+pub const SEARCH_COLOR: u32 = 0x3498DB;
+pub const DEFAULT_LIMIT: i64 = 5;
+// titles outrank body text
+const TSVECTOR: &str =
+    "setweight(to_tsvector('english', $3), 'A') || setweight(to_tsvector('english', $4), 'B')";
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub post_id: i64,
+    pub topic_id: i64,
+    pub title: String,
+    // matched words wrapped in **
+    pub snippet: String,
+    // one per mirrored message, oldest first
+    pub message_links: Vec<String>,
+}
+
+pub fn message_link(guild_id: GuildId, channel_id: i64, message_id: i64) -> String {
+    format!(
+        "https://discord.com/channels/{guild_id}/{}/{}",
+        channel_id as u64, message_id as u64
+    )
+}
+
+pub struct SearchIndex {
+    pool: Pool<Postgres>,
+}
+
+impl SearchIndex {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        SearchIndex { pool }
+    }
+
+    // indexes what Discord showed; an edit simply overwrites the row
+    pub async fn index_post(&self, post_data: &PostData, base_url: &str) -> Result<()> {
+        let post_id: i64 = serde_json::from_value(serde_json::to_value(&post_data.post.id)?)?;
+        let topic_id: i64 = serde_json::from_value(serde_json::to_value(&post_data.topic.id)?)?;
+        let markdown = html_to_md_with_base(&post_data.post.cooked, base_url);
+        sqlx::query(&format!(
+            r#"INSERT INTO post_search (post_id, topic_id, title, markdown, search)
+               VALUES ($1, $2, $3, $4, {TSVECTOR})
+               ON CONFLICT (post_id) DO UPDATE
+               SET topic_id = $2, title = $3, markdown = $4, search = {TSVECTOR}, indexed_at = now()"#
+        ))
+        .bind(post_id)
+        .bind(topic_id)
+        .bind(&post_data.topic.title)
+        .bind(&markdown)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_post(&self, post_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM post_search WHERE post_id = $1")
+            .bind(post_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // `query` uses web search syntax ("quoted phrases", -excluded, or);
+    // posts that were indexed but never mirrored come back without links
+    pub async fn search(
+        &self,
+        guild_id: GuildId,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>> {
+        let rows: Vec<(i64, i64, String, String, Vec<i64>, Vec<i64>)> = sqlx::query_as(
+            r#"SELECT s.post_id, s.topic_id, s.title,
+                      ts_headline('english', s.markdown, q,
+                                  'StartSel=**, StopSel=**, MaxWords=30, MinWords=10'),
+                      COALESCE(m.channels, '{}'), COALESCE(m.messages, '{}')
+               FROM post_search s
+               CROSS JOIN websearch_to_tsquery('english', $1) q
+               LEFT JOIN LATERAL (
+                   SELECT array_agg(discord_channel_id ORDER BY created_at) AS channels,
+                          array_agg(discord_message_id ORDER BY created_at) AS messages
+                   FROM discord_mappings
+                   WHERE post_id = s.post_id AND discord_channel_id IS NOT NULL
+               ) m ON TRUE
+               WHERE s.search @@ q
+               ORDER BY ts_rank(s.search, q) DESC, s.post_id DESC
+               LIMIT $2"#,
+        )
+        .bind(query)
+        .bind(limit.max(1))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(
+                |(post_id, topic_id, title, snippet, channels, messages)| SearchHit {
+                    post_id,
+                    topic_id,
+                    title,
+                    snippet,
+                    message_links: channels
+                        .into_iter()
+                        .zip(messages)
+                        .map(|(channel, message)| message_link(guild_id, channel, message))
+                        .collect(),
+                },
+            )
+            .collect())
+    }
+}
+
+// backs the /search command
+pub async fn search_posts(
+    tenants: &TenantManager,
+    tenant: &str,
+    guild_id: GuildId,
+    query: &str,
+) -> Result<Vec<SearchHit>> {
+    let pool = tenants.get_or_create(tenant).await?;
+    SearchIndex::new(pool)
+        .search(guild_id, query, DEFAULT_LIMIT)
+        .await
+}
+
+// /search reply; each hit links the mirrored message, or the forum post if
+// it never made it to Discord
+pub fn create_search_embed(query: &str, hits: &[SearchHit], base_url: &str) -> CreateEmbed {
+    let mut embed = CreateEmbed::new()
+        .title(format!("Search: {}", trim_to_n_chars(query, 200)))
+        .color(SEARCH_COLOR);
+    // the same query on the forum, which also covers unmirrored posts
+    if let Ok(url) = Url::parse_with_params(&format!("{base_url}/search"), &[("q", query)]) {
+        embed = embed.url(url.as_str());
+    }
+    if hits.is_empty() {
+        return embed.description("No mirrored posts matched.");
+    }
+    hits.iter().fold(embed, |embed, hit| {
+        let link = hit
+            .message_links
+            .first()
+            .cloned()
+            .unwrap_or_else(|| format!("{base_url}/p/{}", hit.post_id));
+        embed.field(
+            trim_to_n_chars(&hit.title, 250),
+            format!("{}\n{link}", trim_to_n_chars(&hit.snippet, 800)),
+            false,
+        )
+    })
+}
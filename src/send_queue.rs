@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, Response, StatusCode, header::HeaderMap};
+use serde_json::{Value, json};
+use serenity::all::{ChannelId, CreateEmbed, MessageId};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+use crate::{
+    error::{ForumStreamError, Result},
+    outbox::{OutboxMessage, OutboxSender},
+};
+
+pub const DISCORD_API: &str = "https://discord.com/api/v10";
+// per message limits
+pub const MAX_EMBEDS: usize = 10;
+pub const MAX_EMBED_CHARS: usize = 6000;
+const MAX_RETRIES: u32 = 5;
+const QUEUE_DEPTH: usize = 256;
+
+// what counts towards the 6000 character total
+fn embed_chars(embed: &Value) -> usize {
+    let len = |v: &Value| v.as_str().map(|s| s.chars().count()).unwrap_or(0);
+    let mut total = len(&embed["title"])
+        + len(&embed["description"])
+        + len(&embed["footer"]["text"])
+        + len(&embed["author"]["name"]);
+    for field in embed["fields"].as_array().into_iter().flatten() {
+        total += len(&field["name"]) + len(&field["value"]);
+    }
+    total
+}
+
+// as few messages as the 10 embed / 6000 character limits allow, in order
+pub fn batch_embeds(embeds: Vec<Value>) -> Vec<Vec<Value>> {
+    let mut batches: Vec<Vec<Value>> = Vec::new();
+    let mut chars = 0;
+    for embed in embeds {
+        let size = embed_chars(&embed);
+        let fits = batches
+            .last()
+            .is_some_and(|b| b.len() < MAX_EMBEDS && chars + size <= MAX_EMBED_CHARS);
+        if !fits {
+            batches.push(Vec::new());
+            chars = 0;
+        }
+        chars += size;
+        if let Some(batch) = batches.last_mut() {
+            batch.push(embed);
+        }
+    }
+    batches
+}
+
+#[derive(Default)]
+struct Limits {
+    // per channel, from X-RateLimit-Remaining / Reset-After
+    channels: HashMap<ChannelId, Instant>,
+    global: Option<Instant>,
+}
+
+struct Job {
+    payload: Value,
+    reply: oneshot::Sender<Result<MessageId>>,
+}
+
+// serializes sends per channel so messages keep their order, waits out
+// Discord's rate limit buckets before hitting them and retries 429s
+pub struct SendQueue {
+    client: Client,
+    token: String,
+    api_base: String,
+    queues: Mutex<HashMap<ChannelId, mpsc::Sender<Job>>>,
+    limits: Arc<Mutex<Limits>>,
+}
+
+fn header_secs(headers: &HeaderMap, name: &str) -> Option<Duration> {
+    let secs: f64 = headers.get(name)?.to_str().ok()?.parse().ok()?;
+    Some(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+impl SendQueue {
+    pub fn new(token: &str) -> Self {
+        SendQueue {
+            client: Client::new(),
+            token: token.to_string(),
+            api_base: String::from(DISCORD_API),
+            queues: Mutex::new(HashMap::new()),
+            limits: Arc::new(Mutex::new(Limits::default())),
+        }
+    }
+
+    pub fn with_api_base(mut self, api_base: &str) -> Self {
+        self.api_base = api_base.trim_end_matches('/').to_string();
+        self
+    }
+
+    // one message per batch of up to 10 embeds. Each id is pushed onto `sent`
+    // as soon as its batch lands, so after a failure the caller still has the
+    // messages already posted; passing the same `sent` back resumes at the
+    // first unsent batch instead of posting duplicates.
+    pub async fn send(
+        &self,
+        channel_id: ChannelId,
+        embeds: &[CreateEmbed],
+        sent: &mut Vec<MessageId>,
+    ) -> Result<()> {
+        let embeds = embeds
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for batch in batch_embeds(embeds).into_iter().skip(sent.len()) {
+            let payload = json!({ "embeds": batch, "allowed_mentions": { "parse": [] } });
+            sent.push(self.send_payload(channel_id, payload).await?);
+        }
+        Ok(())
+    }
+
+    // a full message body, e.g. one built by the outbox
+    pub async fn send_payload(&self, channel_id: ChannelId, payload: Value) -> Result<MessageId> {
+        let (reply, rx) = oneshot::channel();
+        let queue = self.queue(channel_id);
+        queue
+            .send(Job { payload, reply })
+            .await
+            .map_err(|_| ForumStreamError::Data(String::from("send queue closed")))?;
+        rx.await
+            .map_err(|_| ForumStreamError::Data(String::from("send queue dropped the message")))?
+    }
+
+    fn queue(&self, channel_id: ChannelId) -> mpsc::Sender<Job> {
+        let mut queues = self.queues.lock().unwrap();
+        if let Some(queue) = queues.get(&channel_id).filter(|q| !q.is_closed()) {
+            return queue.clone();
+        }
+        let (tx, mut rx) = mpsc::channel::<Job>(QUEUE_DEPTH);
+        let worker = Worker {
+            client: self.client.clone(),
+            token: self.token.clone(),
+            url: format!("{}/channels/{channel_id}/messages", self.api_base),
+            channel_id,
+            limits: Arc::clone(&self.limits),
+        };
+        tokio::spawn(async move {
+            while let Some(job) = rx.recv().await {
+                let result = worker.deliver(&job.payload).await;
+                let _ = job.reply.send(result);
+            }
+        });
+        queues.insert(channel_id, tx.clone());
+        tx
+    }
+}
+
+struct Worker {
+    client: Client,
+    token: String,
+    url: String,
+    channel_id: ChannelId,
+    limits: Arc<Mutex<Limits>>,
+}
+
+impl Worker {
+    fn wait_time(&self) -> Duration {
+        let limits = self.limits.lock().unwrap();
+        let now = Instant::now();
+        [
+            limits.channels.get(&self.channel_id).copied(),
+            limits.global,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|until| until.saturating_duration_since(now))
+        .max()
+        .unwrap_or_default()
+    }
+
+    fn record(&self, res: &Response) {
+        let headers = res.headers();
+        let exhausted = headers
+            .get("x-ratelimit-remaining")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v == "0");
+        if exhausted {
+            if let Some(reset) = header_secs(headers, "x-ratelimit-reset-after") {
+                let mut limits = self.limits.lock().unwrap();
+                limits
+                    .channels
+                    .insert(self.channel_id, Instant::now() + reset);
+            }
+        }
+    }
+
+    async fn deliver(&self, payload: &Value) -> Result<MessageId> {
+        let mut attempt = 0;
+        loop {
+            let wait = self.wait_time();
+            if !wait.is_zero() {
+                debug!(channel = %self.channel_id, ?wait, "waiting for discord bucket");
+                tokio::time::sleep(wait).await;
+            }
+            let res = self
+                .client
+                .post(&self.url)
+                .header("Authorization", format!("Bot {}", self.token))
+                .json(payload)
+                .send()
+                .await?;
+            self.record(&res);
+
+            if res.status() != StatusCode::TOO_MANY_REQUESTS {
+                let res = res.error_for_status()?;
+                let message: Value = res.json().await?;
+                let id = message["id"]
+                    .as_str()
+                    .and_then(|id| id.parse::<u64>().ok())
+                    .ok_or(ForumStreamError::MissingField("id"))?;
+                return Ok(MessageId::new(id));
+            }
+
+            let global = res
+                .headers()
+                .get("x-ratelimit-global")
+                .is_some_and(|v| v.as_bytes() == b"true");
+            let header_wait = header_secs(res.headers(), "retry-after");
+            let body: Value = res.json().await.unwrap_or_default();
+            let retry_after = body["retry_after"]
+                .as_f64()
+                .map(|s| Duration::from_secs_f64(s.max(0.0)))
+                .or(header_wait)
+                .unwrap_or(Duration::from_secs(1));
+            {
+                let mut limits = self.limits.lock().unwrap();
+                let until = Instant::now() + retry_after;
+                if global || body["global"].as_bool() == Some(true) {
+                    limits.global = Some(until);
+                } else {
+                    limits.channels.insert(self.channel_id, until);
+                }
+            }
+            attempt += 1;
+            if attempt > MAX_RETRIES {
+                return Err(ForumStreamError::Data(format!(
+                    "still rate limited in channel {} after {MAX_RETRIES} retries",
+                    self.channel_id
+                )));
+            }
+            warn!(channel = %self.channel_id, ?retry_after, global, "discord 429");
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxSender for SendQueue {
+    async fn send(&self, message: &OutboxMessage) -> anyhow::Result<Vec<MessageId>> {
+        let id = self
+            .send_payload(message.channel_id, message.payload.clone())
+            .await?;
+        Ok(vec![id])
+    }
+}
@@ -0,0 +1,108 @@
+use reqwest::Client;
+use serde_json::{Value, json};
+use tracing::warn;
+
+#[async_trait::async_trait]
+pub trait UrlShortener: Send + Sync {
+    async fn shorten(&self, url: &str) -> anyhow::Result<String>;
+}
+
+pub struct Passthrough;
+
+#[async_trait::async_trait]
+impl UrlShortener for Passthrough {
+    async fn shorten(&self, url: &str) -> anyhow::Result<String> {
+        Ok(url.to_string())
+    }
+}
+
+pub struct ShlinkShortener {
+    client: Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl ShlinkShortener {
+    pub fn new(client: Client, base_url: String, api_key: String) -> Self {
+        ShlinkShortener {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl UrlShortener for ShlinkShortener {
+    async fn shorten(&self, url: &str) -> anyhow::Result<String> {
+        let resp: Value = self
+            .client
+            .post(format!("{}/rest/v3/short-urls", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .json(&json!({ "longUrl": url, "findIfExists": true }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        resp.get("shortUrl")
+            .and_then(|s| s.as_str())
+            .map(String::from)
+            .ok_or_else(|| anyhow::anyhow!("Shlink response without shortUrl"))
+    }
+}
+
+pub struct YourlsShortener {
+    client: Client,
+    api_url: String,
+    signature: String,
+}
+
+impl YourlsShortener {
+    // api_url is the full yourls-api.php URL
+    pub fn new(client: Client, api_url: String, signature: String) -> Self {
+        YourlsShortener {
+            client,
+            api_url,
+            signature,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl UrlShortener for YourlsShortener {
+    async fn shorten(&self, url: &str) -> anyhow::Result<String> {
+        let resp: Value = self
+            .client
+            .get(&self.api_url)
+            .query(&[
+                ("signature", self.signature.as_str()),
+                ("action", "shorturl"),
+                ("format", "json"),
+                ("url", url),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+        // YOURLS reports already-shortened URLs as a failure but still returns them
+        resp.get("shorturl")
+            .and_then(|s| s.as_str())
+            .map(String::from)
+            .ok_or_else(|| anyhow::anyhow!("YOURLS response without shorturl"))
+    }
+}
+
+// only pay for a round trip when the URL doesn't fit; falls back to the original
+pub async fn shorten_if_longer(shortener: &dyn UrlShortener, url: &str, max_len: usize) -> String {
+    if url.chars().count() <= max_len {
+        return url.to_string();
+    }
+    match shortener.shorten(url).await {
+        Ok(short) => short,
+        Err(e) => {
+            warn!(url, error = %e, "url shortening failed");
+            url.to_string()
+        }
+    }
+}
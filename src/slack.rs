@@ -0,0 +1,95 @@
+use discourse::bundle::PostData;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::{Value, json};
+
+use crate::{formatter::PostParts, theme::EmbedTheme, utils::trim_to_n_chars};
+
+// section text objects are capped at 3000 characters
+const MAX_SECTION_CHARS: usize = 3000;
+const MAX_IMAGES: usize = 9;
+
+fn escape_slack(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// converts the Discord-flavored output of html_to_md into Slack mrkdwn
+pub fn discord_md_to_mrkdwn(md: &str) -> String {
+    static LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+)\)").unwrap());
+    static BOLD: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*(.+?)\*\*").unwrap());
+    static ITALIC: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|[^*])\*([^*\n]+)\*").unwrap());
+    static STRIKE: Lazy<Regex> = Lazy::new(|| Regex::new(r"~~(.+?)~~").unwrap());
+    static SPOILER: Lazy<Regex> = Lazy::new(|| Regex::new(r"\|\|(.+?)\|\|").unwrap());
+
+    let escaped = escape_slack(md);
+    let s = SPOILER.replace_all(&escaped, "$1");
+    let s = LINK.replace_all(&s, "<$2|$1>");
+    let s = ITALIC.replace_all(&s, "${1}_${2}_");
+    let s = BOLD.replace_all(&s, "*$1*");
+    let s = STRIKE.replace_all(&s, "~$1~");
+    // html_to_md escapes with backslashes, which Slack shows literally
+    s.replace("\\_", "_").replace("\\*", "*")
+}
+
+pub fn create_slack_blocks(post_data: &PostData, theme: &EmbedTheme) -> Option<Value> {
+    let parts = PostParts::from_post_data(post_data, theme)?;
+    let PostParts {
+        url,
+        title,
+        author_name: author,
+        author_url,
+        avatar_url: avatar,
+        ..
+    } = &parts;
+    let text = discord_md_to_mrkdwn(&parts.content);
+
+    let mut context = Vec::new();
+    // an anonymous identity may come without an icon
+    if !avatar.is_empty() {
+        context.push(json!({ "type": "image", "image_url": avatar, "alt_text": author }));
+    }
+    context.push(json!({
+        "type": "mrkdwn",
+        "text": format!("<{author_url}|{}>", escape_slack(author)),
+    }));
+    let mut blocks = vec![
+        json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format!("*<{url}|{}>*", escape_slack(title)),
+            }
+        }),
+        json!({ "type": "context", "elements": context }),
+    ];
+    if !text.trim().is_empty() {
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": trim_to_n_chars(&text, MAX_SECTION_CHARS),
+            }
+        }));
+    }
+    for src in parts
+        .images
+        .iter()
+        .filter(|src| src.starts_with("http"))
+        .take(MAX_IMAGES)
+    {
+        blocks.push(json!({
+            "type": "image",
+            "image_url": src,
+            "alt_text": "image",
+        }));
+    }
+
+    Some(json!({
+        // shown in notifications and clients without Block Kit
+        "text": format!("{title} — {author}"),
+        "blocks": blocks,
+        "unfurl_links": false,
+    }))
+}
@@ -0,0 +1,224 @@
+use discourse::bundle::PostData;
+use scraper::{ElementRef, Html, Node};
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::{formatter::PostParts, theme::EmbedTheme, utils::trim_to_n_chars};
+
+const MAX_TEXT_CHARS: usize = 4096;
+const MAX_CAPTION_CHARS: usize = 1024;
+const MAX_MEDIA_GROUP: usize = 10;
+
+// one Bot API call, e.g. method "sendMessage" with its JSON body
+#[derive(Serialize, Debug, Clone)]
+pub struct TelegramRequest {
+    pub method: &'static str,
+    pub body: Value,
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn render_children(element: ElementRef, out: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape_html(text)),
+            Node::Element(_) => {
+                if let Some(el) = ElementRef::wrap(child) {
+                    render_element(el, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn wrap(element: ElementRef, tag: &str, out: &mut String) {
+    out.push_str(&format!("<{tag}>"));
+    render_children(element, out);
+    out.push_str(&format!("</{tag}>"));
+}
+
+fn render_element(element: ElementRef, out: &mut String) {
+    let value = element.value();
+    if value
+        .attr("class")
+        .is_some_and(|c| c.split_whitespace().any(|c| c == "avatar"))
+    {
+        return;
+    }
+    match value.name() {
+        "b" | "strong" => wrap(element, "b", out),
+        "i" | "em" => wrap(element, "i", out),
+        "u" | "ins" => wrap(element, "u", out),
+        "s" | "strike" | "del" => wrap(element, "s", out),
+        "code" => wrap(element, "code", out),
+        "pre" => wrap(element, "pre", out),
+        "blockquote" | "aside" => {
+            wrap(element, "blockquote", out);
+            out.push('\n');
+        }
+        "details" => wrap(element, "tg-spoiler", out),
+        "summary" => {
+            render_children(element, out);
+            out.push_str(": ");
+        }
+        "a" => match value.attr("href") {
+            Some(href) => {
+                out.push_str(&format!(
+                    r#"<a href="{}">"#,
+                    escape_html(href).replace('"', "&quot;")
+                ));
+                render_children(element, out);
+                out.push_str("</a>");
+            }
+            None => render_children(element, out),
+        },
+        // sent separately as photos
+        "img" => {
+            if value
+                .attr("class")
+                .is_some_and(|c| c.split_whitespace().any(|c| c == "emoji"))
+            {
+                out.push_str(&escape_html(value.attr("alt").unwrap_or("")));
+            }
+        }
+        "br" => out.push('\n'),
+        "li" => {
+            out.push_str("• ");
+            render_children(element, out);
+            out.push('\n');
+        }
+        "p" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "tr" => {
+            render_children(element, out);
+            out.push('\n');
+        }
+        _ => render_children(element, out),
+    }
+}
+
+// Telegram's HTML parse mode only knows a handful of tags
+pub fn cooked_to_telegram_html(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    render_children(fragment.root_element(), &mut out);
+    let mut collapsed = String::new();
+    let mut newlines = 0;
+    for c in out.trim().chars() {
+        if c == '\n' {
+            newlines += 1;
+            if newlines > 2 {
+                continue;
+            }
+        } else {
+            newlines = 0;
+        }
+        collapsed.push(c);
+    }
+    collapsed
+}
+
+fn strip_tags(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    fragment.root_element().text().collect()
+}
+
+pub fn create_telegram_text(post_data: &PostData, theme: &EmbedTheme) -> Option<String> {
+    let parts = PostParts::from_post_data(post_data, theme)?;
+    telegram_text(post_data, &parts)
+}
+
+fn telegram_text(post_data: &PostData, parts: &PostParts) -> Option<String> {
+    let PostParts {
+        url,
+        title,
+        author_name,
+        ..
+    } = parts;
+    let mut text = format!(
+        "<b><a href=\"{}\">{}</a></b>\n<i>{}</i>\n\n",
+        escape_html(url),
+        escape_html(title),
+        escape_html(author_name)
+    );
+    if let Some(replying_to) = &post_data.replying_to_post {
+        let quoted = trim_to_n_chars(&strip_tags(&replying_to.cooked), 500);
+        text.push_str(&format!(
+            "<blockquote>{}\n— {}</blockquote>\n",
+            escape_html(quoted.trim()),
+            escape_html(&replying_to.username)
+        ));
+    }
+    text.push_str(&cooked_to_telegram_html(&post_data.post.cooked));
+    if text.chars().count() > MAX_TEXT_CHARS {
+        // cutting HTML mid-tag is rejected by the API, so fall back to plain text
+        let plain = strip_tags(&post_data.post.cooked);
+        text = format!(
+            "<b><a href=\"{}\">{}</a></b>\n\n{}…",
+            escape_html(url),
+            escape_html(title),
+            escape_html(&trim_to_n_chars(&plain, MAX_TEXT_CHARS - 200))
+        );
+    }
+    Some(text)
+}
+
+pub fn create_telegram_requests(
+    post_data: &PostData,
+    theme: &EmbedTheme,
+    chat_id: &str,
+) -> Option<Vec<TelegramRequest>> {
+    let parts = PostParts::from_post_data(post_data, theme)?;
+    let text = telegram_text(post_data, &parts)?;
+    let images: Vec<&String> = parts
+        .images
+        .iter()
+        .filter(|src| src.starts_with("http"))
+        .take(MAX_MEDIA_GROUP)
+        .collect();
+
+    let mut ret = vec![TelegramRequest {
+        method: "sendMessage",
+        body: json!({
+            "chat_id": chat_id,
+            "text": text,
+            "parse_mode": "HTML",
+            "disable_web_page_preview": true,
+        }),
+    }];
+
+    let caption = trim_to_n_chars(&parts.title, MAX_CAPTION_CHARS);
+    match images.len() {
+        0 => {}
+        1 => ret.push(TelegramRequest {
+            method: "sendPhoto",
+            body: json!({
+                "chat_id": chat_id,
+                "photo": images[0],
+                "caption": caption,
+            }),
+        }),
+        _ => {
+            let media: Vec<Value> = images
+                .iter()
+                .enumerate()
+                .map(|(i, src)| {
+                    // only the first item's caption is shown for the album
+                    if i == 0 {
+                        json!({ "type": "photo", "media": src, "caption": caption })
+                    } else {
+                        json!({ "type": "photo", "media": src })
+                    }
+                })
+                .collect();
+            ret.push(TelegramRequest {
+                method: "sendMediaGroup",
+                body: json!({ "chat_id": chat_id, "media": media }),
+            });
+        }
+    }
+    Some(ret)
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use discourse::bundle::PostData;
+use serde::Serialize;
+
+use crate::discord::get_link;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TemplateVariable {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub example: String,
+}
+
+// name, description, fixture value
+const VARIABLES: &[(&str, &str, &str)] = &[
+    ("post.number", "Position of the post in its topic", "4"),
+    (
+        "post.url",
+        "Link to the post",
+        "https://forum.example.com/t/1234/4",
+    ),
+    (
+        "post.created_at",
+        "Creation time (RFC 3339)",
+        "2025-01-31T12:00:00Z",
+    ),
+    (
+        "post.type",
+        "Discourse post type (1 regular, 2 whisper, 3 action)",
+        "1",
+    ),
+    ("topic.id", "Topic ID", "1234"),
+    ("topic.title", "Topic title", "Release 2.0 is out"),
+    ("category.name", "Leaf category name", "Announcements"),
+    ("category.color", "Category color as hex", "0277BD"),
+    ("author.username", "Forum username", "jdoe"),
+    (
+        "author.display_name",
+        "Display name, falling back to the username",
+        "Jane Doe",
+    ),
+    (
+        "author.url",
+        "Link to the author's profile",
+        "https://forum.example.com/u/jdoe",
+    ),
+    (
+        "author.avatar_url",
+        "Avatar image URL",
+        "https://forum.example.com/user_avatar/forum.example.com/jdoe/144/1_2.png",
+    ),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    values: BTreeMap<String, String>,
+}
+
+impl TemplateContext {
+    pub fn from_post_data(post_data: &PostData) -> Self {
+        let base_url = &post_data.base_url;
+        let post = &post_data.post;
+        let mut values = BTreeMap::new();
+        values.insert("post.number", post.post_number.to_string());
+        values.insert(
+            "post.url",
+            get_link(post_data, base_url).unwrap_or_default(),
+        );
+        values.insert("post.created_at", post.created_at.to_rfc3339());
+        values.insert("post.type", post.post_type.to_string());
+        values.insert("topic.id", post_data.topic.id.to_string());
+        values.insert("topic.title", post_data.topic.title.clone());
+        values.insert("category.name", post_data.category.name.clone());
+        values.insert(
+            "category.color",
+            post_data.category.color.trim_start_matches('#').to_string(),
+        );
+        values.insert("author.username", post.username.clone());
+        values.insert("author.display_name", post.display_username.clone());
+        values.insert("author.url", format!("{base_url}/u/{}", post.username));
+        values.insert(
+            "author.avatar_url",
+            format!(
+                "{base_url}/{}",
+                post.avatar_template.replace("{size}", "144")
+            ),
+        );
+        TemplateContext {
+            values: values
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect(),
+        }
+    }
+
+    pub fn fixture() -> Self {
+        TemplateContext {
+            values: VARIABLES
+                .iter()
+                .map(|(name, _, example)| (name.to_string(), example.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn variables() -> Vec<TemplateVariable> {
+        VARIABLES
+            .iter()
+            .map(|(name, description, example)| TemplateVariable {
+                name,
+                description,
+                example: example.to_string(),
+            })
+            .collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    // placeholders look like {post.number}; unknown ones are left as-is.
+    // One pass over the template, so braces inside a substituted value (a
+    // topic title, say) are never expanded themselves
+    pub fn render(&self, template: &str) -> String {
+        let mut ret = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            ret.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after.find(['{', '}']) {
+                Some(end) if after[end..].starts_with('}') => {
+                    let name = &after[..end];
+                    match self.get(name) {
+                        Some(value) => ret.push_str(value),
+                        None => ret.push_str(&rest[start..start + end + 2]),
+                    }
+                    rest = &after[end + 1..];
+                }
+                // a stray `{`, the next one may still open a placeholder
+                _ => {
+                    ret.push('{');
+                    rest = after;
+                }
+            }
+        }
+        ret.push_str(rest);
+        ret
+    }
+
+    pub fn unknown_placeholders(template: &str) -> Vec<String> {
+        let mut ret = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let after = &rest[start + 1..];
+            match after.find('}') {
+                Some(end) => {
+                    let name = &after[..end];
+                    if !VARIABLES.iter().any(|(v, _, _)| *v == name) {
+                        ret.push(name.to_string());
+                    }
+                    rest = &after[end + 1..];
+                }
+                None => break,
+            }
+        }
+        ret
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri, header},
+    response::{Html, IntoResponse, Response},
+    routing::post,
+};
+use discourse::bundle::PostData;
+use serde_json::{Value, json};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::pipeline::{
+    Delivery, EmbeddedPipeline, EmbeddedPipelineConfig, PostSource, RenderedPost,
+};
+
+pub struct MockServer {
+    pub addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn serve(router: Router) -> anyhow::Result<MockServer> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let handle = tokio::spawn(async move {
+        let _ = axum::serve(listener, router).await;
+    });
+    Ok(MockServer { addr, handle })
+}
+
+// canned responses keyed by path, e.g. "/latest.json"
+pub async fn mock_discourse(fixtures: HashMap<String, Value>) -> anyhow::Result<MockServer> {
+    let fixtures = Arc::new(fixtures);
+    let router = Router::new().fallback(move |uri: Uri| {
+        let fixtures = fixtures.clone();
+        async move {
+            match fixtures.get(uri.path()) {
+                Some(body) => (StatusCode::OK, Json(body.clone())),
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(json!({ "errors": ["not found"] })),
+                ),
+            }
+        }
+    });
+    serve(router).await
+}
+
+// what MockFlaresolverr solves with; the challenged mock only lets requests
+// through that carry its cf_clearance cookie
+pub const CLEARANCE_COOKIE: &str = "cf_clearance=testkit";
+pub const SOLVER_USER_AGENT: &str = "Mozilla/5.0 (testkit)";
+
+const CHALLENGE_PAGE: &str = "<html><head><title>Just a moment...</title></head>\
+<body><script src=\"/cdn-cgi/challenge-platform/h/b/orchestrate/chl_page/v1\"></script></body></html>";
+
+fn has_clearance(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.split(';').any(|c| c.trim() == CLEARANCE_COOKIE))
+}
+
+// mock_discourse behind Cloudflare: a 403 interstitial until the client
+// presents the clearance cookie MockFlaresolverr hands out
+pub async fn mock_discourse_challenged(
+    fixtures: HashMap<String, Value>,
+) -> anyhow::Result<MockServer> {
+    let fixtures = Arc::new(fixtures);
+    let router = Router::new().fallback(move |uri: Uri, headers: HeaderMap| {
+        let fixtures = fixtures.clone();
+        async move {
+            if !has_clearance(&headers) {
+                return challenge_response();
+            }
+            match fixtures.get(uri.path()) {
+                Some(body) => (StatusCode::OK, Json(body.clone())).into_response(),
+                None => (
+                    StatusCode::NOT_FOUND,
+                    Json(json!({ "errors": ["not found"] })),
+                )
+                    .into_response(),
+            }
+        }
+    });
+    serve(router).await
+}
+
+fn challenge_response() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        [("cf-mitigated", "challenge"), ("server", "cloudflare")],
+        Html(CHALLENGE_PAGE),
+    )
+        .into_response()
+}
+
+async fn flaresolverr_handler(
+    State(solves): State<Arc<Mutex<u32>>>,
+    Json(body): Json<Value>,
+) -> Json<Value> {
+    let cmd = body.get("cmd").and_then(|c| c.as_str()).unwrap_or("");
+    match cmd {
+        "sessions.create" => Json(json!({ "status": "ok", "session": "forum-stream" })),
+        "sessions.list" => Json(json!({ "status": "ok", "sessions": ["forum-stream"] })),
+        "sessions.destroy" => Json(json!({ "status": "ok" })),
+        _ => {
+            *solves.lock().unwrap() += 1;
+            let url = body.get("url").and_then(|u| u.as_str()).unwrap_or("");
+            let domain = url::Url::parse(url)
+                .ok()
+                .and_then(|u| u.host_str().map(String::from))
+                .unwrap_or_default();
+            Json(json!({
+                "status": "ok",
+                "solution": {
+                    "url": url,
+                    "status": 200,
+                    "userAgent": SOLVER_USER_AGENT,
+                    "cookies": [{
+                        "name": "cf_clearance",
+                        "value": "testkit",
+                        "domain": domain,
+                        "path": "/",
+                        "expires": (chrono::Utc::now().timestamp() + 1800) as f64,
+                        "httpOnly": true,
+                        "secure": false,
+                    }]
+                }
+            }))
+        }
+    }
+}
+
+pub struct MockFlaresolverr {
+    pub server: MockServer,
+    solves: Arc<Mutex<u32>>,
+}
+
+impl MockFlaresolverr {
+    pub async fn start() -> anyhow::Result<Self> {
+        let solves = Arc::new(Mutex::new(0));
+        let router = Router::new()
+            .route("/v1", post(flaresolverr_handler))
+            .with_state(solves.clone());
+        Ok(MockFlaresolverr {
+            server: serve(router).await?,
+            solves,
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("{}/v1", self.server.base_url())
+    }
+
+    pub fn solves(&self) -> u32 {
+        *self.solves.lock().unwrap()
+    }
+}
+
+// hands out a fixed set of posts once
+pub struct StaticSource {
+    posts: Mutex<Vec<PostData>>,
+}
+
+impl StaticSource {
+    pub fn new(posts: Vec<PostData>) -> Self {
+        StaticSource {
+            posts: Mutex::new(posts),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PostSource for StaticSource {
+    async fn poll(&self) -> anyhow::Result<Vec<PostData>> {
+        Ok(std::mem::take(&mut *self.posts.lock().unwrap()))
+    }
+}
+
+#[derive(Clone)]
+pub struct CaptureDelivery {
+    captured: Arc<Mutex<Vec<RenderedPost>>>,
+    // number of posts delivered so far, for wait_for
+    delivered: Arc<watch::Sender<usize>>,
+}
+
+impl Default for CaptureDelivery {
+    fn default() -> Self {
+        CaptureDelivery {
+            captured: Arc::new(Mutex::new(Vec::new())),
+            delivered: Arc::new(watch::channel(0).0),
+        }
+    }
+}
+
+impl CaptureDelivery {
+    pub fn len(&self) -> usize {
+        self.captured.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn take(&self) -> Vec<RenderedPost> {
+        std::mem::take(&mut *self.captured.lock().unwrap())
+    }
+
+    // true once `count` posts were delivered, false if the timeout hit first
+    pub async fn wait_for(&self, count: usize, timeout: Duration) -> bool {
+        let mut delivered = self.delivered.subscribe();
+        tokio::time::timeout(timeout, delivered.wait_for(|n| *n >= count))
+            .await
+            .is_ok_and(|r| r.is_ok())
+    }
+}
+
+#[async_trait::async_trait]
+impl Delivery for CaptureDelivery {
+    async fn deliver(&self, post: RenderedPost) -> anyhow::Result<()> {
+        self.captured.lock().unwrap().push(post);
+        self.delivered.send_modify(|n| *n += 1);
+        Ok(())
+    }
+}
+
+// runs the embedded pipeline until `expected` posts were delivered or the timeout hits
+pub async fn run_pipeline(
+    posts: Vec<PostData>,
+    config: EmbeddedPipelineConfig,
+    expected: usize,
+    timeout: Duration,
+) -> Vec<RenderedPost> {
+    run_pipeline_with(
+        Arc::new(StaticSource::new(posts)),
+        config,
+        expected,
+        timeout,
+    )
+    .await
+}
+
+// same with any source, e.g. one reading from mock_discourse
+pub async fn run_pipeline_with(
+    source: Arc<dyn PostSource>,
+    config: EmbeddedPipelineConfig,
+    expected: usize,
+    timeout: Duration,
+) -> Vec<RenderedPost> {
+    let capture = CaptureDelivery::default();
+    let pipeline = EmbeddedPipeline::new(config, source, Arc::new(capture.clone()));
+    let handle = tokio::spawn(pipeline.run());
+    capture.wait_for(expected, timeout).await;
+    handle.abort();
+    capture.take()
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use discourse::bundle::PostData;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::all::CreateEmbedAuthor;
+
+use crate::{
+    discord::_hex_color_to_int,
+    i18n::Locale,
+    md::{ImageStyle, MdOptions, QuoteStyle, RenderProfile},
+    sanitize::SanitizePolicy,
+};
+
+// Discord-ish colors for categories whose color doesn't parse
+pub const FALLBACK_PALETTE: [u32; 8] = [
+    0x5865F2, 0x57F287, 0xFEE75C, 0xEB459E, 0xED4245, 0x3498DB, 0xE67E22, 0x1ABC9C,
+];
+
+// keyed by id, remembering the hex it was parsed from so a recolor is picked up
+static CATEGORY_COLORS: Lazy<RwLock<HashMap<u64, (String, u32)>>> = Lazy::new(Default::default);
+
+pub fn fallback_color(category_id: u64) -> u32 {
+    FALLBACK_PALETTE[(category_id % FALLBACK_PALETTE.len() as u64) as usize]
+}
+
+pub fn category_color(category_id: u64, hex: &str) -> u32 {
+    if let Some((cached_hex, color)) = CATEGORY_COLORS.read().unwrap().get(&category_id) {
+        if cached_hex == hex {
+            return *color;
+        }
+    }
+    let color = _hex_color_to_int(hex).unwrap_or_else(|| {
+        tracing::warn!(
+            category_id,
+            hex,
+            "unparseable category color, using fallback"
+        );
+        fallback_color(category_id)
+    });
+    CATEGORY_COLORS
+        .write()
+        .unwrap()
+        .insert(category_id, (hex.to_string(), color));
+    color
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorName {
+    #[default]
+    DisplayName,
+    Username,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AnonymousIdentity {
+    pub name: String,
+    pub icon_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AuthorOptions {
+    pub name: AuthorName,
+    pub link_profile: bool,
+    // replaces every author with a generic identity
+    pub anonymous: Option<AnonymousIdentity>,
+}
+
+impl Default for AuthorOptions {
+    fn default() -> Self {
+        AuthorOptions {
+            name: AuthorName::DisplayName,
+            link_profile: true,
+            anonymous: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct EmbedTheme {
+    // None falls back to the category color
+    pub regular_color: Option<String>,
+    pub whisper_color: String,
+    pub action_color: Option<String>,
+    pub footer_text: Option<String>,
+    pub show_avatars: bool,
+    pub author: AuthorOptions,
+    pub profile: RenderProfile,
+    pub sanitize: SanitizePolicy,
+    // labels and admin action text; English unless overridden
+    pub locale: Locale,
+    // keeps nested quote chains from eating the description limit
+    pub max_quote_depth: Option<usize>,
+    pub quote_style: QuoteStyle,
+    // see MdOptions::math_renderer
+    pub math_renderer: Option<String>,
+    pub image_style: ImageStyle,
+}
+
+impl Default for EmbedTheme {
+    fn default() -> Self {
+        EmbedTheme {
+            regular_color: None,
+            whisper_color: String::from("#0277BD"),
+            action_color: None,
+            footer_text: None,
+            show_avatars: true,
+            author: AuthorOptions::default(),
+            profile: RenderProfile::Standard,
+            sanitize: SanitizePolicy::default(),
+            locale: Locale::english(),
+            max_quote_depth: Some(3),
+            quote_style: QuoteStyle::Full,
+            math_renderer: None,
+            image_style: ImageStyle::Placeholder,
+        }
+    }
+}
+
+impl EmbedTheme {
+    pub fn md_options(&self, base_url: &str) -> MdOptions {
+        let mut options = MdOptions::for_forum(base_url)
+            .with_profile(self.profile)
+            .with_locale(self.locale.clone())
+            .with_quote_style(self.quote_style)
+            .with_image_style(self.image_style);
+        options.max_quote_depth = self.max_quote_depth;
+        options.math_renderer = self.math_renderer.clone();
+        options
+    }
+
+    // never fails: a bad configured color falls back to the category's,
+    // and a bad category color to the palette
+    pub fn color_for(&self, post_data: &PostData) -> u32 {
+        let category = &post_data.category;
+        match self.configured_color(post_data) {
+            Some(hex) => _hex_color_to_int(hex)
+                .unwrap_or_else(|| category_color(category.id, &category.color)),
+            None => category_color(category.id, &category.color),
+        }
+    }
+
+    fn configured_color(&self, post_data: &PostData) -> Option<&String> {
+        match post_data.post.post_type {
+            2 => Some(&self.whisper_color),
+            3 => self.action_color.as_ref(),
+            _ => self.regular_color.as_ref(),
+        }
+    }
+
+    // the username a post is credited to in footers and notices; with an
+    // anonymous identity the real one never shows up
+    pub fn attribution(&self, post_data: &PostData) -> String {
+        match &self.author.anonymous {
+            Some(anon) => anon.name.clone(),
+            None => post_data.post.username.clone(),
+        }
+    }
+
+    pub fn author_name(&self, post_data: &PostData) -> String {
+        if let Some(anon) = &self.author.anonymous {
+            return anon.name.clone();
+        }
+        let post = &post_data.post;
+        match self.author.name {
+            AuthorName::DisplayName if !post.display_username.is_empty() => {
+                post.display_username.clone()
+            }
+            _ => post.username.clone(),
+        }
+    }
+
+    pub fn create_author(&self, post_data: &PostData) -> CreateEmbedAuthor {
+        let base_url = &post_data.base_url;
+        let mut author = CreateEmbedAuthor::new(self.author_name(post_data));
+        if let Some(anon) = &self.author.anonymous {
+            if let Some(icon_url) = &anon.icon_url {
+                author = author.icon_url(icon_url);
+            }
+            return author;
+        }
+        if self.author.link_profile {
+            author = author.url(format!("{base_url}/u/{}", post_data.post.username));
+        }
+        if self.show_avatars {
+            let avatar = post_data.post.avatar_template.replace("{size}", "144");
+            author = author.icon_url(format!("{base_url}/{avatar}"));
+        }
+        author
+    }
+}
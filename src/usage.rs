@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::utils::ntfy;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageMetric {
+    PostsProcessed,
+    MessagesDelivered,
+    BytesMirrored,
+    ApiCalls,
+}
+
+impl UsageMetric {
+    pub const ALL: [UsageMetric; 4] = [
+        UsageMetric::PostsProcessed,
+        UsageMetric::MessagesDelivered,
+        UsageMetric::BytesMirrored,
+        UsageMetric::ApiCalls,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageMetric::PostsProcessed => "posts_processed",
+            UsageMetric::MessagesDelivered => "messages_delivered",
+            UsageMetric::BytesMirrored => "bytes_mirrored",
+            UsageMetric::ApiCalls => "api_calls",
+        }
+    }
+}
+
+// counters roll up per calendar month (UTC)
+pub async fn record_usage(
+    pool: &Pool<Postgres>,
+    metric: UsageMetric,
+    amount: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"INSERT INTO usage_counters (month, metric, value)
+           VALUES (to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM'), $1, $2)
+           ON CONFLICT (month, metric) DO UPDATE SET value = usage_counters.value + EXCLUDED.value"#,
+    )
+    .bind(metric.as_str())
+    .bind(amount)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct UsageReport {
+    pub month: String,
+    pub posts_processed: i64,
+    pub messages_delivered: i64,
+    pub bytes_mirrored: i64,
+    pub api_calls: i64,
+}
+
+impl UsageReport {
+    pub fn get(&self, metric: UsageMetric) -> i64 {
+        match metric {
+            UsageMetric::PostsProcessed => self.posts_processed,
+            UsageMetric::MessagesDelivered => self.messages_delivered,
+            UsageMetric::BytesMirrored => self.bytes_mirrored,
+            UsageMetric::ApiCalls => self.api_calls,
+        }
+    }
+}
+
+// month is "YYYY-MM"; None means the current month
+pub async fn monthly_report(
+    pool: &Pool<Postgres>,
+    month: Option<&str>,
+) -> anyhow::Result<UsageReport> {
+    let month: String = match month {
+        Some(m) => m.to_string(),
+        None => {
+            sqlx::query_scalar("SELECT to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM')")
+                .fetch_one(pool)
+                .await?
+        }
+    };
+    let rows: Vec<(String, i64)> =
+        sqlx::query_as("SELECT metric, value FROM usage_counters WHERE month = $1")
+            .bind(&month)
+            .fetch_all(pool)
+            .await?;
+
+    let mut report = UsageReport {
+        month,
+        ..Default::default()
+    };
+    for (metric, value) in rows {
+        match metric.as_str() {
+            "posts_processed" => report.posts_processed = value,
+            "messages_delivered" => report.messages_delivered = value,
+            "bytes_mirrored" => report.bytes_mirrored = value,
+            "api_calls" => report.api_calls = value,
+            _ => {}
+        }
+    }
+    Ok(report)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UsageQuota {
+    pub posts_processed: Option<i64>,
+    pub messages_delivered: Option<i64>,
+    pub bytes_mirrored: Option<i64>,
+    pub api_calls: Option<i64>,
+}
+
+impl UsageQuota {
+    pub fn limit(&self, metric: UsageMetric) -> Option<i64> {
+        match metric {
+            UsageMetric::PostsProcessed => self.posts_processed,
+            UsageMetric::MessagesDelivered => self.messages_delivered,
+            UsageMetric::BytesMirrored => self.bytes_mirrored,
+            UsageMetric::ApiCalls => self.api_calls,
+        }
+    }
+}
+
+// soft quotas: nothing is blocked, operators just get a warning. Every
+// metric over its limit is returned, but the alert only goes out the first
+// time it crosses the limit in a month.
+pub async fn check_quota(
+    pool: &Pool<Postgres>,
+    tenant: &str,
+    quota: &UsageQuota,
+    topic: &str,
+) -> anyhow::Result<Vec<UsageMetric>> {
+    let report = monthly_report(pool, None).await?;
+    let mut exceeded = Vec::new();
+    for metric in UsageMetric::ALL {
+        let Some(limit) = quota.limit(metric) else {
+            continue;
+        };
+        let used = report.get(metric);
+        if used <= limit {
+            continue;
+        }
+        exceeded.push(metric);
+        let first = sqlx::query(
+            r#"INSERT INTO quota_alerts (month, metric) VALUES ($1, $2)
+               ON CONFLICT DO NOTHING"#,
+        )
+        .bind(&report.month)
+        .bind(metric.as_str())
+        .execute(pool)
+        .await?
+        .rows_affected()
+            == 1;
+        if first {
+            ntfy(
+                &format!(
+                    "{tenant} exceeded {} quota for {}: {used}/{limit}",
+                    metric.as_str(),
+                    report.month
+                ),
+                topic,
+            )
+            .await;
+        }
+    }
+    Ok(exceeded)
+}
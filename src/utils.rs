@@ -1,7 +1,7 @@
-use once_cell::sync::Lazy;
-use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+use crate::notifier::{self, Alerter, Notification};
+
 
 #[derive(Serialize, Deserialize)]
 pub struct InsertDiscordIdRequest {
@@ -13,15 +13,154 @@ pub fn trim_to_n_chars(s: &str, n: usize) -> String {
     s.chars().take(n).collect()
 }
 
+pub struct TruncateOptions<'a> {
+    pub max_chars: usize,
+    pub word_boundary: bool,
+    // never cut inside a link or a code fence
+    pub markdown_safe: bool,
+    pub read_more_url: Option<&'a str>,
+}
+
+impl<'a> TruncateOptions<'a> {
+    pub fn new(max_chars: usize) -> Self {
+        TruncateOptions {
+            max_chars,
+            word_boundary: true,
+            markdown_safe: true,
+            read_more_url: None,
+        }
+    }
+
+    pub fn read_more(mut self, url: &'a str) -> Self {
+        self.read_more_url = Some(url);
+        self
+    }
+}
+
+fn unfinished_link_start(s: &str) -> Option<usize> {
+    let open = s.rfind('[')?;
+    let rest = &s[open..];
+    // a complete [text](url) after the last '[' means nothing is dangling
+    match rest.find("](") {
+        Some(mid) if rest[mid..].contains(')') => None,
+        _ => Some(open),
+    }
+}
+
+const FORMAT_TOKENS: [&str; 6] = ["**", "__", "~~", "||", "*", "_"];
+
+// closes formatting left open by a cut, so one stray `**` can't swallow the embed
+pub fn balance_markdown(s: &str) -> String {
+    let mut open: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+    let mut in_code = false;
+    let mut i = 0;
+    let bytes = s.as_bytes();
+
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with('\\') {
+            i += 1 + rest[1..].chars().next().map_or(0, char::len_utf8);
+            continue;
+        }
+        if rest.starts_with("```") {
+            in_fence = !in_fence;
+            i += 3;
+            continue;
+        }
+        if in_fence {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        }
+        if rest.starts_with('`') {
+            in_code = !in_code;
+            i += 1;
+            continue;
+        }
+        if in_code {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        }
+        let token = FORMAT_TOKENS.iter().find(|t| rest.starts_with(**t));
+        match token {
+            Some(token) => {
+                let before = if i == 0 { b' ' } else { bytes[i - 1] };
+                let after = bytes.get(i + token.len()).copied().unwrap_or(b' ');
+                // "* item" bullets and lone symbols aren't emphasis
+                let standalone = before.is_ascii_whitespace() && after.is_ascii_whitespace();
+                if !standalone {
+                    match open.iter().rposition(|t| t == token) {
+                        Some(pos) => {
+                            open.truncate(pos);
+                        }
+                        None => open.push(token),
+                    }
+                }
+                i += token.len();
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+
+    let mut ret = s.to_string();
+    if in_code {
+        ret.push('`');
+    }
+    if in_fence {
+        if !ret.ends_with('\n') {
+            ret.push('\n');
+        }
+        ret.push_str("```");
+    }
+    for token in open.iter().rev() {
+        ret.push_str(token);
+    }
+    ret
+}
+
+pub fn truncate(s: &str, opts: &TruncateOptions) -> String {
+    if s.chars().count() <= opts.max_chars {
+        return s.to_string();
+    }
+    let suffix = match opts.read_more_url {
+        Some(url) => format!("… [read more]({url})"),
+        None => String::from("…"),
+    };
+    let budget = opts.max_chars.saturating_sub(suffix.chars().count());
+    let end = s.char_indices().nth(budget).map(|(i, _)| i).unwrap_or(s.len());
+    let mut cut = &s[..end];
+
+    if opts.word_boundary {
+        if let Some(space) = cut.rfind(char::is_whitespace) {
+            // don't throw away more than half of the text for a clean break
+            if space > cut.len() / 2 {
+                cut = &cut[..space];
+            }
+        }
+    }
+    if opts.markdown_safe {
+        if cut.matches("```").count() % 2 == 1 {
+            if let Some(fence) = cut.rfind("```") {
+                cut = &cut[..fence];
+            }
+        }
+        if let Some(link) = unfinished_link_start(cut) {
+            cut = &cut[..link];
+        }
+    }
+
+    let mut ret = cut.trim_end().to_string();
+    if opts.markdown_safe {
+        ret = balance_markdown(&ret);
+    }
+    ret.push_str(&suffix);
+    ret
+}
+
+// fire-and-forget through whatever `notifier::init_global` installed
 pub async fn ntfy(message: &str, topic: &str) {
-    static CLIENT: Lazy<Client> = Lazy::new(|| Client::new());
-    let data = message.to_string();
-    let _ = CLIENT
-        .post(format!("https://ntfy.themadseventeen.xyz/{topic}"))
-        .body(data)
-        .header(reqwest::header::CONTENT_TYPE, "application/json")
-        .send()
-        .await;
+    let notification = Notification::new(message).topic(topic);
+    let _ = notifier::global().notify(&notification).await;
 }
 
 // removes all but top level fields
@@ -0,0 +1,222 @@
+use discourse::bundle::PostData;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serenity::all::CreateEmbed;
+
+use crate::{
+    discord::{avatar_url, create_embeds, create_embeds_impersonate},
+    metadata::MetadataCache,
+    theme::EmbedTheme,
+};
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AllowedMentions {
+    pub parse: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+}
+
+impl AllowedMentions {
+    pub fn none() -> Self {
+        Self::default()
+    }
+}
+
+// body for POST /webhooks/{id}/{token}
+#[derive(Serialize, Debug, Clone)]
+pub struct WebhookPayload {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    pub embeds: Vec<Value>,
+    pub allowed_mentions: AllowedMentions,
+}
+
+fn embeds_to_json(embeds: &[CreateEmbed]) -> Vec<Value> {
+    embeds
+        .iter()
+        .filter_map(|e| serde_json::to_value(e).ok())
+        .collect()
+}
+
+pub fn create_webhook_payload(post_data: &PostData, theme: &EmbedTheme) -> Option<WebhookPayload> {
+    let embeds = create_embeds(post_data, theme).ok()?;
+    Some(WebhookPayload {
+        content: None,
+        username: None,
+        avatar_url: None,
+        embeds: embeds_to_json(&embeds),
+        allowed_mentions: AllowedMentions::none(),
+    })
+}
+
+// posts as the forum author instead of the webhook's own identity
+pub fn create_webhook_payload_impersonate(
+    post_data: &PostData,
+    theme: &EmbedTheme,
+) -> Option<WebhookPayload> {
+    Impersonation::new(post_data, theme).build()
+}
+
+pub const WEBHOOK_USERNAME_MAX_CHARS: usize = 80;
+// Discord rejects webhook names containing these, in any case
+const RESERVED_SUBSTRINGS: &[&str] = &["clyde", "discord"];
+const RESERVED_NAMES: &[&str] = &["everyone", "here"];
+
+// a name Discord accepts as a webhook username override, or None if nothing
+// usable is left. Reserved words are broken up with a zero-width space
+// rather than dropped so the name still reads the same.
+pub fn webhook_username(name: &str) -> Option<String> {
+    let mut name: String = name
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    for reserved in RESERVED_SUBSTRINGS {
+        // ASCII lowercasing keeps byte offsets, the words are ASCII
+        while let Some(pos) = name.to_ascii_lowercase().find(reserved) {
+            name.insert(pos + 1, '\u{200b}');
+        }
+    }
+    if RESERVED_NAMES.iter().any(|r| name.eq_ignore_ascii_case(r)) {
+        return None;
+    }
+    let name: String = name.chars().take(WEBHOOK_USERNAME_MAX_CHARS).collect();
+    let name = name.trim().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
+// webhook overrides that make a message look posted by the forum author:
+// username from the theme's author options (or its anonymous identity),
+// avatar from the forum
+pub struct Impersonation<'a> {
+    post_data: &'a PostData,
+    theme: &'a EmbedTheme,
+    // e.g. " (forum)", so mirrored authors aren't mistaken for members
+    suffix: Option<String>,
+    avatar_size: u32,
+}
+
+impl<'a> Impersonation<'a> {
+    pub fn new(post_data: &'a PostData, theme: &'a EmbedTheme) -> Self {
+        Impersonation {
+            post_data,
+            theme,
+            suffix: None,
+            avatar_size: 144,
+        }
+    }
+
+    pub fn with_suffix(mut self, suffix: &str) -> Self {
+        self.suffix = Some(suffix.to_string());
+        self
+    }
+
+    pub fn with_avatar_size(mut self, size: u32) -> Self {
+        self.avatar_size = size;
+        self
+    }
+
+    // the display name, then the username, then a generic name when neither
+    // survives webhook_username
+    pub fn username(&self) -> String {
+        let suffix = self.suffix.as_deref().unwrap_or_default();
+        let name = webhook_username(&self.theme.author_name(self.post_data))
+            .or_else(|| webhook_username(&self.post_data.post.username))
+            .unwrap_or_else(|| String::from("Forum user"));
+        let room = WEBHOOK_USERNAME_MAX_CHARS.saturating_sub(suffix.chars().count());
+        let name: String = name.chars().take(room).collect();
+        webhook_username(&format!("{name}{suffix}")).unwrap_or(name)
+    }
+
+    pub fn avatar_url(&self) -> Option<String> {
+        if let Some(anon) = &self.theme.author.anonymous {
+            return anon.icon_url.clone();
+        }
+        if !self.theme.show_avatars {
+            return None;
+        }
+        Some(avatar_url(
+            &self.post_data.post,
+            &self.post_data.base_url,
+            self.avatar_size,
+        ))
+    }
+
+    pub fn build(&self) -> Option<WebhookPayload> {
+        let embeds =
+            create_embeds_impersonate(self.post_data, &self.post_data.base_url, self.theme);
+        if embeds.is_empty() {
+            return None;
+        }
+        Some(WebhookPayload {
+            content: None,
+            username: Some(self.username()),
+            avatar_url: self.avatar_url(),
+            embeds: embeds_to_json(&embeds),
+            allowed_mentions: AllowedMentions::none(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IdentityOverride {
+    pub username: String,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategoryIdentity {
+    // category name or "Parent › Child" path, subcategories included
+    pub category: String,
+    pub identity: IdentityOverride,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct IdentityRules {
+    pub rules: Vec<CategoryIdentity>,
+}
+
+impl IdentityRules {
+    // first matching rule wins
+    pub fn resolve(
+        &self,
+        post_data: &PostData,
+        cache: Option<&MetadataCache>,
+    ) -> Option<&IdentityOverride> {
+        self.rules
+            .iter()
+            .find(|rule| match cache {
+                Some(cache) => cache.matches_category_path(post_data.category.id, &rule.category),
+                None => post_data.category.name.eq_ignore_ascii_case(&rule.category),
+            })
+            .map(|rule| &rule.identity)
+    }
+}
+
+pub fn apply_identity(payload: &mut WebhookPayload, identity: &IdentityOverride) {
+    payload.username = Some(identity.username.clone());
+    payload.avatar_url = identity.avatar_url.clone();
+}
+
+// impersonates the author unless a category rule pins an official identity
+pub fn create_webhook_payload_routed(
+    post_data: &PostData,
+    theme: &EmbedTheme,
+    rules: &IdentityRules,
+    cache: Option<&MetadataCache>,
+) -> Option<WebhookPayload> {
+    match rules.resolve(post_data, cache) {
+        Some(identity) => {
+            let mut payload = create_webhook_payload(post_data, theme)?;
+            apply_identity(&mut payload, identity);
+            Some(payload)
+        }
+        None => create_webhook_payload_impersonate(post_data, theme),
+    }
+}
@@ -0,0 +1,57 @@
+use discourse::bundle::PostData;
+use reqwest_middleware::ClientWithMiddleware;
+use serde_json::Value;
+
+pub const WIKI_MARKER: &str = "📝";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    NewMessage,
+    // wikis churn, so revisions update the mirrored message instead
+    EditInPlace,
+}
+
+pub fn is_wiki(post_data: &PostData) -> bool {
+    post_data.post.wiki
+}
+
+pub fn sync_mode_for(post_data: &PostData) -> SyncMode {
+    if is_wiki(post_data) {
+        SyncMode::EditInPlace
+    } else {
+        SyncMode::NewMessage
+    }
+}
+
+pub fn last_edited_line(post_data: &PostData, editor: Option<&str>) -> Option<String> {
+    if !is_wiki(post_data) {
+        return None;
+    }
+    let when = post_data.post.updated_at.format("%Y-%m-%d %H:%M UTC");
+    Some(match editor {
+        Some(editor) => format!("{WIKI_MARKER} wiki · last edited by {editor} on {when}"),
+        None => format!("{WIKI_MARKER} wiki · last edited {when}"),
+    })
+}
+
+// the post JSON only carries the editor's ID, the revision has the name
+pub async fn fetch_last_editor(
+    client: &ClientWithMiddleware,
+    base_url: &str,
+    post_id: u64,
+) -> Option<String> {
+    let revision: Value = client
+        .get(format!("{base_url}/posts/{post_id}/revisions/latest.json"))
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    revision
+        .get("username")
+        .and_then(|u| u.as_str())
+        .map(String::from)
+}
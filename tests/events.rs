@@ -0,0 +1,51 @@
+use library::envelope::{SCHEMA_VERSION, deserialize_enveloped};
+use library::error::ForumStreamError;
+use library::events::PostEvent;
+
+// events published before topic_id was added still decode
+#[test]
+fn decodes_legacy_events_without_topic_id() {
+    for (kind, raw) in [
+        ("created", r#"{"type":"created","post_id":7}"#),
+        (
+            "edited",
+            r#"{"schema_version":1,"payload":{"type":"edited","post_id":7}}"#,
+        ),
+        ("recovered", r#"{"type":"recovered","post_id":7}"#),
+    ] {
+        let event: PostEvent = deserialize_enveloped(raw.as_bytes()).unwrap();
+        assert_eq!(event.kind(), kind);
+        match event {
+            PostEvent::Created { topic_id, .. }
+            | PostEvent::Edited { topic_id, .. }
+            | PostEvent::Recovered { topic_id, .. } => assert!(topic_id.is_none()),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn decodes_events_with_topic_id() {
+    let raw = r#"{"schema_version":1,"payload":{"type":"created","post_id":7,"topic_id":3}}"#;
+    let event: PostEvent = deserialize_enveloped(raw.as_bytes()).unwrap();
+    let PostEvent::Created { topic_id, .. } = event else {
+        panic!("unexpected {event:?}");
+    };
+    assert!(topic_id.is_some());
+}
+
+#[test]
+fn rejects_newer_schema_versions() {
+    let raw = format!(
+        r#"{{"schema_version":{},"payload":{{"type":"created","post_id":7}}}}"#,
+        SCHEMA_VERSION + 1
+    );
+    let err = deserialize_enveloped::<PostEvent>(raw.as_bytes()).unwrap_err();
+    assert!(matches!(
+        err,
+        ForumStreamError::UnsupportedSchema {
+            supported: SCHEMA_VERSION,
+            ..
+        }
+    ));
+}
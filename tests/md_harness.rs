@@ -0,0 +1,105 @@
+use library::md::MdOptions;
+use library::md_harness::{check_html, replay_corpus};
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+#[test]
+fn corpus_round_trips() {
+    let results = replay_corpus().expect("read fixtures/md");
+    assert!(!results.is_empty(), "no fixtures in fixtures/md");
+    let failures: Vec<String> = results
+        .iter()
+        .filter(|r| !r.violations.is_empty())
+        .map(|r| {
+            let reasons: Vec<String> = r.violations.iter().map(|v| v.to_string()).collect();
+            format!("{}: {}", r.path.display(), reasons.join(", "))
+        })
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn fuzz_cooked_html() {
+    if let Err(e) = fuzz(256, &MdOptions::default()) {
+        panic!("{e}");
+    }
+}
+
+fn text() -> impl Strategy<Value = String> {
+    // markdown metacharacters on purpose: the output must stay balanced
+    "[a-zA-Z0-9 *_~|`>#@:\\[\\]()\\\\.!-]{0,40}"
+}
+
+fn leaf() -> impl Strategy<Value = String> {
+    prop_oneof![
+        text(),
+        text().prop_map(|t| format!("<strong>{t}</strong>")),
+        text().prop_map(|t| format!("<em>{t}</em>")),
+        text().prop_map(|t| format!("<code>{t}</code>")),
+        text().prop_map(|t| format!("<a href=\"/t/{}/1\">{t}</a>", t.len())),
+        text().prop_map(|t| format!("<img src=\"/uploads/{}.png\" alt=\"{t}\">", t.len())),
+        text().prop_map(|t| format!("<span class=\"math\">${t}$</span>")),
+        Just(String::from(
+            "<sup class=\"footnote-ref\"><a href=\"#footnote-1\">[1]</a></sup>"
+        )),
+        Just(String::from(
+            "<img class=\"emoji\" title=\":smile:\" alt=\":smile:\">"
+        )),
+    ]
+}
+
+// random Discourse-shaped cooked html: quotes, spoilers, lists, tables,
+// code blocks and the like nested a few levels deep
+fn cooked_html() -> impl Strategy<Value = String> {
+    let inline = prop::collection::vec(leaf(), 0..6).prop_map(|parts| parts.join(" "));
+    inline.prop_recursive(4, 64, 6, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|c| format!("<p>{c}</p>")),
+            inner.clone().prop_map(|c| format!("<blockquote>{c}</blockquote>")),
+            (text(), inner.clone()).prop_map(|(user, c)| format!(
+                "<aside class=\"quote\" data-username=\"{user}\" data-topic=\"1\" data-post=\"2\"><div class=\"title\">{user}:</div><blockquote>{c}</blockquote></aside>"
+            )),
+            inner.clone().prop_map(|c| format!(
+                "<details><summary>Spoiler</summary>{c}</details>"
+            )),
+            prop::collection::vec(inner.clone(), 1..4).prop_map(|items| format!(
+                "<ul>{}</ul>",
+                items.iter().map(|i| format!("<li>{i}</li>")).collect::<String>()
+            )),
+            prop::collection::vec(inner.clone(), 1..4).prop_map(|items| format!(
+                "<ol start=\"3\">{}</ol>",
+                items.iter().map(|i| format!("<li>{i}</li>")).collect::<String>()
+            )),
+            text().prop_map(|t| format!("<pre><code>{t}</code></pre>")),
+            (inner.clone(), inner.clone()).prop_map(|(a, b)| format!(
+                "<table><tr><th>{a}</th></tr><tr><td>{b}</td></tr></table>"
+            )),
+            inner.prop_map(|c| format!(
+                "{c}<hr class=\"footnotes-sep\"><ol class=\"footnotes-list\"><li id=\"footnote-1\" class=\"footnote-item\"><p>{c} <a href=\"#footnote-ref-1\" class=\"footnote-backref\">↩︎</a></p></li></ol>"
+            )),
+        ]
+    })
+}
+
+// runs `cases` generated documents through check_html with `options`,
+// returning the first minimized failure
+fn fuzz(cases: u32, options: &MdOptions) -> Result<(), String> {
+    let mut runner = TestRunner::new(Config {
+        cases,
+        ..Config::default()
+    });
+    runner
+        .run(&cooked_html(), |html| {
+            let (md, violations) = check_html(&html, options);
+            if violations.is_empty() {
+                Ok(())
+            } else {
+                let reasons: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                Err(TestCaseError::fail(format!(
+                    "{}\nhtml: {html}\nmarkdown: {md}",
+                    reasons.join(", ")
+                )))
+            }
+        })
+        .map_err(|e| e.to_string())
+}
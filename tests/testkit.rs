@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use discourse::{bundle::PostData, model::TopicId};
+use futures::TryStreamExt;
+use library::flaresolverr_middleware::FlaresolverrMiddleware;
+use library::pipeline::{EmbeddedPipelineConfig, PostSource};
+use library::post_stream::{ForumTopicPager, PostStream};
+use library::testkit::{MockFlaresolverr, mock_discourse_challenged, run_pipeline_with};
+use reqwest::{Client, cookie::Jar};
+use reqwest_middleware::ClientBuilder;
+use serde_json::{Value, json};
+
+// pages one topic through the forum the first time it's polled
+struct TopicSource {
+    pager: Arc<ForumTopicPager>,
+    topic_id: TopicId,
+    done: AtomicBool,
+}
+
+#[async_trait::async_trait]
+impl PostSource for TopicSource {
+    async fn poll(&self) -> anyhow::Result<Vec<PostData>> {
+        if self.done.swap(true, Ordering::AcqRel) {
+            return Ok(Vec::new());
+        }
+        PostStream::new(self.pager.clone(), self.topic_id)
+            .posts()
+            .try_collect()
+            .await
+    }
+}
+
+fn post(id: u64, post_number: u64, cooked: &str) -> Value {
+    json!({
+        "id": id,
+        "name": "Alice",
+        "username": "alice",
+        "display_username": "Alice",
+        "avatar_template": "/letter_avatar_proxy/v4/letter/a/8c91f0/{size}.png",
+        "created_at": "2026-01-02T03:04:05.000Z",
+        "updated_at": "2026-01-02T03:04:05.000Z",
+        "cooked": cooked,
+        "post_number": post_number,
+        "post_type": 1,
+        "reply_count": 0,
+        "reply_to_post_number": null,
+        "quote_count": 0,
+        "incoming_link_count": 0,
+        "reads": 1,
+        "score": 0.0,
+        "topic_id": 42,
+        "topic_slug": "arm-builds",
+        "version": 1,
+        "user_id": 7,
+        "trust_level": 1,
+        "hidden": false,
+        "wiki": false,
+        "deleted_at": null,
+        "user_deleted": false,
+    })
+}
+
+fn topic_fixtures() -> HashMap<String, Value> {
+    let posts = vec![
+        post(101, 1, "<p>Has anyone tried the new build on ARM?</p>"),
+        post(
+            102,
+            2,
+            "<p>Yes, it works with <code>--target aarch64</code>.</p>",
+        ),
+    ];
+    HashMap::from([
+        (
+            String::from("/t/42.json"),
+            json!({
+                "id": 42,
+                "title": "ARM builds",
+                "fancy_title": "ARM builds",
+                "slug": "arm-builds",
+                "posts_count": 2,
+                "reply_count": 1,
+                "created_at": "2026-01-02T03:04:05.000Z",
+                "last_posted_at": "2026-01-02T03:04:05.000Z",
+                "category_id": 5,
+                "tags": [],
+                "archetype": "regular",
+                "visible": true,
+                "closed": false,
+                "archived": false,
+                "views": 3,
+                "like_count": 0,
+                "post_stream": { "stream": [101, 102] },
+            }),
+        ),
+        (
+            String::from("/t/42/posts.json"),
+            json!({ "post_stream": { "posts": posts } }),
+        ),
+        (
+            String::from("/c/5/show.json"),
+            json!({
+                "category": {
+                    "id": 5,
+                    "name": "Support",
+                    "slug": "support",
+                    "color": "0088CC",
+                    "text_color": "FFFFFF",
+                    "description": null,
+                    "parent_category_id": null,
+                }
+            }),
+        ),
+    ])
+}
+
+// mock Discourse -> Cloudflare challenge -> mock FlareSolverr -> pipeline ->
+// capture delivery
+#[tokio::test]
+async fn mirrors_a_challenged_topic() {
+    let flaresolverr = MockFlaresolverr::start().await.unwrap();
+    let forum = mock_discourse_challenged(topic_fixtures()).await.unwrap();
+
+    let jar = Arc::new(Jar::default());
+    let http = Client::builder()
+        .cookie_provider(jar.clone())
+        .build()
+        .unwrap();
+    let middleware = FlaresolverrMiddleware::new(Client::new(), jar, flaresolverr.url())
+        .await
+        .unwrap();
+    let client = ClientBuilder::new(http).with(middleware).build();
+
+    let source = TopicSource {
+        pager: Arc::new(ForumTopicPager::new(client, &forum.base_url())),
+        topic_id: serde_json::from_value(json!(42)).unwrap(),
+        done: AtomicBool::new(false),
+    };
+    let rendered = run_pipeline_with(
+        Arc::new(source),
+        EmbeddedPipelineConfig::default(),
+        2,
+        Duration::from_secs(10),
+    )
+    .await;
+
+    let ids: Vec<String> = rendered.iter().map(|r| r.post_id.to_string()).collect();
+    assert_eq!(ids, ["101", "102"]);
+    assert!(rendered.iter().all(|r| !r.embeds.is_empty()));
+    // the first request was challenged, the clearance carried the rest
+    assert_eq!(flaresolverr.solves(), 1);
+}